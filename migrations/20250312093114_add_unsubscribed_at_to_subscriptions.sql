@@ -0,0 +1 @@
+ALTER TABLE subscriptions ADD COLUMN unsubscribed_at timestamptz NULL;
@@ -0,0 +1,8 @@
+CREATE TABLE failed_deliveries (
+    newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues(id),
+    subscriber_email TEXT NOT NULL,
+    n_retries INTEGER NOT NULL,
+    last_error TEXT NOT NULL,
+    failed_at timestamptz NOT NULL,
+    PRIMARY KEY(newsletter_issue_id, subscriber_email)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE newsletter_issues
+    ADD COLUMN n_delivered INTEGER NOT NULL DEFAULT 0;
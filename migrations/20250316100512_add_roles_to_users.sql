@@ -0,0 +1,6 @@
+-- Existing users keep full access.
+ALTER TABLE users
+    ADD COLUMN role TEXT NOT NULL DEFAULT 'admin'
+        CHECK (role IN ('admin', 'editor', 'viewer')),
+    ADD COLUMN email TEXT NULL UNIQUE;
+ALTER TABLE users ALTER COLUMN role DROP DEFAULT;
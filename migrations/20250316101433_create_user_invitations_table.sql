@@ -0,0 +1,8 @@
+CREATE TABLE user_invitations (
+    token TEXT NOT NULL PRIMARY KEY,
+    email TEXT NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('admin', 'editor', 'viewer')),
+    invited_by uuid NOT NULL REFERENCES users (id),
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL
+);
@@ -0,0 +1,7 @@
+ALTER TABLE users ADD COLUMN totp_secret TEXT NULL;
+
+CREATE TABLE recovery_codes (
+    user_id uuid NOT NULL REFERENCES users (id),
+    code_hash TEXT NOT NULL,
+    PRIMARY KEY (user_id, code_hash)
+);
@@ -0,0 +1,9 @@
+-- Bumped whenever all the sessions of a user must be invalidated
+ALTER TABLE users ADD COLUMN session_epoch INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE password_reset_tokens (
+    token TEXT NOT NULL PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (id),
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL
+);
@@ -0,0 +1,7 @@
+ALTER TABLE subscription_tokens
+    ADD COLUMN created_at timestamptz NOT NULL DEFAULT now(),
+    ADD COLUMN expires_at timestamptz NOT NULL DEFAULT now() + interval '2 days';
+
+ALTER TABLE subscription_tokens
+    ALTER COLUMN created_at DROP DEFAULT,
+    ALTER COLUMN expires_at DROP DEFAULT;
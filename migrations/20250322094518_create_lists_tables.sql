@@ -0,0 +1,24 @@
+CREATE TABLE lists (
+    id uuid NOT NULL,
+    PRIMARY KEY (id),
+    name TEXT NOT NULL UNIQUE,
+    description TEXT NOT NULL,
+    created_at timestamptz NOT NULL
+);
+
+CREATE TABLE subscription_lists (
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    list_id uuid NOT NULL
+        REFERENCES lists (id) ON DELETE CASCADE,
+    PRIMARY KEY (subscriber_id, list_id)
+);
+
+-- Issues without any row here go out to every confirmed subscriber.
+CREATE TABLE newsletter_issue_lists (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (id) ON DELETE CASCADE,
+    list_id uuid NOT NULL
+        REFERENCES lists (id) ON DELETE CASCADE,
+    PRIMARY KEY (newsletter_issue_id, list_id)
+);
@@ -0,0 +1,14 @@
+ALTER TABLE subscriptions
+    ADD COLUMN attributes JSONB NOT NULL DEFAULT '{}';
+
+CREATE TABLE segments (
+    id uuid NOT NULL,
+    PRIMARY KEY (id),
+    name TEXT NOT NULL UNIQUE,
+    -- The rules of `domain::Segment`, serialised as JSON.
+    definition JSONB NOT NULL,
+    created_at timestamptz NOT NULL
+);
+
+ALTER TABLE newsletter_issues
+    ADD COLUMN segment_id uuid NULL REFERENCES segments (id);
@@ -0,0 +1,21 @@
+ALTER TABLE newsletter_issues
+    ADD COLUMN tracking_enabled BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE tracking_events (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (id) ON DELETE CASCADE,
+    -- The history of deleted subscribers goes with them.
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    -- Either 'open' or 'click'.
+    kind TEXT NOT NULL,
+    -- The link that was followed, for clicks.
+    url TEXT NULL,
+    occurred_at timestamptz NOT NULL
+);
+
+CREATE INDEX tracking_events_issue_kind_idx ON tracking_events (newsletter_issue_id, kind);
+-- Only the first open of an issue, and the first click on each of its links, is kept.
+CREATE UNIQUE INDEX tracking_events_unique_event_idx
+    ON tracking_events (newsletter_issue_id, subscriber_id, kind, COALESCE(url, ''));
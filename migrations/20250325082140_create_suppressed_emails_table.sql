@@ -0,0 +1,9 @@
+-- Addresses we must not email anymore, as reported by the email provider.
+-- Kept apart from `subscriptions` so that they outlive the removal of the subscriber.
+CREATE TABLE suppressed_emails (
+    email TEXT NOT NULL,
+    PRIMARY KEY (email),
+    -- Either 'bounced' or 'complained'.
+    reason TEXT NOT NULL,
+    suppressed_at timestamptz NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE issue_delivery_queue
+    ADD COLUMN created_at timestamptz NOT NULL DEFAULT now();
@@ -0,0 +1,8 @@
+-- Heartbeats are recorded per process, so that each one only reports on the workers it runs.
+CREATE TABLE worker_heartbeats (
+    worker TEXT NOT NULL,
+    -- The `workers::INSTANCE_ID` of the process running the worker.
+    instance uuid NOT NULL,
+    PRIMARY KEY (worker, instance),
+    beat_at timestamptz NOT NULL
+);
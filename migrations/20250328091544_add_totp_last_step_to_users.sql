@@ -0,0 +1,2 @@
+-- The time step of the last code accepted, so that a code cannot be replayed
+ALTER TABLE users ADD COLUMN totp_last_step BIGINT NULL;
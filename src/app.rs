@@ -1,28 +1,55 @@
-use crate::{auth::reject_anonymous_users, config::Settings, email_client::EmailClient, routes::*};
-use actix_session::{
-    storage::{RedisSessionStore, SessionStore},
-    SessionMiddleware,
+use crate::{
+    auth::{reject_anonymous_users, reject_invalid_api_keys, LoginThrottle},
+    config::{HealthSettings, Settings},
+    email_client::EmailTransport,
+    metrics::record_http_metrics,
+    routes::*,
 };
+use actix_session::{storage::RedisSessionStore, SessionMiddleware};
 use actix_web::{
-    cookie::Key, dev::Server, middleware::from_fn as mw_fn, web, web::Data, HttpServer,
+    cookie::Key,
+    dev::{Server, ServerHandle},
+    middleware::from_fn as mw_fn,
+    web,
+    web::Data,
+    HttpServer,
 };
 use actix_web_flash_messages::{storage::CookieMessageStore, FlashMessagesFramework};
 use core::net::SocketAddr;
 use secrecy::{ExposeSecret, SecretString};
 use sqlx::PgPool;
-use std::net::TcpListener;
+use std::{
+    net::{IpAddr, TcpListener},
+    sync::Arc,
+    time::Duration,
+};
 use tracing_actix_web::TracingLogger;
 
 #[derive(Clone)]
 pub struct HmacSecret(pub SecretString);
 
+/// The reverse proxies allowed to tell the client's address through `X-Forwarded-For`.
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+/// The background workers running in this process, which the readiness probe checks on.
+pub struct LocalWorkers(pub Vec<&'static str>);
+
+/// The token the metrics scraper authenticates with.
+pub struct MetricsToken(pub SecretString);
+
+/// The credentials the email provider authenticates its webhook requests with.
+pub struct WebhookCredentials {
+    pub username: String,
+    pub password: SecretString,
+}
+
 pub struct App {
     server: Server,
     socket_addr: SocketAddr,
 }
 
 impl App {
-    pub async fn build(config: &Settings) -> anyhow::Result<Self> {
+    pub async fn build(config: &Settings, local_workers: &[&'static str]) -> anyhow::Result<Self> {
         // create the app dependencies
         let listener =
             TcpListener::bind((config.application.host.clone(), config.application.port))?;
@@ -31,7 +58,22 @@ impl App {
         let email_client = config.email_client.client();
         let base_url = AppBaseUrl(config.application.base_url.clone());
         let hmac_secret = config.application.hmac_secret.clone();
+        let webhook_credentials = WebhookCredentials {
+            username: config.email_client.postmark.webhook_username.clone(),
+            password: config.email_client.postmark.webhook_password.clone(),
+        };
+        let metrics_token = MetricsToken(config.metrics.bearer_token.clone());
+        let health_settings = config.health.clone();
+        let shutdown_timeout = config.application.shutdown_timeout();
         let session_store = RedisSessionStore::new(config.redis_uri.expose_secret()).await?;
+        let login_throttle = LoginThrottle::new(
+            &config.redis_uri,
+            config.login_throttle.policy(),
+            config.login_throttle.key_prefix.clone(),
+        )
+        .await?;
+        let trusted_proxies = TrustedProxies(config.application.trusted_proxies.clone());
+        let local_workers = LocalWorkers(local_workers.to_vec());
 
         // create the app runner
         let server = Self::get_server_runner(
@@ -40,7 +82,14 @@ impl App {
             email_client,
             base_url,
             hmac_secret,
+            webhook_credentials,
+            metrics_token,
+            health_settings,
+            local_workers,
             session_store,
+            login_throttle,
+            trusted_proxies,
+            shutdown_timeout,
         )?;
 
         Ok(Self {
@@ -49,16 +98,59 @@ impl App {
         })
     }
 
+    /// Serves only the health probes and the metrics, on the metrics port, for processes
+    /// that run workers without the API.
+    pub fn build_monitoring(
+        config: &Settings,
+        local_workers: &[&'static str],
+    ) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind((config.application.host.clone(), config.metrics.port))?;
+        let socket_addr = listener.local_addr().unwrap();
+        let db_pool = Data::new(config.database.get_db_pool());
+        let metrics_token = Data::new(MetricsToken(config.metrics.bearer_token.clone()));
+        let health_settings = Data::new(config.health.clone());
+        let local_workers = Data::new(LocalWorkers(local_workers.to_vec()));
+        let server = HttpServer::new(move || {
+            actix_web::App::new()
+                .wrap(TracingLogger::default())
+                .wrap(mw_fn(record_http_metrics))
+                .service(health_check)
+                .service(readiness)
+                .service(prometheus_metrics)
+                .app_data(Data::clone(&db_pool))
+                .app_data(Data::clone(&metrics_token))
+                .app_data(Data::clone(&health_settings))
+                .app_data(Data::clone(&local_workers))
+        })
+        .listen(listener)?
+        .disable_signals()
+        .shutdown_timeout(config.application.shutdown_timeout().as_secs())
+        .run();
+
+        Ok(Self {
+            server,
+            socket_addr,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn get_server_runner(
         listener: TcpListener,
         db_pool: PgPool,
-        email_client: EmailClient,
+        email_client: Arc<dyn EmailTransport>,
         base_url: AppBaseUrl,
         hmac_secret: SecretString,
-        session_store: impl SessionStore + Send + Clone + 'static,
+        webhook_credentials: WebhookCredentials,
+        metrics_token: MetricsToken,
+        health_settings: HealthSettings,
+        local_workers: LocalWorkers,
+        session_store: RedisSessionStore,
+        login_throttle: LoginThrottle,
+        trusted_proxies: TrustedProxies,
+        shutdown_timeout: Duration,
     ) -> anyhow::Result<Server> {
         let db_pool = Data::new(db_pool);
-        let email_client = Data::new(email_client);
+        let email_client = Data::from(email_client);
         let base_url = Data::new(base_url);
         let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
         let message_framework = {
@@ -66,6 +158,13 @@ impl App {
             FlashMessagesFramework::builder(store).build()
         };
         let hmac_secret = Data::new(HmacSecret(hmac_secret));
+        let login_throttle = Data::new(login_throttle);
+        let trusted_proxies = Data::new(trusted_proxies);
+        let webhook_credentials = Data::new(webhook_credentials);
+        let metrics_token = Data::new(metrics_token);
+        let health_settings = Data::new(health_settings);
+        let local_workers = Data::new(local_workers);
+        let session_store_data = Data::new(session_store.clone());
         let server = HttpServer::new(move || {
             actix_web::App::new()
                 .wrap(message_framework.clone())
@@ -74,18 +173,78 @@ impl App {
                     secret_key.clone(),
                 ))
                 .wrap(TracingLogger::default())
+                .wrap(mw_fn(record_http_metrics))
                 .service(health_check)
+                .service(readiness)
+                .service(prometheus_metrics)
                 .service(subscribe)
                 .service(confirm)
+                .service(resend_confirmation)
+                .service(unsubscribe_form)
+                .service(unsubscribe)
+                .service(preferences_form)
+                .service(save_preferences)
+                .service(track_open)
+                .service(track_click)
+                .service(postmark_webhook)
+                .service(web_issue)
                 .service(home)
                 .service(login_form)
                 .service(login)
+                .service(second_factor_form)
+                .service(verify_second_factor)
+                .service(password_reset_form)
+                .service(request_password_reset)
+                .service(new_password_form)
+                .service(reset_password)
+                .service(accept_invitation_form)
+                .service(accept_invitation)
+                .service(
+                    web::scope("/api/v1")
+                        .wrap(mw_fn(reject_invalid_api_keys))
+                        .configure(api_extractor_configs)
+                        .service(list_subscribers)
+                        .service(get_subscriber)
+                        .service(create_subscriber)
+                        .service(confirm_subscriber)
+                        .service(unsubscribe_subscriber)
+                        .service(set_subscriber_attributes)
+                        .service(delete_subscriber)
+                        .service(create_issue)
+                        .service(get_issue_status),
+                )
                 .service(
                     web::scope("/admin")
                         .wrap(mw_fn(reject_anonymous_users))
                         .service(admin_dashboard)
                         .service(newsletters_form)
                         .service(publish_newsletter)
+                        .service(list_issues)
+                        .service(issue_details)
+                        .service(preview_issue)
+                        .service(send_test_issue)
+                        .service(publish_draft)
+                        .service(reschedule_issue)
+                        .service(cancel_issue)
+                        .service(failed_deliveries)
+                        .service(requeue_failed_deliveries)
+                        .service(subscribers)
+                        .service(import_subscribers)
+                        .service(export_subscribers)
+                        .service(lists)
+                        .service(create_list)
+                        .service(segments)
+                        .service(create_segment)
+                        .service(manage_users)
+                        .service(invite_user)
+                        .service(change_role)
+                        .service(api_keys)
+                        .service(create_api_key)
+                        .service(revoke_api_key)
+                        .service(security)
+                        .service(enable_two_factor)
+                        .service(disable_two_factor)
+                        .service(set_email)
                         .service(change_password)
                         .service(change_password_form)
                         .service(logout),
@@ -94,13 +253,28 @@ impl App {
                 .app_data(Data::clone(&email_client))
                 .app_data(Data::clone(&base_url))
                 .app_data(Data::clone(&hmac_secret))
+                .app_data(Data::clone(&login_throttle))
+                .app_data(Data::clone(&trusted_proxies))
+                .app_data(Data::clone(&webhook_credentials))
+                .app_data(Data::clone(&metrics_token))
+                .app_data(Data::clone(&health_settings))
+                .app_data(Data::clone(&local_workers))
+                .app_data(Data::clone(&session_store_data))
         })
         .listen(listener)?
+        // Shutdown is driven by the caller through `handle`
+        .disable_signals()
+        .shutdown_timeout(shutdown_timeout.as_secs())
         .run();
 
         Ok(server)
     }
 
+    /// Lets the server be stopped, gracefully or not, while it runs.
+    pub fn handle(&self) -> ServerHandle {
+        self.server.handle()
+    }
+
     pub fn addr(&self) -> SocketAddr {
         self.socket_addr
     }
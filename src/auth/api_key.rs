@@ -0,0 +1,95 @@
+use crate::domain::UserRole;
+use anyhow::Context;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64, Engine};
+use rand::{distributions, Rng};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+const API_KEY_LEN: usize = 40;
+
+/// The key a request to the JSON API has been authenticated with.
+/// Requests act on behalf of the user who created the key.
+#[derive(Clone, Copy, Debug)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub created_by: Uuid,
+}
+
+/// Stores a new API key and returns it. Only its hash is kept, so it cannot be shown again.
+#[tracing::instrument(name = "Create API key", skip(executor))]
+pub async fn create_api_key(
+    name: &str,
+    created_by: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> anyhow::Result<SecretString> {
+    let key: String = rand::thread_rng()
+        .sample_iter(distributions::Alphanumeric)
+        .map(char::from)
+        .take(API_KEY_LEN)
+        .collect();
+
+    sqlx::query!(
+        r#"
+    INSERT INTO api_keys (id, name, key_hash, created_by, created_at)
+    VALUES ($1, $2, $3, $4, now())
+    "#,
+        Uuid::new_v4(),
+        name,
+        hash_api_key(&key),
+        created_by
+    )
+    .execute(executor)
+    .await
+    .context("Failed to store the API key.")?;
+
+    Ok(SecretString::from(key))
+}
+
+/// Returns whether an active key has been revoked.
+#[tracing::instrument(name = "Revoke API key", skip(executor))]
+pub async fn revoke_api_key(key_id: Uuid, executor: impl PgExecutor<'_>) -> anyhow::Result<bool> {
+    let r = sqlx::query!(
+        "UPDATE api_keys SET revoked_at = now() WHERE id = $1 AND revoked_at IS NULL",
+        key_id
+    )
+    .execute(executor)
+    .await
+    .context("Failed to revoke the API key.")?;
+    Ok(r.rows_affected() == 1)
+}
+
+/// Returns the key if it exists, has not been revoked and the user who created it is
+/// still an admin, recording that it has been used.
+#[tracing::instrument(name = "Validate API key", skip_all)]
+pub async fn validate_api_key(
+    key: &SecretString,
+    executor: impl PgExecutor<'_>,
+) -> anyhow::Result<Option<ApiKey>> {
+    let r = sqlx::query!(
+        r#"
+    UPDATE api_keys k
+    SET last_used_at = now()
+    FROM users u
+    WHERE k.key_hash = $1 AND k.revoked_at IS NULL
+        AND u.id = k.created_by AND u.role = $2
+    RETURNING k.id, k.created_by
+    "#,
+        hash_api_key(key.expose_secret()),
+        UserRole::Admin.as_str()
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to perform a query to validate the API key.")?;
+
+    Ok(r.map(|r| ApiKey {
+        id: r.id,
+        created_by: r.created_by,
+    }))
+}
+
+/// API keys are random enough for a fast hash to be sufficient.
+fn hash_api_key(key: &str) -> String {
+    BASE64.encode(Sha256::digest(key.as_bytes()))
+}
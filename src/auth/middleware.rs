@@ -1,11 +1,16 @@
-use crate::{session_state::Session, utils};
+use super::api_key::validate_api_key;
+use crate::{domain::UserRole, routes::ApiError, session_state::Session, utils};
 use actix_web::middleware::Next;
 use actix_web::{
     body::MessageBody,
     dev::{ServiceRequest, ServiceResponse},
     error::InternalError,
-    FromRequest, HttpMessage,
+    http::header,
+    web, FromRequest, HttpMessage, HttpResponse,
 };
+use anyhow::Context;
+use secrecy::SecretString;
+use sqlx::{PgExecutor, PgPool};
 use std::{fmt::Display, ops::Deref};
 use uuid::Uuid;
 
@@ -35,15 +40,137 @@ pub async fn reject_anonymous_users(
         Session::from_request(req, payload).await
     }?;
 
-    match session.user_id().get().map_err(utils::e500)? {
-        Some(user_id) => {
-            req.extensions_mut().insert(UserId(user_id));
-            next.call(req).await
-        }
-        None => {
-            let resp = utils::see_other("/login");
-            let e = anyhow::anyhow!("The user has not logged in");
-            Err(InternalError::from_response(e, resp).into())
-        }
+    let Some(user_id) = session.user_id().get().map_err(utils::e500)? else {
+        let resp = utils::see_other("/login");
+        let e = anyhow::anyhow!("The user has not logged in");
+        return Err(InternalError::from_response(e, resp).into());
+    };
+
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or_else(|| utils::e500("The database pool is missing"))?;
+    let current_epoch = get_session_epoch(user_id, pool.as_ref())
+        .await
+        .map_err(utils::e500)?;
+    let session_epoch = session.session_epoch().get().map_err(utils::e500)?;
+    if current_epoch != Some(session_epoch.unwrap_or_default()) {
+        session.logout();
+        let resp = utils::see_other("/login");
+        let e = anyhow::anyhow!("The session has been invalidated");
+        return Err(InternalError::from_response(e, resp).into());
     }
+
+    req.extensions_mut().insert(UserId(user_id));
+    next.call(req).await
+}
+
+/// Authenticates requests to the JSON API with an `Authorization: Bearer <API key>` header.
+pub async fn reject_invalid_api_keys(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(key) = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(|k| SecretString::from(k.trim()))
+    else {
+        return Err(ApiError::Unauthorized("An API key is required.".into()).into());
+    };
+    let pool = req.app_data::<web::Data<PgPool>>().ok_or_else(|| {
+        ApiError::UnexpectedError(anyhow::anyhow!("The database pool is missing"))
+    })?;
+    let api_key = validate_api_key(&key, pool.as_ref())
+        .await
+        .map_err(ApiError::UnexpectedError)?
+        .ok_or_else(|| {
+            ApiError::Unauthorized(
+                "The API key is invalid, has been revoked or belongs to a former admin.".into(),
+            )
+        })?;
+
+    req.extensions_mut().insert(api_key);
+    next.call(req).await
+}
+
+/// Only lets editors and admins through. Must be wrapped by `reject_anonymous_users`.
+pub async fn reject_non_editors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    reject_users_below(UserRole::Editor, req, next).await
+}
+
+/// Only lets admins through. Must be wrapped by `reject_anonymous_users`.
+pub async fn reject_non_admins(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    reject_users_below(UserRole::Admin, req, next).await
+}
+
+async fn reject_users_below(
+    required: UserRole,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let user_id = *req
+        .extensions()
+        .get::<UserId>()
+        .ok_or_else(|| utils::e500("The user has not been authenticated"))?;
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or_else(|| utils::e500("The database pool is missing"))?;
+    let role = get_role(*user_id, pool.as_ref())
+        .await
+        .map_err(utils::e500)?;
+
+    if role < required {
+        let e = anyhow::anyhow!(
+            "The user is a(n) {} but this requires a(n) {}",
+            role,
+            required
+        );
+        return Err(InternalError::from_response(e, HttpResponse::Forbidden().finish()).into());
+    }
+    next.call(req).await
+}
+
+#[tracing::instrument(name = "Get user role", skip(executor))]
+pub async fn get_role(user_id: Uuid, executor: impl PgExecutor<'_>) -> anyhow::Result<UserRole> {
+    let r = sqlx::query!(
+        r#"
+    SELECT role
+    FROM users
+    WHERE id = $1
+    "#,
+        user_id
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to perform a query to retrieve a user's role.")?;
+
+    UserRole::parse(&r.role).map_err(anyhow::Error::msg)
+}
+
+/// Returns the current session epoch of the user, or `None` if the user no longer exists.
+#[tracing::instrument(name = "Get session epoch", skip(executor))]
+pub async fn get_session_epoch(
+    user_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> anyhow::Result<Option<i32>> {
+    let r = sqlx::query!(
+        r#"
+    SELECT session_epoch
+    FROM users
+    WHERE id = $1
+    "#,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to perform a query to retrieve a user's session epoch.")?;
+
+    Ok(r.map(|r| r.session_epoch))
 }
@@ -1,5 +1,20 @@
+mod api_key;
 mod middleware;
 mod password;
+mod throttle;
+mod totp;
 
-pub use middleware::{reject_anonymous_users, UserId};
-pub use password::{change_password, validate_credentials, AuthError, Credentials};
+pub use api_key::{create_api_key, revoke_api_key, validate_api_key, ApiKey};
+pub use middleware::{
+    get_role, get_session_epoch, reject_anonymous_users, reject_invalid_api_keys,
+    reject_non_admins, reject_non_editors, UserId,
+};
+pub use password::{
+    change_email, change_password, create_user, invalidate_sessions, validate_credentials,
+    AuthError, ChangeEmailError, CreateUserError, Credentials, NewUser,
+};
+pub use throttle::{lockout_message, lockout_secs, LoginThrottle, ThrottleKey, ThrottlePolicy};
+pub use totp::{
+    disable_totp, enable_totp, generate_totp_secret, get_totp_secret, otpauth_uri,
+    use_recovery_code, use_totp_code, verify_totp_code,
+};
@@ -1,4 +1,8 @@
-use crate::{domain::ValidPassword, telemetry, utils};
+use crate::{
+    domain::{SubscriberEmail, UserRole, ValidPassword},
+    metrics::PASSWORD_VERIFICATION_DURATION,
+    telemetry, utils,
+};
 use anyhow::Context;
 use argon2::{
     password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
@@ -54,6 +58,7 @@ fn verify_password_hash(expected: SecretString, candidate: SecretString) -> Resu
     let expected = PasswordHash::new(expected.expose_secret())
         .context("Failed to parse hash in PHC string format.")?;
 
+    let _timer = PASSWORD_VERIFICATION_DURATION.start_timer();
     Argon2::default()
         .verify_password(candidate.expose_secret().as_bytes(), &expected)
         .context("Invalid password.")
@@ -107,6 +112,135 @@ pub async fn change_password(
     Ok(())
 }
 
+/// Logs the user out everywhere, by making the epoch stored in their sessions stale.
+#[tracing::instrument(name = "Invalidate sessions", skip(executor))]
+pub async fn invalidate_sessions(
+    user_id: Uuid,
+    executor: impl '_ + PgExecutor<'_>,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+    UPDATE users
+    SET session_epoch = session_epoch + 1
+    WHERE id = $1
+    "#,
+        user_id
+    )
+    .execute(executor)
+    .await
+    .context("Failed to invalidate the user's sessions.")?;
+
+    Ok(())
+}
+
+#[derive(thiserror::Error)]
+pub enum CreateUserError {
+    #[error("This username is already taken.")]
+    UsernameTaken,
+    #[error("There already is an account with this email.")]
+    EmailTaken,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for CreateUserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        utils::error_chain_fmt(self, f)
+    }
+}
+
+pub struct NewUser {
+    pub username: String,
+    pub email: String,
+    pub role: UserRole,
+    pub password: ValidPassword,
+}
+
+/// Stores a new user, unless their username or email is already taken.
+#[tracing::instrument(name = "Create user", skip_all, fields(username = %user.username))]
+pub async fn create_user(
+    user: NewUser,
+    executor: impl '_ + PgExecutor<'_>,
+) -> Result<Uuid, CreateUserError> {
+    let password = user.password;
+    let password_hash = telemetry::spawn_blocking_with_tracing(|| compute_password_hash(password))
+        .await
+        .context("Failed to spawn blocking task.")?
+        .context("Failed to hash password")?;
+
+    let r = sqlx::query!(
+        r#"
+    INSERT INTO users (id, username, email, role, password_hash)
+    VALUES ($1, $2, $3, $4, $5)
+    RETURNING id
+    "#,
+        Uuid::new_v4(),
+        user.username,
+        user.email,
+        user.role.as_str(),
+        password_hash.expose_secret(),
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|e| {
+        let constraint = e.as_database_error().and_then(|e| e.constraint());
+        match constraint {
+            Some("users_username_key") => CreateUserError::UsernameTaken,
+            Some("users_email_key") => CreateUserError::EmailTaken,
+            _ => anyhow::Error::new(e)
+                .context("Failed to store the new user in the database.")
+                .into(),
+        }
+    })?;
+
+    Ok(r.id)
+}
+
+#[derive(thiserror::Error)]
+pub enum ChangeEmailError {
+    #[error("There already is an account with this email.")]
+    EmailTaken,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for ChangeEmailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        utils::error_chain_fmt(self, f)
+    }
+}
+
+/// Sets the address that password reset links are sent to.
+#[tracing::instrument(name = "Change email", skip(email, executor))]
+pub async fn change_email(
+    user_id: Uuid,
+    email: &SubscriberEmail,
+    executor: impl '_ + PgExecutor<'_>,
+) -> Result<(), ChangeEmailError> {
+    sqlx::query!(
+        r#"
+    UPDATE users
+    SET email = $1
+    WHERE id = $2
+    "#,
+        email.as_ref(),
+        user_id
+    )
+    .execute(executor)
+    .await
+    .map_err(|e| {
+        let constraint = e.as_database_error().and_then(|e| e.constraint());
+        match constraint {
+            Some("users_email_key") => ChangeEmailError::EmailTaken,
+            _ => anyhow::Error::new(e)
+                .context("Failed to change user's email in the database.")
+                .into(),
+        }
+    })?;
+
+    Ok(())
+}
+
 fn compute_password_hash(password: ValidPassword) -> anyhow::Result<SecretString> {
     let salt = SaltString::generate(rand::thread_rng());
 
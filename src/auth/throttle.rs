@@ -0,0 +1,211 @@
+use anyhow::Context;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use secrecy::{ExposeSecret, SecretString};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Failure counters are forgotten after a day without failures.
+const FAILURES_TTL_SECS: i64 = 24 * 60 * 60;
+
+/// What failed login attempts are counted against.
+#[derive(Debug)]
+pub enum ThrottleKey<'a> {
+    Username(&'a str),
+    Ip(&'a str),
+    SecondFactor(Uuid),
+    /// Password reset requests, which send an email each, are limited per address and per IP.
+    PasswordReset(&'a str),
+    PasswordResetIp(&'a str),
+}
+
+impl ThrottleKey<'_> {
+    fn name(&self) -> String {
+        match self {
+            // Case variations of a username must not get a fresh set of attempts
+            Self::Username(u) => format!("username:{}", u.to_lowercase()),
+            Self::Ip(ip) => format!("ip:{}", ip),
+            Self::SecondFactor(id) => format!("second_factor:{}", id),
+            Self::PasswordReset(email) => format!("password_reset:{}", email.to_lowercase()),
+            Self::PasswordResetIp(ip) => format!("password_reset_ip:{}", ip),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ThrottlePolicy {
+    pub max_attempts_per_user: u32,
+    pub max_attempts_per_ip: u32,
+    pub initial_lockout: Duration,
+    pub max_lockout: Duration,
+}
+
+impl ThrottlePolicy {
+    /// How long to lock the key out for after its `n_failures`-th consecutive failure.
+    /// The lockout doubles with every failure past the allowed attempts.
+    pub fn lockout(&self, key: &ThrottleKey<'_>, n_failures: u32) -> Option<Duration> {
+        let max_attempts = match key {
+            ThrottleKey::Ip(_) | ThrottleKey::PasswordResetIp(_) => self.max_attempts_per_ip,
+            _ => self.max_attempts_per_user,
+        };
+        let n_excess = n_failures.checked_sub(max_attempts)?.checked_sub(1)?;
+        let factor = 2u32.saturating_pow(n_excess);
+        Some(
+            self.initial_lockout
+                .saturating_mul(factor)
+                .min(self.max_lockout),
+        )
+    }
+}
+
+/// Counts failed login attempts in Redis and locks out the usernames and IPs
+/// they come from, so that passwords cannot be guessed at will.
+#[derive(Clone)]
+pub struct LoginThrottle {
+    redis: ConnectionManager,
+    policy: ThrottlePolicy,
+    /// Prepended to every Redis key, to keep apps sharing a Redis instance apart.
+    key_prefix: String,
+}
+
+impl LoginThrottle {
+    pub async fn new(
+        redis_uri: &SecretString,
+        policy: ThrottlePolicy,
+        key_prefix: String,
+    ) -> anyhow::Result<Self> {
+        let client = redis::Client::open(redis_uri.expose_secret())
+            .context("Failed to parse the Redis URI.")?;
+        let redis = ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to Redis.")?;
+        Ok(Self {
+            redis,
+            policy,
+            key_prefix,
+        })
+    }
+
+    /// Returns how long the longest lockout among the keys still lasts, if any.
+    #[tracing::instrument(name = "Check login lockout", skip(self))]
+    pub async fn lockout(&self, keys: &[ThrottleKey<'_>]) -> anyhow::Result<Option<Duration>> {
+        let mut redis = self.redis.clone();
+        let mut lockout = None;
+        for key in keys {
+            let ttl_ms: i64 = redis
+                .pttl(self.lockout_key(key))
+                .await
+                .context("Failed to read a login lockout.")?;
+            if ttl_ms > 0 {
+                lockout = lockout.max(Some(Duration::from_millis(ttl_ms as u64)));
+            }
+        }
+        Ok(lockout)
+    }
+
+    /// Counts a failed attempt against every key, returning the lockout it caused, if any.
+    #[tracing::instrument(name = "Record login failure", skip(self))]
+    pub async fn record_failure(
+        &self,
+        keys: &[ThrottleKey<'_>],
+    ) -> anyhow::Result<Option<Duration>> {
+        let mut redis = self.redis.clone();
+        let mut lockout = None;
+        for key in keys {
+            let failures_key = self.failures_key(key);
+            // Atomically, lest a counter without an expiry lock the key out for good
+            let (n_failures,): (u32,) = redis::pipe()
+                .atomic()
+                .incr(&failures_key, 1)
+                .expire(&failures_key, FAILURES_TTL_SECS)
+                .ignore()
+                .query_async(&mut redis)
+                .await
+                .context("Failed to count a login failure.")?;
+
+            if let Some(duration) = self.policy.lockout(key, n_failures) {
+                let _: () = redis
+                    .pset_ex(self.lockout_key(key), 1, duration.as_millis() as u64)
+                    .await
+                    .context("Failed to lock out a login.")?;
+                lockout = lockout.max(Some(duration));
+            }
+        }
+        Ok(lockout)
+    }
+
+    #[tracing::instrument(name = "Reset login failures", skip(self))]
+    pub async fn reset(&self, keys: &[ThrottleKey<'_>]) -> anyhow::Result<()> {
+        let mut redis = self.redis.clone();
+        for key in keys {
+            let _: () = redis
+                .del(&[self.failures_key(key), self.lockout_key(key)])
+                .await
+                .context("Failed to reset login failures.")?;
+        }
+        Ok(())
+    }
+
+    fn failures_key(&self, key: &ThrottleKey<'_>) -> String {
+        format!("{}_failures:{}", self.key_prefix, key.name())
+    }
+
+    fn lockout_key(&self, key: &ThrottleKey<'_>) -> String {
+        format!("{}_lockout:{}", self.key_prefix, key.name())
+    }
+}
+
+/// The message shown to users who are locked out.
+pub fn lockout_message(lockout: Duration) -> String {
+    format!(
+        "Too many failed login attempts - please try again in {} seconds.",
+        lockout_secs(lockout)
+    )
+}
+
+/// The lockout in whole seconds, rounded up so that users never retry too early.
+pub fn lockout_secs(lockout: Duration) -> u64 {
+    lockout.as_secs() + u64::from(lockout.subsec_nanos() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_none, assert_some_eq};
+
+    fn policy() -> ThrottlePolicy {
+        ThrottlePolicy {
+            max_attempts_per_user: 5,
+            max_attempts_per_ip: 20,
+            initial_lockout: Duration::from_secs(30),
+            max_lockout: Duration::from_secs(3600),
+        }
+    }
+
+    #[test]
+    fn the_allowed_attempts_do_not_lock_out() {
+        assert_none!(policy().lockout(&ThrottleKey::Username("admin"), 5));
+        assert_none!(policy().lockout(&ThrottleKey::Ip("127.0.0.1"), 20));
+    }
+
+    #[test]
+    fn the_lockout_doubles_after_each_excess_failure() {
+        let key = ThrottleKey::Username("admin");
+        assert_some_eq!(policy().lockout(&key, 6), Duration::from_secs(30));
+        assert_some_eq!(policy().lockout(&key, 7), Duration::from_secs(60));
+        assert_some_eq!(policy().lockout(&key, 8), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn the_lockout_message_rounds_up_to_the_next_second() {
+        assert_eq!(
+            lockout_message(Duration::from_millis(29_001)),
+            "Too many failed login attempts - please try again in 30 seconds."
+        );
+    }
+
+    #[test]
+    fn the_lockout_is_capped() {
+        let key = ThrottleKey::Ip("127.0.0.1");
+        assert_some_eq!(policy().lockout(&key, u32::MAX), Duration::from_secs(3600));
+    }
+}
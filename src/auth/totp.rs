@@ -0,0 +1,225 @@
+use anyhow::Context;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64, Engine};
+use rand::{distributions, Rng};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use sqlx::PgExecutor;
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+const ISSUER: &str = "zero2prod";
+const N_RECOVERY_CODES: usize = 10;
+const RECOVERY_CODE_LEN: usize = 10;
+
+/// Generates a new base32-encoded TOTP secret.
+pub fn generate_totp_secret() -> SecretString {
+    match Secret::generate_secret().to_encoded() {
+        Secret::Encoded(s) => SecretString::from(s),
+        Secret::Raw(_) => unreachable!("The secret has just been encoded"),
+    }
+}
+
+/// The `otpauth://` URI authenticator apps use to enroll the secret.
+pub fn otpauth_uri(secret: &SecretString, username: &str) -> anyhow::Result<String> {
+    Ok(totp(secret, username)?.get_url())
+}
+
+/// Checks a 6-digit code, tolerating one step of clock drift in either direction, and
+/// returns the time step it was generated for.
+pub fn verify_totp_code(secret: &SecretString, code: &str) -> anyhow::Result<Option<u64>> {
+    let mut totp = totp(secret, "")?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("The system clock is set before the UNIX epoch.")?
+        .as_secs();
+    let current_step = now / totp.step;
+    // Pin each check to a single step to know which one matched
+    totp.skew = 0;
+    let step = (current_step.saturating_sub(1)..=current_step + 1)
+        .find(|step| totp.check(code.trim(), step * totp.step));
+    Ok(step)
+}
+
+/// Checks a code to log the user in, rejecting codes at or before the last one accepted.
+#[tracing::instrument(name = "Use TOTP code", skip(secret, code, executor))]
+pub async fn use_totp_code(
+    user_id: Uuid,
+    secret: &SecretString,
+    code: &str,
+    executor: impl PgExecutor<'_>,
+) -> anyhow::Result<bool> {
+    let Some(step) = verify_totp_code(secret, code)? else {
+        return Ok(false);
+    };
+    let r = sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_last_step = $2
+        WHERE id = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)
+        "#,
+        user_id,
+        step as i64
+    )
+    .execute(executor)
+    .await
+    .context("Failed to record the TOTP code as used.")?;
+    Ok(r.rows_affected() == 1)
+}
+
+fn totp(secret: &SecretString, username: &str) -> anyhow::Result<TOTP> {
+    let secret = Secret::Encoded(secret.expose_secret().to_owned())
+        .to_bytes()
+        .context("The TOTP secret is not valid base32.")?;
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret,
+        Some(ISSUER.into()),
+        username.replace(':', ""),
+    )
+    .context("Failed to set up TOTP.")
+}
+
+#[tracing::instrument(name = "Get TOTP secret", skip(executor))]
+pub async fn get_totp_secret(
+    user_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> anyhow::Result<Option<SecretString>> {
+    let r = sqlx::query!("SELECT totp_secret FROM users WHERE id = $1", user_id)
+        .fetch_one(executor)
+        .await
+        .context("Failed to retrieve the user's TOTP secret.")?;
+    Ok(r.totp_secret.map(SecretString::from))
+}
+
+/// Turns on the second factor for the user and returns a fresh set of single-use recovery codes.
+#[tracing::instrument(name = "Enable TOTP", skip(secret, txn))]
+pub async fn enable_totp(
+    user_id: Uuid,
+    secret: &SecretString,
+    txn: &mut sqlx::PgConnection,
+) -> anyhow::Result<Vec<String>> {
+    sqlx::query!(
+        "UPDATE users SET totp_secret = $2, totp_last_step = NULL WHERE id = $1",
+        user_id,
+        secret.expose_secret()
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to store the user's TOTP secret.")?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *txn)
+        .await
+        .context("Failed to delete old recovery codes.")?;
+
+    let codes: Vec<String> = (0..N_RECOVERY_CODES)
+        .map(|_| generate_recovery_code())
+        .collect();
+    let hashes: Vec<String> = codes.iter().map(|c| hash_recovery_code(c)).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO recovery_codes (user_id, code_hash)
+        SELECT $1, UNNEST($2::text[])
+        "#,
+        user_id,
+        &hashes
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to store recovery codes.")?;
+
+    Ok(codes)
+}
+
+#[tracing::instrument(name = "Disable TOTP", skip(txn))]
+pub async fn disable_totp(user_id: Uuid, txn: &mut sqlx::PgConnection) -> anyhow::Result<()> {
+    sqlx::query!("UPDATE users SET totp_secret = NULL WHERE id = $1", user_id)
+        .execute(&mut *txn)
+        .await
+        .context("Failed to clear the user's TOTP secret.")?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *txn)
+        .await
+        .context("Failed to delete recovery codes.")?;
+    Ok(())
+}
+
+/// Consumes a recovery code, returning whether it was valid.
+#[tracing::instrument(name = "Use recovery code", skip(code, executor))]
+pub async fn use_recovery_code(
+    user_id: Uuid,
+    code: &str,
+    executor: impl PgExecutor<'_>,
+) -> anyhow::Result<bool> {
+    let r = sqlx::query!(
+        "DELETE FROM recovery_codes WHERE user_id = $1 AND code_hash = $2",
+        user_id,
+        hash_recovery_code(code.trim())
+    )
+    .execute(executor)
+    .await
+    .context("Failed to consume the recovery code.")?;
+    Ok(r.rows_affected() == 1)
+}
+
+fn generate_recovery_code() -> String {
+    rand::thread_rng()
+        .sample_iter(distributions::Alphanumeric)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .take(RECOVERY_CODE_LEN)
+        .collect()
+}
+
+/// Recovery codes are random enough for a fast hash to be sufficient.
+fn hash_recovery_code(code: &str) -> String {
+    BASE64.encode(Sha256::digest(code.to_ascii_lowercase().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_none, assert_ok, assert_ok_eq, assert_some};
+
+    #[test]
+    fn codes_generated_for_the_secret_are_accepted() {
+        let secret = generate_totp_secret();
+        let totp = totp(&secret, "").unwrap();
+        let code = totp.generate_current().unwrap();
+        let step = totp.next_step_current().unwrap() / totp.step - 1;
+        assert_ok_eq!(verify_totp_code(&secret, &code), Some(step));
+    }
+
+    #[test]
+    fn codes_from_the_previous_step_are_accepted_for_that_step() {
+        let secret = generate_totp_secret();
+        let totp = totp(&secret, "").unwrap();
+        let step = totp.next_step_current().unwrap() / totp.step - 2;
+        let code = totp.generate(step * totp.step);
+        assert_ok_eq!(verify_totp_code(&secret, &code), Some(step));
+    }
+
+    #[test]
+    fn codes_generated_for_another_secret_are_rejected() {
+        let secret = generate_totp_secret();
+        let code = totp(&generate_totp_secret(), "")
+            .unwrap()
+            .generate_current()
+            .unwrap();
+        assert_none!(assert_ok!(verify_totp_code(&secret, &code)));
+    }
+
+    #[test]
+    fn the_otpauth_uri_names_the_issuer_and_the_user() {
+        let uri = otpauth_uri(&generate_totp_secret(), "admin").unwrap();
+        assert!(uri.starts_with("otpauth://totp/zero2prod:admin?"));
+        assert_some!(uri.find("issuer=zero2prod"));
+    }
+
+    #[test]
+    fn recovery_codes_are_case_insensitive() {
+        assert_eq!(hash_recovery_code("abc123"), hash_recovery_code("ABC123"));
+    }
+}
@@ -0,0 +1,422 @@
+use anyhow::{bail, Context};
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::{builder::PossibleValuesParser, Args, Parser, Subcommand};
+use secrecy::SecretString;
+use sqlx::PgPool;
+use std::io::{self, BufRead};
+use uuid::Uuid;
+use zero2prod::{
+    auth::{self, NewUser},
+    config,
+    domain::{SubscriberEmail, UserRole, ValidPassword},
+    routes::{remove_subscriber, SUBSCRIPTION_STATUSES},
+    telemetry,
+    utils::escape_like,
+    workers::issue_delivery::requeue_failed_tasks,
+};
+
+/// Manages users, subscribers and the delivery queue of the newsletter.
+///
+/// Reads the same configuration as the server, so it talks to the same database.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage the users of the admin area.
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Inspect and fix up subscriptions.
+    #[command(subcommand)]
+    Subscriber(SubscriberCommand),
+    /// Inspect and act on the issue delivery queue.
+    #[command(subcommand)]
+    Queue(QueueCommand),
+    /// Apply the pending database migrations.
+    Migrate,
+}
+
+#[derive(Subcommand)]
+enum UserCommand {
+    /// Create a user.
+    Create {
+        username: String,
+        #[arg(long)]
+        email: String,
+        #[arg(long, value_parser = UserRole::parse)]
+        role: UserRole,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// Set a new password for a user and log them out everywhere.
+    ResetPassword {
+        username: String,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+}
+
+#[derive(Args)]
+struct PasswordArgs {
+    /// Read the password from the first line of stdin instead of prompting for it.
+    #[arg(long)]
+    password_stdin: bool,
+}
+
+#[derive(Subcommand)]
+enum SubscriberCommand {
+    /// List the most recent subscribers.
+    List {
+        #[arg(long, value_parser = PossibleValuesParser::new(SUBSCRIPTION_STATUSES))]
+        status: Option<String>,
+        /// Matched against both the email and the name, ignoring case.
+        #[arg(long)]
+        search: Option<String>,
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+    },
+    /// Confirm a subscriber without them following the confirmation link.
+    Confirm { email: String },
+    /// Delete a subscriber along with their pending deliveries.
+    Remove {
+        email: String,
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum QueueCommand {
+    /// Count the queued and failed deliveries of each issue.
+    Stats,
+    /// Delete queued deliveries so that they are never sent.
+    Purge {
+        /// Only purge the deliveries of this issue.
+        #[arg(long)]
+        issue: Option<Uuid>,
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Move failed deliveries back to the queue.
+    Requeue {
+        /// Only re-queue the deliveries of this issue.
+        #[arg(long)]
+        issue: Option<Uuid>,
+        /// Only re-queue the deliveries to this subscriber.
+        #[arg(long)]
+        email: Option<String>,
+    },
+}
+
+#[actix_web::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    // Keep stdout for the command's output
+    let subscriber = telemetry::get_subscriber("zero2prod-admin", "warn", std::io::stderr);
+    telemetry::init_subscriber(subscriber);
+
+    let config = config::get().map_err(|e| anyhow::anyhow!("Failed to read configuration: {e}"))?;
+    let pool = config.database.get_db_pool();
+
+    match cli.command {
+        Command::User(command) => manage_users(command, &pool).await,
+        Command::Subscriber(command) => manage_subscribers(command, &pool).await,
+        Command::Queue(command) => manage_queue(command, &pool).await,
+        Command::Migrate => {
+            sqlx::migrate!("./migrations")
+                .run(&pool)
+                .await
+                .context("Failed to migrate the database.")?;
+            println!("The database is up to date.");
+            Ok(())
+        }
+    }
+}
+
+async fn manage_users(command: UserCommand, pool: &PgPool) -> anyhow::Result<()> {
+    match command {
+        UserCommand::Create {
+            username,
+            email,
+            role,
+            password,
+        } => {
+            let email = SubscriberEmail::parse(email).map_err(anyhow::Error::msg)?;
+            let user = NewUser {
+                username: username.clone(),
+                email: email.as_ref().to_owned(),
+                role,
+                password: read_password(&password)?,
+            };
+            let user_id = auth::create_user(user, pool).await?;
+            println!("Created {} ({}) with the {} role.", username, user_id, role);
+        }
+        UserCommand::ResetPassword { username, password } => {
+            let user_id = sqlx::query_scalar!("SELECT id FROM users WHERE username = $1", username)
+                .fetch_optional(pool)
+                .await
+                .context("Failed to look up the user.")?
+                .with_context(|| format!("There is no user named {}.", username))?;
+            let password = read_password(&password)?;
+
+            let mut txn = pool
+                .begin()
+                .await
+                .context("Failed to acquire a Postgres connection from the pool")?;
+            auth::change_password(user_id, password, txn.as_mut()).await?;
+            auth::invalidate_sessions(user_id, txn.as_mut()).await?;
+            txn.commit()
+                .await
+                .context("Failed to commit SQL transaction to reset a password.")?;
+            println!("Reset the password of {} and logged them out.", username);
+        }
+    }
+    Ok(())
+}
+
+fn read_password(args: &PasswordArgs) -> anyhow::Result<ValidPassword> {
+    let password = if args.password_stdin {
+        let mut line = String::new();
+        io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .context("Failed to read the password from stdin.")?;
+        line.trim_end_matches(['\r', '\n']).to_owned()
+    } else {
+        let password = rpassword::prompt_password("Password: ")?;
+        if rpassword::prompt_password("Repeat password: ")? != password {
+            bail!("The passwords don't match.");
+        }
+        password
+    };
+    Ok(ValidPassword::parse(SecretString::from(password))?)
+}
+
+async fn manage_subscribers(command: SubscriberCommand, pool: &PgPool) -> anyhow::Result<()> {
+    match command {
+        SubscriberCommand::List {
+            status,
+            search,
+            limit,
+        } => {
+            let pattern = search.map(|s| format!("%{}%", escape_like(&s)));
+            let subscribers = sqlx::query!(
+                r#"
+                SELECT email, name, status, subscribed_at
+                FROM subscriptions
+                WHERE ($1::text IS NULL OR status = $1)
+                AND ($2::text IS NULL OR email ILIKE $2 OR name ILIKE $2)
+                ORDER BY subscribed_at DESC, id
+                LIMIT $3
+                "#,
+                status,
+                pattern,
+                limit
+            )
+            .fetch_all(pool)
+            .await
+            .context("Failed to list subscribers.")?;
+
+            print_table(
+                ["EMAIL", "NAME", "STATUS", "SUBSCRIBED AT"],
+                subscribers
+                    .into_iter()
+                    .map(|s| [s.email, s.name, s.status, format_timestamp(s.subscribed_at)]),
+            );
+        }
+        SubscriberCommand::Confirm { email } => {
+            let mut txn = pool
+                .begin()
+                .await
+                .context("Failed to acquire a Postgres connection from the pool")?;
+            let subscriber_id = sqlx::query_scalar!(
+                r#"
+                UPDATE subscriptions
+                SET status = 'confirmed'
+                WHERE email = $1 AND status = 'pending_confirmation'
+                RETURNING id
+                "#,
+                email
+            )
+            .fetch_optional(txn.as_mut())
+            .await
+            .context("Failed to confirm the subscriber.")?;
+            let Some(subscriber_id) = subscriber_id else {
+                let status =
+                    sqlx::query_scalar!("SELECT status FROM subscriptions WHERE email = $1", email)
+                        .fetch_optional(txn.as_mut())
+                        .await
+                        .context("Failed to retrieve the subscriber.")?;
+                match status {
+                    Some(status) => bail!(
+                        "Only pending subscribers can be confirmed, but {} is {}.",
+                        email,
+                        status
+                    ),
+                    None => bail!("There is no subscriber with the email {}.", email),
+                }
+            };
+            // Their confirmation link and email are no longer of any use
+            sqlx::query!(
+                "DELETE FROM subscription_tokens WHERE subscriber_id = $1",
+                subscriber_id
+            )
+            .execute(txn.as_mut())
+            .await
+            .context("Failed to delete the subscriber's confirmation token.")?;
+            sqlx::query!(
+                "DELETE FROM confirmation_email_outbox WHERE subscriber_id = $1",
+                subscriber_id
+            )
+            .execute(txn.as_mut())
+            .await
+            .context("Failed to cancel the subscriber's confirmation email.")?;
+            txn.commit()
+                .await
+                .context("Failed to commit SQL transaction to confirm a subscriber.")?;
+            println!("Confirmed {}.", email);
+        }
+        SubscriberCommand::Remove { email, yes } => {
+            if !yes {
+                bail!(
+                    "Pass --yes to remove {} and their pending deliveries.",
+                    email
+                );
+            }
+            let mut txn = pool
+                .begin()
+                .await
+                .context("Failed to acquire a Postgres connection from the pool")?;
+            let subscriber_id =
+                sqlx::query_scalar!("SELECT id FROM subscriptions WHERE email = $1", email)
+                    .fetch_optional(txn.as_mut())
+                    .await
+                    .context("Failed to retrieve the subscriber.")?
+                    .with_context(|| format!("There is no subscriber with the email {}.", email))?;
+            remove_subscriber(txn.as_mut(), subscriber_id).await?;
+            txn.commit()
+                .await
+                .context("Failed to commit SQL transaction to remove a subscriber.")?;
+            println!("Removed {}.", email);
+        }
+    }
+    Ok(())
+}
+
+async fn manage_queue(command: QueueCommand, pool: &PgPool) -> anyhow::Result<()> {
+    match command {
+        QueueCommand::Stats => {
+            let issues = sqlx::query!(
+                r#"
+                SELECT
+                    i.id,
+                    i.title,
+                    COUNT(q.*) AS "queued!",
+                    COUNT(q.*) FILTER (WHERE q.n_retries > 0) AS "retrying!",
+                    (
+                        SELECT COUNT(*)
+                        FROM failed_deliveries f
+                        WHERE f.newsletter_issue_id = i.id
+                    ) AS "failed!",
+                    MIN(q.created_at) AS oldest_task_at
+                FROM newsletter_issues i
+                LEFT JOIN issue_delivery_queue q ON q.newsletter_issue_id = i.id
+                GROUP BY i.id
+                HAVING COUNT(q.*) > 0
+                    OR EXISTS (SELECT 1 FROM failed_deliveries f WHERE f.newsletter_issue_id = i.id)
+                ORDER BY MIN(q.created_at) NULLS LAST, i.title
+                "#
+            )
+            .fetch_all(pool)
+            .await
+            .context("Failed to count queued deliveries.")?;
+            let n_confirmation_emails =
+                sqlx::query_scalar!(r#"SELECT COUNT(*) AS "n!" FROM confirmation_email_outbox"#)
+                    .fetch_one(pool)
+                    .await
+                    .context("Failed to count queued confirmation emails.")?;
+
+            print_table(
+                ["ISSUE", "TITLE", "QUEUED", "RETRYING", "FAILED", "OLDEST"],
+                issues.into_iter().map(|i| {
+                    [
+                        i.id.to_string(),
+                        i.title,
+                        i.queued.to_string(),
+                        i.retrying.to_string(),
+                        i.failed.to_string(),
+                        i.oldest_task_at.map(format_timestamp).unwrap_or_default(),
+                    ]
+                }),
+            );
+            println!("\nConfirmation emails queued: {}", n_confirmation_emails);
+        }
+        QueueCommand::Purge { issue, yes } => {
+            if !yes {
+                let n = sqlx::query_scalar!(
+                    r#"
+                    SELECT COUNT(*) AS "n!"
+                    FROM issue_delivery_queue
+                    WHERE $1::uuid IS NULL OR newsletter_issue_id = $1
+                    "#,
+                    issue
+                )
+                .fetch_one(pool)
+                .await
+                .context("Failed to count queued deliveries.")?;
+                bail!("Pass --yes to delete {} queued deliveries.", n);
+            }
+            let n = sqlx::query!(
+                r#"
+                DELETE FROM issue_delivery_queue
+                WHERE $1::uuid IS NULL OR newsletter_issue_id = $1
+                "#,
+                issue
+            )
+            .execute(pool)
+            .await
+            .context("Failed to purge the delivery queue.")?
+            .rows_affected();
+            println!("Deleted {} queued deliveries.", n);
+        }
+        QueueCommand::Requeue { issue, email } => {
+            let n = requeue_failed_tasks(issue, email.as_deref(), pool).await?;
+            println!("{} failed deliveries have been re-queued.", n);
+        }
+    }
+    Ok(())
+}
+
+fn format_timestamp(t: DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Prints rows under a header, padding every column to its widest cell.
+fn print_table<const N: usize>(header: [&str; N], rows: impl IntoIterator<Item = [String; N]>) {
+    let rows: Vec<_> = rows.into_iter().collect();
+    let mut widths = header.map(|h| h.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let print_row = |cells: [&str; N]| {
+        let line = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    };
+    print_row(header);
+    for row in &rows {
+        print_row(row.each_ref().map(String::as_str));
+    }
+}
@@ -1,17 +1,29 @@
-use crate::{domain::SubscriberEmail, email_client::EmailClient};
+use crate::{
+    auth::ThrottlePolicy,
+    domain::SubscriberEmail,
+    email_client::{
+        EmailTransport, FileTransport, MeteredTransport, PostmarkTransport, SmtpTransport,
+    },
+    workers::issue_delivery,
+};
 use config::{Config, File};
 use reqwest::Url;
 use secrecy::{ExposeSecret, SecretString};
 use serde::Deserialize;
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::postgres::{PgConnectOptions, PgPool, PgSslMode};
-use std::{env, error::Error, time::Duration};
+use std::{env, error::Error, net::IpAddr, sync::Arc, time::Duration};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct Settings {
     pub application: ApplicationSettings,
     pub database: DatabaseSettings,
     pub email_client: EmailClientSettings,
+    pub issue_delivery: IssueDeliverySettings,
+    pub login_throttle: LoginThrottleSettings,
+    pub subscriptions: SubscriptionSettings,
+    pub metrics: MetricsSettings,
+    pub health: HealthSettings,
     pub redis_uri: SecretString,
 }
 
@@ -22,6 +34,17 @@ pub struct ApplicationSettings {
     pub port: u16,
     pub base_url: String,
     pub hmac_secret: SecretString,
+    /// How long in-flight requests and running worker tasks are given to complete on shutdown.
+    pub shutdown_timeout_secs: u64,
+    /// Reverse proxies whose `X-Forwarded-For` header is trusted to name the client.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl ApplicationSettings {
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
 }
 
 #[derive(Deserialize, Clone)]
@@ -59,31 +82,163 @@ impl DatabaseSettings {
 
 #[derive(Deserialize, Clone)]
 pub struct EmailClientSettings {
-    pub base_url: String,
+    pub transport: EmailTransportKind,
     pub sender_email: String,
-    pub auth_token: SecretString,
     pub timeout_ms: u64,
+    pub postmark: PostmarkSettings,
+    pub smtp: SmtpSettings,
+    pub file: FileSettings,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailTransportKind {
+    Postmark,
+    Smtp,
+    File,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct PostmarkSettings {
+    pub base_url: String,
+    pub auth_token: SecretString,
+    /// The HTTP basic credentials Postmark must send along with webhook requests.
+    pub webhook_username: String,
+    #[serde(deserialize_with = "deserialize_non_empty_secret")]
+    pub webhook_password: SecretString,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<SecretString>,
+    pub starttls: bool,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct FileSettings {
+    pub spool_dir: String,
 }
 
 impl EmailClientSettings {
-    pub fn client(&self) -> EmailClient {
+    /// Builds the transport selected by `transport`, recording the outcome of every email.
+    pub fn client(&self) -> Arc<dyn EmailTransport> {
         let sender = self.sender().expect("Invalid sender email address.");
-        let url = self.url().expect("Invalid base url.");
         let timeout = self.timeout();
-        let auth_token = self.auth_token.clone();
-        EmailClient::new(url, sender, auth_token, timeout)
+        let transport: Arc<dyn EmailTransport> = match self.transport {
+            EmailTransportKind::Postmark => {
+                let url = self.postmark.url().expect("Invalid base url.");
+                let auth_token = self.postmark.auth_token.clone();
+                Arc::new(PostmarkTransport::new(url, sender, auth_token, timeout))
+            }
+            EmailTransportKind::Smtp => {
+                let s = &self.smtp;
+                let credentials = s.username.clone().zip(s.password.clone());
+                let transport =
+                    SmtpTransport::new(&s.host, s.port, credentials, s.starttls, sender, timeout)
+                        .expect("Invalid SMTP settings.");
+                Arc::new(transport)
+            }
+            EmailTransportKind::File => Arc::new(FileTransport::new(&self.file.spool_dir, sender)),
+        };
+        Arc::new(MeteredTransport(transport))
     }
 
     pub fn sender(&self) -> Result<SubscriberEmail, String> {
         SubscriberEmail::parse(self.sender_email.clone())
     }
 
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+impl PostmarkSettings {
     pub fn url(&self) -> Result<Url, String> {
         Url::parse(&self.base_url).map_err(|e| e.to_string())
     }
+}
 
-    pub fn timeout(&self) -> Duration {
-        Duration::from_millis(self.timeout_ms)
+#[derive(Deserialize, Clone)]
+pub struct IssueDeliverySettings {
+    pub batch_size: u32,
+    pub max_retries: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl IssueDeliverySettings {
+    pub fn retry_policy(&self) -> issue_delivery::RetryPolicy {
+        issue_delivery::RetryPolicy {
+            max_retries: self.max_retries,
+            initial_backoff: Duration::from_millis(self.initial_backoff_ms),
+            max_backoff: Duration::from_millis(self.max_backoff_ms),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct LoginThrottleSettings {
+    pub key_prefix: String,
+    pub max_attempts_per_user: u32,
+    pub max_attempts_per_ip: u32,
+    pub initial_lockout_ms: u64,
+    pub max_lockout_ms: u64,
+}
+
+impl LoginThrottleSettings {
+    pub fn policy(&self) -> ThrottlePolicy {
+        ThrottlePolicy {
+            max_attempts_per_user: self.max_attempts_per_user,
+            max_attempts_per_ip: self.max_attempts_per_ip,
+            initial_lockout: Duration::from_millis(self.initial_lockout_ms),
+            max_lockout: Duration::from_millis(self.max_lockout_ms),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct SubscriptionSettings {
+    /// Days an expired confirmation token, or a never-confirmed subscription, is kept
+    /// before being pruned.
+    pub unconfirmed_retention_days: u32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct MetricsSettings {
+    /// The token Prometheus must present to scrape `/metrics`.
+    #[serde(deserialize_with = "deserialize_non_empty_secret")]
+    pub bearer_token: SecretString,
+    /// Where processes running workers without the API serve `/metrics` and the health probes.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+}
+
+/// An empty secret would let requests without credentials through.
+fn deserialize_non_empty_secret<'de, D>(deserializer: D) -> Result<SecretString, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let secret = SecretString::deserialize(deserializer)?;
+    if secret.expose_secret().trim().is_empty() {
+        return Err(serde::de::Error::custom("the secret cannot be empty"));
+    }
+    Ok(secret)
+}
+
+#[derive(Deserialize, Clone)]
+pub struct HealthSettings {
+    /// How long a worker may go without a heartbeat before the app is reported as not ready.
+    /// Must exceed the longest pause of any worker.
+    pub worker_heartbeat_timeout_secs: u64,
+}
+
+impl HealthSettings {
+    pub fn worker_heartbeat_timeout(&self) -> Duration {
+        Duration::from_secs(self.worker_heartbeat_timeout_secs)
     }
 }
 
@@ -151,3 +306,37 @@ impl TryFrom<&str> for Environment {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_empty_metrics_token_is_rejected() {
+        for token in ["", "  "] {
+            let settings = serde_json::from_value::<MetricsSettings>(
+                serde_json::json!({ "bearer_token": token, "port": 9000 }),
+            );
+            assert!(settings.is_err());
+        }
+    }
+
+    #[test]
+    fn an_empty_webhook_password_is_rejected() {
+        let settings = serde_json::from_value::<PostmarkSettings>(serde_json::json!({
+            "base_url": "https://api.postmarkapp.com",
+            "auth_token": "my-secret-token",
+            "webhook_username": "postmark",
+            "webhook_password": "",
+        }));
+        assert!(settings.is_err());
+    }
+
+    #[test]
+    fn a_metrics_token_is_accepted() {
+        let settings = serde_json::from_value::<MetricsSettings>(
+            serde_json::json!({ "bearer_token": "my-metrics-token", "port": 9000 }),
+        );
+        assert!(settings.is_ok());
+    }
+}
@@ -0,0 +1,132 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Identifies the delivery of an issue to a subscriber in tracking links.
+///
+/// Formatted as `<issue id><subscriber id>.<signature>`, so that it cannot be forged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeliveryId {
+    pub issue_id: Uuid,
+    pub subscriber_id: Uuid,
+}
+
+impl DeliveryId {
+    pub fn new(issue_id: Uuid, subscriber_id: Uuid) -> Self {
+        Self {
+            issue_id,
+            subscriber_id,
+        }
+    }
+
+    /// The signed form of the id, to be embedded in links.
+    pub fn sign(&self, secret: &SecretString) -> String {
+        let signature = self.mac(b"delivery:", secret).finalize().into_bytes();
+        format!(
+            "{}{}.{}",
+            self.issue_id.simple(),
+            self.subscriber_id.simple(),
+            URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    /// Parses a signed id, checking its signature in constant time.
+    pub fn verify(s: &str, secret: &SecretString) -> Result<Self, String> {
+        let invalid = || format!("{} is not a valid delivery id.", s);
+        let (ids, signature) = s.split_once('.').ok_or_else(invalid)?;
+        if ids.len() != 64 || !ids.is_ascii() {
+            return Err(invalid());
+        }
+        let id = Self::new(
+            Uuid::try_parse(&ids[..32]).map_err(|_| invalid())?,
+            Uuid::try_parse(&ids[32..]).map_err(|_| invalid())?,
+        );
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+        id.mac(b"delivery:", secret)
+            .verify_slice(&signature)
+            .map_err(|_| invalid())?;
+        Ok(id)
+    }
+
+    /// Signs a link of the issue, so that tracked redirections only lead to its own links.
+    pub fn sign_link(&self, url: &str, secret: &SecretString) -> String {
+        let mut mac = self.mac(b"link:", secret);
+        mac.update(url.as_bytes());
+        URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+    }
+
+    pub fn verify_link(&self, url: &str, signature: &str, secret: &SecretString) -> bool {
+        let Ok(signature) = URL_SAFE_NO_PAD.decode(signature) else {
+            return false;
+        };
+        let mut mac = self.mac(b"link:", secret);
+        mac.update(url.as_bytes());
+        mac.verify_slice(&signature).is_ok()
+    }
+
+    fn mac(&self, purpose: &[u8], secret: &SecretString) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(secret.expose_secret().as_bytes())
+            .expect("HMAC can take a key of any size.");
+        mac.update(purpose);
+        mac.update(self.issue_id.as_bytes());
+        mac.update(self.subscriber_id.as_bytes());
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok_eq};
+
+    fn secret() -> SecretString {
+        SecretString::from("super-secret-key")
+    }
+
+    fn id() -> DeliveryId {
+        DeliveryId::new(Uuid::new_v4(), Uuid::new_v4())
+    }
+
+    #[test]
+    fn a_signed_id_is_verified() {
+        let id = id();
+        assert_ok_eq!(DeliveryId::verify(&id.sign(&secret()), &secret()), id);
+    }
+
+    #[test]
+    fn a_tampered_id_is_rejected() {
+        let signed = id().sign(&secret());
+        let (_, signature) = signed.split_once('.').unwrap();
+        let other = id().sign(&secret());
+        let (ids, _) = other.split_once('.').unwrap();
+        assert_err!(DeliveryId::verify(
+            &format!("{}.{}", ids, signature),
+            &secret()
+        ));
+    }
+
+    #[test]
+    fn an_id_signed_with_another_secret_is_rejected() {
+        let signed = id().sign(&SecretString::from("another-secret-key"));
+        assert_err!(DeliveryId::verify(&signed, &secret()));
+    }
+
+    #[test]
+    fn malformed_ids_are_rejected() {
+        assert_err!(DeliveryId::verify("", &secret()));
+        assert_err!(DeliveryId::verify("not.an-id", &secret()));
+        assert_err!(DeliveryId::verify(&"é".repeat(40), &secret()));
+    }
+
+    #[test]
+    fn link_signatures_are_bound_to_the_url() {
+        let id = id();
+        let signature = id.sign_link("https://example.com/a", &secret());
+        assert!(id.verify_link("https://example.com/a", &signature, &secret()));
+        assert!(!id.verify_link("https://evil.example.com", &signature, &secret()));
+    }
+}
@@ -0,0 +1,62 @@
+use rand::{distributions, Rng};
+
+const TOKEN_LEN: usize = 32;
+
+/// A single-use token letting an invited collaborator create their account.
+#[derive(Debug, Clone)]
+pub struct InvitationToken(String);
+
+impl InvitationToken {
+    pub fn parse(s: String) -> Result<Self, String> {
+        let is_right_sized = s.chars().count() == TOKEN_LEN;
+        let is_alphanumeric = s.chars().all(|c| c.is_ascii_alphanumeric());
+
+        if is_right_sized && is_alphanumeric {
+            Ok(Self(s))
+        } else {
+            Err(format!("{} is not a valid invitation token.", s))
+        }
+    }
+
+    /// Generate a random 32-characters-long case-sensitive invitation token.
+    pub fn generate() -> Self {
+        let raw = rand::thread_rng()
+            .sample_iter(distributions::Alphanumeric)
+            .map(char::from)
+            .take(TOKEN_LEN)
+            .collect();
+        Self(raw)
+    }
+}
+
+impl AsRef<str> for InvitationToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn generated_tokens_can_be_parsed() {
+        let token = InvitationToken::generate();
+        assert_ok!(InvitationToken::parse(token.as_ref().to_owned()));
+    }
+
+    #[test]
+    fn tokens_of_the_wrong_length_are_rejected() {
+        assert_err!(InvitationToken::parse("a".repeat(TOKEN_LEN - 1)));
+        assert_err!(InvitationToken::parse("a".repeat(TOKEN_LEN + 1)));
+    }
+
+    #[test]
+    fn tokens_with_non_alphanumeric_chars_are_rejected() {
+        assert_err!(InvitationToken::parse(format!(
+            "{}-",
+            "a".repeat(TOKEN_LEN - 1)
+        )));
+    }
+}
@@ -1,11 +1,23 @@
+mod delivery_id;
+mod invitation_token;
 mod new_subscriber;
+mod password_reset_token;
+mod segment;
 mod subscriber_email;
 mod subscriber_name;
 mod subscription_token;
+mod unsubscribe_token;
 mod user_password;
+mod user_role;
 
+pub use delivery_id::DeliveryId;
+pub use invitation_token::InvitationToken;
 pub use new_subscriber::NewSubscriber;
+pub use password_reset_token::PasswordResetToken;
+pub use segment::{Segment, SegmentRule};
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
 pub use subscription_token::SubscriptionToken;
+pub use unsubscribe_token::UnsubscribeToken;
 pub use user_password::{ValidPassword, ValidPasswordError};
+pub use user_role::UserRole;
@@ -0,0 +1,174 @@
+use crate::utils::escape_like;
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, QueryBuilder};
+
+/// How far back a segment can look - about a century.
+const MAX_SEGMENT_DAYS: u16 = 36_500;
+
+/// A condition on the `subscriptions` row aliased as `s`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum SegmentRule {
+    SubscribedWithinDays { days: u16 },
+    NameContains { text: String },
+    AttributeEquals { key: String, value: String },
+}
+
+impl SegmentRule {
+    /// Appends the condition to the query, binding every value.
+    fn push_sql(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        match self {
+            Self::SubscribedWithinDays { days } => {
+                qb.push("s.subscribed_at >= now() - make_interval(days => ")
+                    .push_bind(i32::from(*days))
+                    .push(")");
+            }
+            Self::NameContains { text } => {
+                qb.push("s.name ILIKE ")
+                    .push_bind(format!("%{}%", escape_like(text)));
+            }
+            Self::AttributeEquals { key, value } => {
+                qb.push("s.attributes ->> ")
+                    .push_bind(key.clone())
+                    .push(" = ")
+                    .push_bind(value.clone());
+            }
+        }
+    }
+
+    /// A human readable description of the rule.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::SubscribedWithinDays { days } => format!("subscribed in the last {} days", days),
+            Self::NameContains { text } => format!("name contains \"{}\"", text),
+            Self::AttributeEquals { key, value } => format!("{} is \"{}\"", key, value),
+        }
+    }
+}
+
+/// Subscribers matching every rule of the segment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Segment {
+    rules: Vec<SegmentRule>,
+}
+
+impl Segment {
+    pub fn parse(rules: Vec<SegmentRule>) -> Result<Self, String> {
+        if rules.is_empty() {
+            return Err("A segment needs at least one rule.".into());
+        }
+        for r in &rules {
+            match r {
+                SegmentRule::SubscribedWithinDays { days } if *days > MAX_SEGMENT_DAYS => {
+                    return Err(format!(
+                        "A segment cannot look back more than {} days.",
+                        MAX_SEGMENT_DAYS
+                    ))
+                }
+                SegmentRule::NameContains { text } if text.trim().is_empty() => {
+                    return Err("The name to look for cannot be empty.".into())
+                }
+                SegmentRule::AttributeEquals { key, .. } if key.trim().is_empty() => {
+                    return Err("The attribute name cannot be empty.".into())
+                }
+                _ => (),
+            }
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn rules(&self) -> &[SegmentRule] {
+        &self.rules
+    }
+
+    /// Appends ` AND <rule>` to the query for every rule.
+    pub fn push_conditions(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        for r in &self.rules {
+            qb.push(" AND (");
+            r.push_sql(qb);
+            qb.push(")");
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        self.rules
+            .iter()
+            .map(SegmentRule::describe)
+            .collect::<Vec<_>>()
+            .join(" and ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+
+    fn compile(segment: &Segment) -> String {
+        let mut qb = QueryBuilder::new("SELECT s.email FROM subscriptions s WHERE true");
+        segment.push_conditions(&mut qb);
+        qb.sql().to_owned()
+    }
+
+    #[test]
+    fn a_segment_without_rules_is_rejected() {
+        assert_err!(Segment::parse(vec![]));
+    }
+
+    #[test]
+    fn looking_back_more_than_a_century_is_rejected() {
+        assert_ok!(Segment::parse(vec![SegmentRule::SubscribedWithinDays {
+            days: MAX_SEGMENT_DAYS
+        }]));
+        assert_err!(Segment::parse(vec![SegmentRule::SubscribedWithinDays {
+            days: MAX_SEGMENT_DAYS + 1
+        }]));
+    }
+
+    #[test]
+    fn blank_attribute_names_are_rejected() {
+        assert_err!(Segment::parse(vec![SegmentRule::AttributeEquals {
+            key: " ".into(),
+            value: "pro".into(),
+        }]));
+    }
+
+    #[test]
+    fn every_value_is_bound() {
+        let segment = assert_ok!(Segment::parse(vec![
+            SegmentRule::SubscribedWithinDays { days: 30 },
+            SegmentRule::NameContains {
+                text: "'; DROP TABLE subscriptions; --".into()
+            },
+            SegmentRule::AttributeEquals {
+                key: "plan".into(),
+                value: "pro".into()
+            },
+        ]));
+
+        assert_eq!(
+            compile(&segment),
+            "SELECT s.email FROM subscriptions s WHERE true \
+            AND (s.subscribed_at >= now() - make_interval(days => $1)) \
+            AND (s.name ILIKE $2) \
+            AND (s.attributes ->> $3 = $4)"
+        );
+    }
+
+    #[test]
+    fn segments_round_trip_through_json() {
+        let segment = assert_ok!(Segment::parse(vec![SegmentRule::AttributeEquals {
+            key: "plan".into(),
+            value: "pro".into(),
+        }]));
+
+        let json = serde_json::to_value(&segment).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!([{ "rule": "attribute_equals", "key": "plan", "value": "pro" }])
+        );
+        assert_eq!(segment, serde_json::from_value(json).unwrap());
+    }
+}
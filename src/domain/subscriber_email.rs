@@ -1,7 +1,7 @@
 use std::fmt::Display;
 use validator::ValidateEmail;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SubscriberEmail(String);
 
 impl SubscriberEmail {
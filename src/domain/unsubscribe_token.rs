@@ -0,0 +1,93 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const SIGNATURE_LEN: usize = 32;
+
+/// A signature binding an unsubscribe request to a single subscriber.
+#[derive(Debug, Clone)]
+pub struct UnsubscribeToken(String);
+
+impl UnsubscribeToken {
+    pub fn parse(s: String) -> Result<Self, String> {
+        match URL_SAFE_NO_PAD.decode(&s) {
+            Ok(raw) if raw.len() == SIGNATURE_LEN => Ok(Self(s)),
+            _ => Err(format!("{} is not a valid unsubscribe token.", s)),
+        }
+    }
+
+    /// Sign the given subscriber id with the application secret.
+    pub fn sign(subscriber_id: Uuid, secret: &SecretString) -> Self {
+        let signature = mac(subscriber_id, secret).finalize().into_bytes();
+        Self(URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    /// Check, in constant time, that the token has been issued for the given subscriber.
+    pub fn verify(&self, subscriber_id: Uuid, secret: &SecretString) -> bool {
+        let Ok(signature) = URL_SAFE_NO_PAD.decode(&self.0) else {
+            return false;
+        };
+        mac(subscriber_id, secret).verify_slice(&signature).is_ok()
+    }
+}
+
+fn mac(subscriber_id: Uuid, secret: &SecretString) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size.");
+    mac.update(b"unsubscribe:");
+    mac.update(subscriber_id.as_bytes());
+    mac
+}
+
+impl AsRef<str> for UnsubscribeToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+
+    fn secret() -> SecretString {
+        SecretString::from("super-secret-key")
+    }
+
+    #[test]
+    fn a_signed_token_is_verified_for_the_same_subscriber() {
+        let id = Uuid::new_v4();
+        let token = UnsubscribeToken::sign(id, &secret());
+        assert!(token.verify(id, &secret()));
+    }
+
+    #[test]
+    fn a_signed_token_is_rejected_for_another_subscriber() {
+        let token = UnsubscribeToken::sign(Uuid::new_v4(), &secret());
+        assert!(!token.verify(Uuid::new_v4(), &secret()));
+    }
+
+    #[test]
+    fn a_signed_token_is_rejected_with_another_secret() {
+        let id = Uuid::new_v4();
+        let token = UnsubscribeToken::sign(id, &secret());
+        assert!(!token.verify(id, &SecretString::from("another-secret-key")));
+    }
+
+    #[test]
+    fn signed_tokens_can_be_parsed() {
+        let token = UnsubscribeToken::sign(Uuid::new_v4(), &secret());
+        assert_ok!(UnsubscribeToken::parse(token.as_ref().to_owned()));
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        assert_err!(UnsubscribeToken::parse("".into()));
+        assert_err!(UnsubscribeToken::parse("not base64!".into()));
+        assert_err!(UnsubscribeToken::parse(URL_SAFE_NO_PAD.encode([0u8; 16])));
+    }
+}
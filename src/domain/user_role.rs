@@ -0,0 +1,64 @@
+use std::fmt::Display;
+
+/// What a user is allowed to do in the admin area. Each role can do everything
+/// the roles below it can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UserRole {
+    /// Can look at issues and deliveries.
+    Viewer,
+    /// Can also write, schedule and publish issues.
+    Editor,
+    /// Can also manage users.
+    Admin,
+}
+
+impl UserRole {
+    pub const ALL: [Self; 3] = [Self::Admin, Self::Editor, Self::Viewer];
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "admin" => Ok(Self::Admin),
+            "editor" => Ok(Self::Editor),
+            "viewer" => Ok(Self::Viewer),
+            _ => Err(format!("{} is not a valid role.", s)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::Editor => "editor",
+            Self::Viewer => "viewer",
+        }
+    }
+}
+
+impl Display for UserRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::assert_err;
+
+    #[test]
+    fn roles_round_trip_through_their_name() {
+        for role in UserRole::ALL {
+            assert_eq!(role, UserRole::parse(role.as_str()).unwrap());
+        }
+    }
+
+    #[test]
+    fn unknown_roles_are_rejected() {
+        assert_err!(UserRole::parse("owner"));
+    }
+
+    #[test]
+    fn admins_outrank_editors_who_outrank_viewers() {
+        assert!(UserRole::Admin > UserRole::Editor);
+        assert!(UserRole::Editor > UserRole::Viewer);
+    }
+}
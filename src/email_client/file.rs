@@ -0,0 +1,89 @@
+use super::{EmailHeader, EmailTransport};
+use crate::domain::SubscriberEmail;
+use anyhow::Context;
+use async_trait::async_trait;
+use lettre::{AsyncFileTransport, AsyncTransport, Tokio1Executor};
+use std::path::PathBuf;
+
+/// Writes every email as an `.eml` file into a spool directory instead of sending it.
+pub struct FileTransport {
+    transport: AsyncFileTransport<Tokio1Executor>,
+    spool_dir: PathBuf,
+    sender: SubscriberEmail,
+}
+
+impl FileTransport {
+    pub fn new(spool_dir: impl Into<PathBuf>, sender: SubscriberEmail) -> Self {
+        let spool_dir = spool_dir.into();
+        Self {
+            transport: AsyncFileTransport::new(&spool_dir),
+            spool_dir,
+            sender,
+        }
+    }
+}
+
+#[async_trait]
+impl EmailTransport for FileTransport {
+    async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_body: &str,
+        text_body: &str,
+        headers: &[EmailHeader],
+    ) -> anyhow::Result<()> {
+        let message = super::build_message(
+            &self.sender,
+            recipient,
+            subject,
+            html_body,
+            text_body,
+            headers,
+        )?;
+        tokio::fs::create_dir_all(&self.spool_dir)
+            .await
+            .context("Failed to create the spool directory.")?;
+        self.transport
+            .send(message)
+            .await
+            .context("Failed to write the email to the spool directory.")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::{faker::internet::en::SafeEmail, Fake};
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn send_email_writes_an_eml_file_to_the_spool_directory() {
+        // Arrange
+        let spool_dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let sender = SubscriberEmail::parse(SafeEmail().fake()).unwrap();
+        let recipient = SubscriberEmail::parse(SafeEmail().fake()).unwrap();
+        let transport = FileTransport::new(&spool_dir, sender);
+        let headers = [EmailHeader::new(
+            "List-Unsubscribe",
+            "<https://example.com>",
+        )];
+
+        // Act
+        transport
+            .send_email(&recipient, "A subject", "<p>Body</p>", "Body", &headers)
+            .await
+            .unwrap();
+
+        // Assert
+        let mut files = std::fs::read_dir(&spool_dir).unwrap();
+        let eml = std::fs::read_to_string(files.next().unwrap().unwrap().path()).unwrap();
+        assert!(files.next().is_none());
+        assert!(eml.contains(&format!("To: {}", recipient)));
+        assert!(eml.contains("Subject: A subject"));
+        assert!(eml.contains("List-Unsubscribe: <https://example.com>"));
+
+        std::fs::remove_dir_all(spool_dir).unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+use super::{
+    postmark::{ChunkError, RejectedEmail},
+    Email, EmailHeader, EmailTransport,
+};
+use crate::{
+    domain::SubscriberEmail,
+    metrics::{EMAILS_SENT, EMAIL_FAILURES},
+};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Counts the outcome of every email sent through the inner transport.
+pub struct MeteredTransport(pub Arc<dyn EmailTransport>);
+
+impl MeteredTransport {
+    fn record(outcome: &anyhow::Result<()>) {
+        match outcome {
+            Ok(()) => EMAILS_SENT.inc(),
+            Err(e) => EMAIL_FAILURES
+                .with_label_values(&[&failure_reason(e)])
+                .inc(),
+        }
+    }
+}
+
+#[async_trait]
+impl EmailTransport for MeteredTransport {
+    async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_body: &str,
+        text_body: &str,
+        headers: &[EmailHeader],
+    ) -> anyhow::Result<()> {
+        let outcome = self
+            .0
+            .send_email(recipient, subject, html_body, text_body, headers)
+            .await;
+        Self::record(&outcome);
+        outcome
+    }
+
+    async fn send_batch(&self, emails: &[Email]) -> Vec<anyhow::Result<()>> {
+        let outcomes = self.0.send_batch(emails).await;
+        outcomes.iter().for_each(Self::record);
+        outcomes
+    }
+}
+
+/// A label for the cause of a failure, from a small set of values.
+fn failure_reason(e: &anyhow::Error) -> String {
+    for cause in e.chain() {
+        if let Some(e) = cause.downcast_ref::<ChunkError>() {
+            return failure_reason(&e.0);
+        }
+        if let Some(e) = cause.downcast_ref::<RejectedEmail>() {
+            return format!("rejected_{}", e.code);
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            return match e.status() {
+                Some(status) => format!("http_{}", status.as_u16()),
+                None if e.is_timeout() => "timeout".into(),
+                None if e.is_connect() => "connection".into(),
+                None => "request".into(),
+            };
+        }
+        if cause
+            .downcast_ref::<lettre::transport::smtp::Error>()
+            .is_some()
+        {
+            return "smtp".into();
+        }
+    }
+    "other".into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn rejections_are_labelled_with_their_error_code() {
+        let e = anyhow::Error::from(RejectedEmail {
+            code: 406,
+            message: "Inactive recipient".into(),
+        });
+        assert_eq!(failure_reason(&e), "rejected_406");
+    }
+
+    #[test]
+    fn chunk_failures_are_labelled_with_their_cause() {
+        let rejected = anyhow::Error::from(RejectedEmail {
+            code: 300,
+            message: "Invalid email request".into(),
+        })
+        .context("Failed to send the chunk.");
+        let e = anyhow::Error::from(ChunkError(Arc::new(rejected)));
+        assert_eq!(failure_reason(&e), "rejected_300");
+    }
+
+    #[test]
+    fn unknown_failures_are_labelled_as_other() {
+        let e = Err::<(), _>(std::io::Error::other("disk full"))
+            .context("Failed to write the email.")
+            .unwrap_err();
+        assert_eq!(failure_reason(&e), "other");
+    }
+}
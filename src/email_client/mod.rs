@@ -0,0 +1,114 @@
+mod file;
+mod metered;
+mod postmark;
+mod smtp;
+
+pub use file::FileTransport;
+pub use metered::MeteredTransport;
+pub use postmark::PostmarkTransport;
+pub use smtp::SmtpTransport;
+
+use crate::domain::SubscriberEmail;
+use anyhow::Context;
+use async_trait::async_trait;
+use lettre::message::{
+    header::{HeaderName, HeaderValue},
+    Mailbox, MultiPart,
+};
+use serde::Serialize;
+
+/// Delivers emails on behalf of the sender it has been configured with.
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_body: &str,
+        text_body: &str,
+        headers: &[EmailHeader],
+    ) -> anyhow::Result<()>;
+
+    /// Sends every email, returning one outcome per email in the same order.
+    ///
+    /// Transports without a batch API send the emails one by one.
+    async fn send_batch(&self, emails: &[Email]) -> Vec<anyhow::Result<()>> {
+        let mut outcomes = Vec::with_capacity(emails.len());
+        for e in emails {
+            let outcome = self
+                .send_email(
+                    &e.recipient,
+                    &e.subject,
+                    &e.html_body,
+                    &e.text_body,
+                    &e.headers,
+                )
+                .await;
+            outcomes.push(outcome);
+        }
+        outcomes
+    }
+}
+
+/// An email addressed to a single recipient.
+#[derive(Debug)]
+pub struct Email {
+    pub recipient: SubscriberEmail,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+    pub headers: Vec<EmailHeader>,
+}
+
+/// A custom header attached to an outgoing email.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailHeader {
+    pub name: String,
+    pub value: String,
+}
+
+impl EmailHeader {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
+
+/// Builds a MIME message for the transports that deal with raw emails.
+fn build_message(
+    sender: &SubscriberEmail,
+    recipient: &SubscriberEmail,
+    subject: &str,
+    html_body: &str,
+    text_body: &str,
+    headers: &[EmailHeader],
+) -> anyhow::Result<lettre::Message> {
+    let mailbox = |e: &SubscriberEmail| -> anyhow::Result<Mailbox> {
+        e.as_ref()
+            .parse()
+            .with_context(|| format!("{} is not a valid mailbox.", e))
+    };
+
+    let mut message = lettre::Message::builder()
+        .from(mailbox(sender)?)
+        .to(mailbox(recipient)?)
+        .subject(subject)
+        .multipart(MultiPart::alternative_plain_html(
+            text_body.to_owned(),
+            html_body.to_owned(),
+        ))
+        .context("Failed to build the email message.")?;
+
+    for h in headers {
+        let name = HeaderName::new_from_ascii(h.name.clone())
+            .with_context(|| format!("{} is not a valid header name.", h.name))?;
+        message
+            .headers_mut()
+            .insert_raw(HeaderValue::new(name, h.value.clone()));
+    }
+
+    Ok(message)
+}
@@ -0,0 +1,416 @@
+use super::{Email, EmailHeader, EmailTransport};
+use crate::domain::SubscriberEmail;
+use anyhow::Context;
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, sync::Arc, time::Duration};
+
+/// The maximum number of messages Postmark accepts in a single batch request.
+const MAX_BATCH_SIZE: usize = 500;
+
+/// Sends emails through Postmark's JSON API.
+pub struct PostmarkTransport {
+    client: Client,
+    url: Url,
+    sender: SubscriberEmail,
+    auth_token: SecretString,
+}
+
+impl PostmarkTransport {
+    pub fn new(
+        url: Url,
+        sender: SubscriberEmail,
+        auth_token: SecretString,
+        timeout: Duration,
+    ) -> Self {
+        let client = Client::builder().timeout(timeout).build().unwrap();
+
+        Self {
+            client,
+            url,
+            sender,
+            auth_token,
+        }
+    }
+
+    /// Sends up to `MAX_BATCH_SIZE` emails with a single call to the batch endpoint.
+    async fn send_chunk(&self, emails: &[Email]) -> anyhow::Result<Vec<anyhow::Result<()>>> {
+        let body: Vec<_> = emails
+            .iter()
+            .map(|e| SendEmailRequest {
+                from: self.sender.as_ref(),
+                to: e.recipient.as_ref(),
+                subject: &e.subject,
+                text_body: &e.text_body,
+                html_body: &e.html_body,
+                headers: &e.headers,
+            })
+            .collect();
+
+        let url = self
+            .url
+            .join("email/batch")
+            .expect("Given URL cannot fail parsing.");
+
+        let results: Vec<SendBatchResult> = self
+            .client
+            .post(url)
+            .header("X-Postmark-Server-Token", self.auth_token.expose_secret())
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("Failed to parse the response of the batch endpoint.")?;
+
+        anyhow::ensure!(
+            results.len() == emails.len(),
+            "Postmark returned {} results for {} emails.",
+            results.len(),
+            emails.len()
+        );
+
+        let outcomes = results
+            .into_iter()
+            .map(|r| match r.error_code {
+                0 => Ok(()),
+                code => Err(RejectedEmail {
+                    code,
+                    message: r.message,
+                }
+                .into()),
+            })
+            .collect();
+        Ok(outcomes)
+    }
+}
+
+#[async_trait]
+impl EmailTransport for PostmarkTransport {
+    async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_body: &str,
+        text_body: &str,
+        headers: &[EmailHeader],
+    ) -> anyhow::Result<()> {
+        let body = SendEmailRequest {
+            from: self.sender.as_ref(),
+            to: recipient.as_ref(),
+            subject,
+            text_body,
+            html_body,
+            headers,
+        };
+
+        let url = self
+            .url
+            .join("email")
+            .expect("Given URL cannot fail parsing.");
+
+        self.client
+            .post(url)
+            .header("X-Postmark-Server-Token", self.auth_token.expose_secret())
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn send_batch(&self, emails: &[Email]) -> Vec<anyhow::Result<()>> {
+        let mut outcomes = Vec::with_capacity(emails.len());
+        for chunk in emails.chunks(MAX_BATCH_SIZE) {
+            match self.send_chunk(chunk).await {
+                Ok(o) => outcomes.extend(o),
+                // The whole chunk has been rejected, every email in it has failed.
+                Err(e) => {
+                    let e = ChunkError(Arc::new(e));
+                    outcomes.extend(chunk.iter().map(|_| Err(e.clone().into())));
+                }
+            }
+        }
+        outcomes
+    }
+}
+
+/// An email of a batch that Postmark refused to send.
+#[derive(thiserror::Error, Debug)]
+#[error("{message} (error code {code})")]
+pub struct RejectedEmail {
+    pub code: i64,
+    pub message: String,
+}
+
+/// The failure of a whole chunk, reported for each of its emails.
+#[derive(Debug, Clone)]
+pub struct ChunkError(pub Arc<anyhow::Error>);
+
+impl Display for ChunkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#}", self.0)
+    }
+}
+
+impl std::error::Error for ChunkError {}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    text_body: &'a str,
+    html_body: &'a str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    headers: &'a [EmailHeader],
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendBatchResult {
+    error_code: i64,
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+    use fake::{
+        faker::{
+            internet::en::SafeEmail,
+            lorem::en::{Paragraph, Sentence},
+        },
+        Fake, Faker,
+    };
+    use wiremock::{matchers, Match, Mock, MockServer, Request, ResponseTemplate};
+
+    struct SendEmailBodyMatcher;
+
+    impl Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let body: serde_json::Value = match request.body_json() {
+                Ok(v) => v,
+                Err(_) => return false,
+            };
+
+            body.get("From").is_some()
+                && body.get("To").is_some()
+                && body.get("Subject").is_some()
+                && body.get("HtmlBody").is_some()
+                && body.get("TextBody").is_some()
+        }
+    }
+
+    fn email_client(mock_server: &MockServer) -> PostmarkTransport {
+        let parse = mock_server.uri().parse().unwrap();
+        let sender = SubscriberEmail::parse(SafeEmail().fake()).unwrap();
+        let auth_token = SecretString::from(Faker.fake::<String>());
+        let timeout = Duration::from_millis(200);
+        PostmarkTransport::new(parse, sender, auth_token, timeout)
+    }
+
+    /// Generates fake data and sends an email request to the given `MockServer` by using
+    /// `PostmarkTransport::send_email`.
+    async fn send_fake_email(mock_server: &MockServer) -> anyhow::Result<()> {
+        let email_client = email_client(mock_server);
+
+        // Arrange - Generate Fake Data
+        let subscriber_email = SubscriberEmail::parse(SafeEmail().fake()).unwrap();
+        let content: String = Paragraph(1..10).fake();
+        let subject: String = Sentence(1..2).fake();
+
+        email_client
+            .send_email(&subscriber_email, &subject, &content, &content, &[])
+            .await
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        // Arrange
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::header_exists("X-Postmark-Server-Token"))
+            .and(matchers::header("Content-Type", "application/json"))
+            .and(matchers::path("/email"))
+            .and(matchers::method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // Act
+        let _ = send_fake_email(&server).await;
+
+        // Assert
+    }
+
+    #[tokio::test]
+    async fn send_email_includes_the_given_headers() {
+        // Arrange
+        let server = MockServer::start().await;
+        let headers = [EmailHeader::new(
+            "List-Unsubscribe",
+            "<https://example.com>",
+        )];
+
+        Mock::given(matchers::body_partial_json(serde_json::json!({
+            "Headers": [{ "Name": "List-Unsubscribe", "Value": "<https://example.com>" }]
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+        // Act
+        let recipient = SubscriberEmail::parse(SafeEmail().fake()).unwrap();
+        let outcome = email_client(&server)
+            .send_email(&recipient, "Subject", "<p>Body</p>", "Body", &headers)
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    fn fake_emails(n: usize) -> Vec<Email> {
+        (0..n)
+            .map(|_| Email {
+                recipient: SubscriberEmail::parse(SafeEmail().fake()).unwrap(),
+                subject: Sentence(1..2).fake(),
+                html_body: Paragraph(1..10).fake(),
+                text_body: Paragraph(1..10).fake(),
+                headers: vec![],
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn send_batch_reports_the_outcome_of_each_email() {
+        // Arrange
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::path("/email/batch"))
+            .and(matchers::method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "ErrorCode": 0, "Message": "OK" },
+                { "ErrorCode": 406, "Message": "Inactive recipient" },
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // Act
+        let outcomes = email_client(&server).send_batch(&fake_emails(2)).await;
+
+        // Assert
+        assert_eq!(2, outcomes.len());
+        assert_ok!(&outcomes[0]);
+        assert_err!(&outcomes[1]);
+    }
+
+    #[tokio::test]
+    async fn send_batch_fails_every_email_if_the_server_returns_500() {
+        // Arrange
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // Act
+        let outcomes = email_client(&server).send_batch(&fake_emails(3)).await;
+
+        // Assert
+        assert_eq!(3, outcomes.len());
+        assert!(outcomes.iter().all(|o| o.is_err()));
+    }
+
+    #[tokio::test]
+    async fn send_batch_splits_large_batches() {
+        // Arrange
+        let server = MockServer::start().await;
+        let ok = serde_json::json!({ "ErrorCode": 0, "Message": "OK" });
+
+        Mock::given(matchers::path("/email/batch"))
+            .respond_with(move |r: &Request| {
+                let n = r.body_json::<Vec<serde_json::Value>>().unwrap().len();
+                ResponseTemplate::new(200).set_body_json(vec![ok.clone(); n])
+            })
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        // Act
+        let outcomes = email_client(&server)
+            .send_batch(&fake_emails(MAX_BATCH_SIZE + 1))
+            .await;
+
+        // Assert
+        assert_eq!(MAX_BATCH_SIZE + 1, outcomes.len());
+        assert!(outcomes.iter().all(|o| o.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn send_email_succeeds_if_the_server_returns_200() {
+        // Arrange
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // Act
+        let outcome = send_fake_email(&server).await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        // Arrange
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // Act
+        let outcome = send_fake_email(&server).await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        // Arrange
+        let server = MockServer::start().await;
+
+        let response = ResponseTemplate::new(200).set_delay(Duration::from_secs(180));
+        Mock::given(matchers::any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // Act
+        let outcome = send_fake_email(&server).await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+}
@@ -0,0 +1,73 @@
+use super::{EmailHeader, EmailTransport};
+use crate::domain::SubscriberEmail;
+use anyhow::Context;
+use async_trait::async_trait;
+use lettre::{
+    transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport,
+    Tokio1Executor,
+};
+use secrecy::{ExposeSecret, SecretString};
+use std::time::Duration;
+
+/// Sends emails through an SMTP relay.
+pub struct SmtpTransport {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: SubscriberEmail,
+}
+
+impl SmtpTransport {
+    /// Connects to the relay over STARTTLS, or in plain text when `starttls` is disabled.
+    pub fn new(
+        host: &str,
+        port: u16,
+        credentials: Option<(String, SecretString)>,
+        starttls: bool,
+        sender: SubscriberEmail,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        let mut builder = if starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                .context("Failed to set up the SMTP relay.")?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+        };
+        builder = builder.port(port).timeout(Some(timeout));
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(Credentials::new(
+                username,
+                password.expose_secret().to_owned(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            sender,
+        })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpTransport {
+    async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_body: &str,
+        text_body: &str,
+        headers: &[EmailHeader],
+    ) -> anyhow::Result<()> {
+        let message = super::build_message(
+            &self.sender,
+            recipient,
+            subject,
+            html_body,
+            text_body,
+            headers,
+        )?;
+        self.transport
+            .send(message)
+            .await
+            .context("The SMTP relay rejected the email.")?;
+        Ok(())
+    }
+}
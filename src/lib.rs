@@ -4,8 +4,11 @@ pub mod config;
 pub mod domain;
 pub mod email_client;
 pub mod idempotency;
+pub mod metrics;
 pub mod routes;
 pub mod session_state;
+pub mod supervisor;
+pub mod suppression;
 pub mod telemetry;
 pub mod utils;
 pub mod workers;
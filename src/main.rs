@@ -1,56 +1,223 @@
-use std::fmt::{Debug, Display};
-use tokio::task::JoinError;
+use clap::{Args, Parser, Subcommand};
+use std::future::Future;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 use zero2prod::{
     app::App,
-    config, telemetry,
-    workers::{expiration, issue_delivery},
+    config::{self, Settings},
+    supervisor::{self, report_exit, supervise, RestartPolicy},
+    telemetry,
+    workers::{confirmation_email, expiration, issue_delivery, publishing},
 };
 
+/// Runs the newsletter's API and background workers, together or apart.
+///
+/// Workers coordinate through Postgres, so any number of processes can run them. Processes
+/// running workers without the API serve the health probes and metrics on the metrics port.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Runs everything when omitted.
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Serve the web pages and the JSON API.
+    Serve,
+    /// Send newsletter issues and confirmation emails.
+    Deliver(DeliveryArgs),
+    /// Publish scheduled issues once they are due.
+    Publish,
+    /// Prune expired idempotency keys, confirmation tokens and unconfirmed subscriptions.
+    Expire,
+    /// Run the API and every worker in a single process.
+    All(DeliveryArgs),
+}
+
+#[derive(Args, Clone)]
+struct DeliveryArgs {
+    /// Issue delivery workers to run concurrently.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    delivery_workers: u16,
+    /// Confirmation email workers to run concurrently.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    confirmation_workers: u16,
+}
+
+impl Default for DeliveryArgs {
+    fn default() -> Self {
+        Self {
+            delivery_workers: 1,
+            confirmation_workers: 1,
+        }
+    }
+}
+
 #[actix_web::main]
 async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
     // binary init - telemetry
     let subscriber = telemetry::get_subscriber("zero2prod", "info", std::io::stdout);
     telemetry::init_subscriber(subscriber);
 
-    // build the app and workers
     let config = config::get().expect("Failed to read configuration");
-    let app = {
-        let f = App::build(&config).await?.run_until_stopped();
-        tokio::spawn(f)
+    let command = cli
+        .command
+        .unwrap_or_else(|| Command::All(DeliveryArgs::default()));
+    let (serve, delivery, publish, expire) = match command {
+        Command::Serve => (true, None, false, false),
+        Command::Deliver(args) => (false, Some(args), false, false),
+        Command::Publish => (false, None, true, false),
+        Command::Expire => (false, None, false, true),
+        Command::All(args) => (true, Some(args), true, true),
     };
-    let issue_delivery_worker = {
-        let f = issue_delivery::Worker::builder(&config).finish();
-        tokio::spawn(f)
-    };
-    let expiration_worker = {
-        let f = expiration::Worker::builder(&config).finish();
-        tokio::spawn(f)
+
+    let mut local_workers = Vec::new();
+    if delivery.is_some() {
+        local_workers.extend([issue_delivery::NAME, confirmation_email::NAME]);
+    }
+    if publish {
+        local_workers.push(publishing::NAME);
+    }
+    if expire {
+        local_workers.push(expiration::NAME);
+    }
+
+    // build the app, or just the probes and metrics without it, and the workers
+    let shutdown = CancellationToken::new();
+    let mut workers = JoinSet::new();
+    let (app, server_name) = if serve {
+        (App::build(&config, &local_workers).await?, "API")
+    } else {
+        (
+            App::build_monitoring(&config, &local_workers)?,
+            "Monitoring server",
+        )
     };
+    let server = app.handle();
+    let mut server_task = tokio::spawn(app.run_until_stopped());
+    if let Some(args) = delivery {
+        spawn_supervised(
+            &mut workers,
+            "Issue Delivery Background Worker",
+            args.delivery_workers,
+            &shutdown,
+            worker_factory(&config, |c, s| issue_delivery::Worker::builder(c).finish(s)),
+        );
+        spawn_supervised(
+            &mut workers,
+            "Confirmation Email Background Worker",
+            args.confirmation_workers,
+            &shutdown,
+            worker_factory(&config, |c, s| {
+                confirmation_email::Worker::builder(c).finish(s)
+            }),
+        );
+    }
+    if publish {
+        spawn_supervised(
+            &mut workers,
+            "Publishing Background Worker",
+            1,
+            &shutdown,
+            worker_factory(&config, |c, s| publishing::Worker::builder(c).finish(s)),
+        );
+    }
+    if expire {
+        spawn_supervised(
+            &mut workers,
+            "Expiration Background Worker",
+            1,
+            &shutdown,
+            worker_factory(&config, |c, s| expiration::Worker::builder(c).finish(s)),
+        );
+    }
 
-    // run concurrently
-    tokio::select!(
-        o = app => report_exit("API", o),
-        o = expiration_worker => report_exit("Expiration Background Worker", o),
-        o = issue_delivery_worker => report_exit("Issue Delivery Background Worker", o),
+    // run until asked to stop, or until the server goes down
+    let server_exited = tokio::select!(
+        o = supervisor::shutdown_signal() => {
+            if let Err(e) = o {
+                tracing::error!(error.cause_chain = ?e, "Failed to listen for shutdown signals");
+            }
+            tracing::info!("Shutting down");
+            false
+        }
+        o = &mut server_task => {
+            report_exit(server_name, o);
+            true
+        }
     );
+    shutdown.cancel();
+
+    // let in-flight requests and worker tasks complete within the deadline
+    let drain = async {
+        server.stop(true).await;
+        if !server_exited {
+            report_exit(server_name, server_task.await);
+        }
+        while workers.join_next().await.is_some() {}
+    };
+    let deadline = config.application.shutdown_timeout();
+    if tokio::time::timeout(deadline, drain).await.is_err() {
+        tracing::warn!("Some tasks did not complete within {:?}", deadline);
+    }
 
     Ok(())
 }
 
-fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
-    match outcome {
-        Ok(Ok(_)) => tracing::info!("{} has exited", task_name),
-        Ok(Err(e)) => tracing::error!(
-                error.cause_chain = ?e,
-                error.message = %e,
-                "{} failed",
-                task_name
-        ),
-        Err(e) => tracing::error!(
-                error.cause_chain = ?e,
-                error.message = %e,
-                "{} task failed to complete",
-                task_name
-        ),
+/// Builds a fresh worker from the configuration every time the supervisor (re)starts it.
+fn worker_factory<Fut>(
+    config: &Settings,
+    run: fn(&Settings, CancellationToken) -> Fut,
+) -> impl FnMut(CancellationToken) -> Fut + Clone + Send + 'static
+where
+    Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let config = config.clone();
+    move |shutdown| run(&config, shutdown)
+}
+
+/// Runs `n` supervised copies of a worker.
+fn spawn_supervised<F, Fut>(
+    workers: &mut JoinSet<()>,
+    name: &str,
+    n: u16,
+    shutdown: &CancellationToken,
+    spawn_task: F,
+) where
+    F: FnMut(CancellationToken) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    for i in 1..=n {
+        let name = match n {
+            1 => name.to_owned(),
+            _ => format!("{} #{}", name, i),
+        };
+        workers.spawn(supervise(
+            name,
+            RestartPolicy::default(),
+            shutdown.clone(),
+            spawn_task.clone(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_is_well_formed() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn worker_counts_must_be_positive() {
+        let result = Cli::try_parse_from(["zero2prod", "deliver", "--delivery-workers", "0"]);
+        assert!(result.is_err());
     }
 }
@@ -0,0 +1,168 @@
+//! The Prometheus metrics of the application, served by `routes::metrics`.
+//!
+//! Counters and histograms are recorded as things happen, while gauges are sampled
+//! by the scraping request.
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::{sync::LazyLock, time::Instant};
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(collector: T) -> T {
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("Metrics are registered once.");
+    collector
+}
+
+pub static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status."),
+            &["method", "route", "status"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static HTTP_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to answer HTTP requests, by route.",
+            ),
+            &["method", "route"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static EMAILS_SENT: LazyLock<IntCounter> = LazyLock::new(|| {
+    register(
+        IntCounter::new(
+            "emails_sent_total",
+            "Emails accepted by the email provider.",
+        )
+        .unwrap(),
+    )
+});
+
+pub static EMAIL_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "email_failures_total",
+                "Emails that could not be sent, by reason.",
+            ),
+            &["reason"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static PASSWORD_VERIFICATION_DURATION: LazyLock<Histogram> = LazyLock::new(|| {
+    register(
+        Histogram::with_opts(HistogramOpts::new(
+            "password_verification_duration_seconds",
+            "Time taken to verify a password against its Argon2 hash.",
+        ))
+        .unwrap(),
+    )
+});
+
+pub static DELIVERY_QUEUE_DEPTH: LazyLock<IntGauge> = LazyLock::new(|| {
+    register(
+        IntGauge::new(
+            "issue_delivery_queue_depth",
+            "Emails waiting in the issue delivery queue.",
+        )
+        .unwrap(),
+    )
+});
+
+pub static DELIVERY_QUEUE_OLDEST_TASK_AGE: LazyLock<IntGauge> = LazyLock::new(|| {
+    register(
+        IntGauge::new(
+            "issue_delivery_queue_oldest_task_age_seconds",
+            "Time spent in the issue delivery queue by its oldest task.",
+        )
+        .unwrap(),
+    )
+});
+
+pub static DB_POOL_CONNECTIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register(
+        IntGaugeVec::new(
+            Opts::new(
+                "db_pool_connections",
+                "Connections of the Postgres pool, by state.",
+            ),
+            &["state"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static DB_POOL_MAX_CONNECTIONS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register(
+        IntGauge::new(
+            "db_pool_max_connections",
+            "Connections the Postgres pool can open.",
+        )
+        .unwrap(),
+    )
+});
+
+/// Encodes every metric in the Prometheus text format.
+pub fn render() -> anyhow::Result<String> {
+    // Make sure metrics that have not been recorded yet are exposed anyway
+    LazyLock::force(&HTTP_REQUESTS);
+    LazyLock::force(&HTTP_REQUEST_DURATION);
+    LazyLock::force(&EMAILS_SENT);
+    LazyLock::force(&EMAIL_FAILURES);
+    LazyLock::force(&PASSWORD_VERIFICATION_DURATION);
+    LazyLock::force(&DELIVERY_QUEUE_DEPTH);
+    LazyLock::force(&DELIVERY_QUEUE_OLDEST_TASK_AGE);
+    LazyLock::force(&DB_POOL_CONNECTIONS);
+    LazyLock::force(&DB_POOL_MAX_CONNECTIONS);
+
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+/// Counts and times every request by the route pattern it matched, to keep the number
+/// of series bounded.
+pub async fn record_http_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let method = req.method().to_string();
+    let start = Instant::now();
+    let outcome = next.call(req).await;
+    let (route, status) = match &outcome {
+        Ok(res) => (
+            res.request()
+                .match_pattern()
+                .unwrap_or_else(|| "unmatched".into()),
+            res.status(),
+        ),
+        // Middleware errors are raised before the matched route can be read back
+        Err(e) => ("unknown".into(), e.as_response_error().status_code()),
+    };
+    HTTP_REQUESTS
+        .with_label_values(&[&method, &route, status.as_str()])
+        .inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[&method, &route])
+        .observe(start.elapsed().as_secs_f64());
+    outcome
+}
@@ -0,0 +1,108 @@
+use crate::{auth::reject_non_admins, utils};
+use actix_web::{get, http::header, middleware::from_fn, web, HttpResponse, Responder};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal as escape;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Write;
+use uuid::Uuid;
+
+#[get("/api-keys", wrap = "from_fn(reject_non_admins)")]
+pub async fn api_keys(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", escape(m.content())).unwrap();
+    }
+
+    let mut keys_html = String::new();
+    for k in get_api_keys(pool.as_ref()).await.map_err(utils::e500)? {
+        let status_html = match k.revoked_at {
+            Some(t) => format!("Revoked {}", t.to_rfc3339()),
+            None => format!(
+                r#"<form action="/admin/api-keys/{}/revoke" method="post">
+                        <button type="submit">Revoke</button>
+                    </form>"#,
+                k.id
+            ),
+        };
+        writeln!(
+            keys_html,
+            r#"<tr>
+                <td>{name}</td>
+                <td>{created_by}</td>
+                <td>{created_at}</td>
+                <td>{last_used_at}</td>
+                <td>{status_html}</td>
+            </tr>"#,
+            name = escape(&k.name),
+            created_by = escape(&k.created_by),
+            created_at = k.created_at.to_rfc3339(),
+            last_used_at = k.last_used_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>API keys</title>
+    </head>
+    <body>
+        {msg_html}
+        <table>
+            <tr>
+                <th>Name</th>
+                <th>Created by</th>
+                <th>Created</th>
+                <th>Last used</th>
+                <th>Status</th>
+            </tr>
+            {keys_html}
+        </table>
+        <h2>Create an API key</h2>
+        <form action="/admin/api-keys" method="post">
+            <label>Name
+                <input type="text" name="name" placeholder="What the key is for">
+            </label>
+            <button type="submit">Create</button>
+        </form>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+        )))
+}
+
+struct ApiKeyRow {
+    id: Uuid,
+    name: String,
+    created_by: String,
+    created_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(skip(exec))]
+async fn get_api_keys(exec: impl PgExecutor<'_>) -> anyhow::Result<Vec<ApiKeyRow>> {
+    sqlx::query_as!(
+        ApiKeyRow,
+        r#"
+        SELECT k.id, k.name, u.username AS created_by, k.created_at, k.last_used_at, k.revoked_at
+        FROM api_keys k
+        JOIN users u ON u.id = k.created_by
+        ORDER BY k.revoked_at IS NOT NULL, k.created_at DESC
+        "#
+    )
+    .fetch_all(exec)
+    .await
+    .context("Failed to retrieve the API keys.")
+}
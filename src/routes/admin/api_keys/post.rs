@@ -0,0 +1,73 @@
+use crate::{
+    auth::{self, reject_non_admins, UserId},
+    utils,
+};
+use actix_web::{http::header, middleware::from_fn, post, web, HttpResponse, Responder};
+use actix_web_flash_messages::FlashMessage;
+use htmlescape::encode_minimal as escape;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize)]
+struct FormData {
+    name: String,
+}
+
+/// Creates a key and shows it once - only its hash is stored.
+#[post("/api-keys", wrap = "from_fn(reject_non_admins)")]
+#[tracing::instrument(name = "Create an API key", skip_all, fields(user_id = %*user_id, name = %form.name))]
+pub async fn create_api_key(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> actix_web::Result<impl Responder> {
+    let name = form.0.name.trim().to_owned();
+    if name.is_empty() {
+        FlashMessage::error("Please name the API key.").send();
+        return Ok(utils::see_other("/admin/api-keys"));
+    }
+
+    let key = auth::create_api_key(&name, **user_id, pool.as_ref())
+        .await
+        .map_err(utils::e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>New API key</title>
+    </head>
+    <body>
+        <p>The API key {name} has been created. Copy it now, it will not be shown again:</p>
+        <p><code>{key}</code></p>
+        <p>Send it in the <code>Authorization: Bearer</code> header of your requests to <code>/api/v1</code>.</p>
+        <p><a href="/admin/api-keys">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+            name = escape(&name),
+            key = key.expose_secret(),
+        )))
+}
+
+#[post("/api-keys/{key_id}/revoke", wrap = "from_fn(reject_non_admins)")]
+#[tracing::instrument(name = "Revoke an API key", skip(pool))]
+pub async fn revoke_api_key(
+    key_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    if auth::revoke_api_key(*key_id, pool.as_ref())
+        .await
+        .map_err(utils::e500)?
+    {
+        FlashMessage::info("The API key has been revoked.").send();
+    } else {
+        FlashMessage::error("The API key has already been revoked.").send();
+    }
+    Ok(utils::see_other("/admin/api-keys"))
+}
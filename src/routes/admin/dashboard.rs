@@ -29,7 +29,15 @@ pub async fn admin_dashboard(
         <p>Available actions:</p>
         <ol>
             <li><a href="/admin/newsletters">Send a newsletter issue</a></li>
+            <li><a href="/admin/newsletters/issues">Browse past issues</a></li>
+            <li><a href="/admin/deliveries/failed">Review failed deliveries</a></li>
+            <li><a href="/admin/subscribers">Import or export subscribers</a></li>
+            <li><a href="/admin/lists">Manage lists</a></li>
+            <li><a href="/admin/segments">Manage segments</a></li>
+            <li><a href="/admin/users">Manage users</a></li>
+            <li><a href="/admin/api-keys">Manage API keys</a></li>
             <li><a href="/admin/password">Change password</a></li>
+            <li><a href="/admin/security">Two-factor authentication</a></li>
             <li>
                 <form name="logoutForm" action="/admin/logout" method="post">
                     <input type="submit" value="Logout">
@@ -0,0 +1,115 @@
+use crate::utils;
+use actix_web::{get, http::header, web, HttpResponse, Responder};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal as escape;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Write;
+use uuid::Uuid;
+
+#[get("/deliveries/failed")]
+pub async fn failed_deliveries(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let mut rows_html = String::new();
+    for d in get_failed_deliveries(pool.as_ref())
+        .await
+        .map_err(utils::e500)?
+    {
+        writeln!(
+            rows_html,
+            r#"<tr>
+                <td>{title}</td>
+                <td>{email}</td>
+                <td>{n_retries}</td>
+                <td>{last_error}</td>
+                <td>{failed_at}</td>
+                <td>
+                    <form action="/admin/deliveries/failed/requeue" method="post">
+                        <input hidden type="text" name="newsletter_issue_id" value="{issue_id}">
+                        <input hidden type="text" name="subscriber_email" value="{email}">
+                        <button type="submit">Re-queue</button>
+                    </form>
+                </td>
+            </tr>"#,
+            title = escape(&d.title),
+            email = escape(&d.subscriber_email),
+            n_retries = d.n_retries,
+            last_error = escape(&d.last_error),
+            failed_at = d.failed_at.to_rfc3339(),
+            issue_id = d.newsletter_issue_id,
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html> 
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Failed Deliveries</title>
+    </head>
+    <body>
+        {msg_html}
+        <table>
+            <tr>
+                <th>Issue</th>
+                <th>Subscriber</th>
+                <th>Retries</th>
+                <th>Last error</th>
+                <th>Failed at</th>
+                <th></th>
+            </tr>
+            {rows_html}
+        </table>
+        <form action="/admin/deliveries/failed/requeue" method="post">
+            <button type="submit">Re-queue all</button>
+        </form>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+        )))
+}
+
+struct FailedDelivery {
+    newsletter_issue_id: Uuid,
+    title: String,
+    subscriber_email: String,
+    n_retries: i32,
+    last_error: String,
+    failed_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_failed_deliveries(exec: impl PgExecutor<'_>) -> anyhow::Result<Vec<FailedDelivery>> {
+    let deliveries = sqlx::query_as!(
+        FailedDelivery,
+        r#"
+        SELECT
+            f.newsletter_issue_id,
+            i.title,
+            f.subscriber_email,
+            f.n_retries,
+            f.last_error,
+            f.failed_at
+        FROM failed_deliveries f
+        JOIN newsletter_issues i ON i.id = f.newsletter_issue_id
+        ORDER BY f.failed_at DESC
+        "#
+    )
+    .fetch_all(exec)
+    .await
+    .context("Failed to retrieve failed deliveries.")?;
+    Ok(deliveries)
+}
@@ -0,0 +1,31 @@
+use crate::{auth::reject_non_editors, utils, workers::issue_delivery::requeue_failed_tasks};
+use actix_web::{middleware::from_fn, post, web, Responder};
+use actix_web_flash_messages::FlashMessage;
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Identifies a single failed delivery. Re-queues every failed delivery when omitted.
+#[derive(Deserialize)]
+struct FormData {
+    newsletter_issue_id: Option<Uuid>,
+    subscriber_email: Option<String>,
+}
+
+#[post("/deliveries/failed/requeue", wrap = "from_fn(reject_non_editors)")]
+#[tracing::instrument(name = "Re-queue failed deliveries", skip_all)]
+pub async fn requeue_failed_deliveries(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let n = requeue_failed_tasks(
+        form.newsletter_issue_id,
+        form.subscriber_email.as_deref(),
+        pool.as_ref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+
+    FlashMessage::info(format!("{n} failed deliveries have been re-queued.")).send();
+    Ok(utils::see_other("/admin/deliveries/failed"))
+}
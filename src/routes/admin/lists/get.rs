@@ -0,0 +1,100 @@
+use crate::{auth::reject_non_admins, utils};
+use actix_web::{get, http::header, middleware::from_fn, web, HttpResponse, Responder};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use htmlescape::encode_minimal as escape;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Write;
+
+#[get("/lists", wrap = "from_fn(reject_non_admins)")]
+pub async fn lists(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", escape(m.content())).unwrap();
+    }
+
+    let mut lists_html = String::new();
+    for l in get_list_summaries(pool.as_ref())
+        .await
+        .map_err(utils::e500)?
+    {
+        writeln!(
+            lists_html,
+            r#"<tr>
+                <td>{name}</td>
+                <td>{description}</td>
+                <td>{n_confirmed}</td>
+            </tr>"#,
+            name = escape(&l.name),
+            description = escape(&l.description),
+            n_confirmed = l.n_confirmed,
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Lists</title>
+    </head>
+    <body>
+        {msg_html}
+        <table>
+            <tr>
+                <th>Name</th>
+                <th>Description</th>
+                <th>Confirmed subscribers</th>
+            </tr>
+            {lists_html}
+        </table>
+        <h2>Create a list</h2>
+        <form action="/admin/lists" method="post">
+            <label>Name
+                <input type="text" name="name" placeholder="Weekly digest">
+            </label>
+            <label>Description
+                <input type="text" name="description" placeholder="What subscribers get">
+            </label>
+            <button type="submit">Create</button>
+        </form>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+        )))
+}
+
+struct ListSummary {
+    name: String,
+    description: String,
+    n_confirmed: i64,
+}
+
+#[tracing::instrument(skip(exec))]
+async fn get_list_summaries(exec: impl PgExecutor<'_>) -> anyhow::Result<Vec<ListSummary>> {
+    sqlx::query_as!(
+        ListSummary,
+        r#"
+        SELECT
+            l.name,
+            l.description,
+            COUNT(s.id) AS "n_confirmed!"
+        FROM lists l
+        LEFT JOIN subscription_lists sl ON sl.list_id = l.id
+        LEFT JOIN subscriptions s ON s.id = sl.subscriber_id AND s.status = 'confirmed'
+        GROUP BY l.id
+        ORDER BY l.name
+        "#
+    )
+    .fetch_all(exec)
+    .await
+    .context("Failed to retrieve the lists.")
+}
@@ -0,0 +1,109 @@
+mod get;
+mod post;
+
+pub use get::lists;
+pub use post::create_list;
+
+use anyhow::Context;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// A named list, or topic, subscribers can opt into.
+pub(crate) struct List {
+    pub(crate) id: Uuid,
+    pub(crate) name: String,
+    pub(crate) description: String,
+}
+
+#[tracing::instrument(skip(exec))]
+pub(crate) async fn get_lists(exec: impl PgExecutor<'_>) -> anyhow::Result<Vec<List>> {
+    sqlx::query_as!(
+        List,
+        "SELECT id, name, description FROM lists ORDER BY name"
+    )
+    .fetch_all(exec)
+    .await
+    .context("Failed to retrieve the lists.")
+}
+
+/// Renders one checkbox per list, named `lists`, ticking the ones in `checked`.
+pub(crate) fn list_checkboxes(all: &[List], checked: &[Uuid]) -> String {
+    all.iter()
+        .map(|l| {
+            format!(
+                r#"<label><input type="checkbox" name="lists" value="{id}"{checked}> {name}</label> {description}<br>"#,
+                id = l.id,
+                checked = if checked.contains(&l.id) { " checked" } else { "" },
+                name = htmlescape::encode_minimal(&l.name),
+                description = htmlescape::encode_minimal(&l.description),
+            )
+        })
+        .collect()
+}
+
+/// Adds the subscriber to the given lists, ignoring unknown ids.
+/// Returns how many lists the subscriber has joined.
+#[tracing::instrument(skip(exec))]
+pub(crate) async fn add_subscriber_to_lists(
+    exec: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+    list_ids: &[Uuid],
+) -> anyhow::Result<u64> {
+    let r = sqlx::query!(
+        r#"
+        INSERT INTO subscription_lists (subscriber_id, list_id)
+        SELECT $1, id FROM lists WHERE id = ANY($2)
+        ON CONFLICT DO NOTHING
+        "#,
+        subscriber_id,
+        list_ids
+    )
+    .execute(exec)
+    .await
+    .context("Failed to add the subscriber to the lists.")?;
+    Ok(r.rows_affected())
+}
+
+/// Removes the subscriber from every list but the given ones.
+#[tracing::instrument(skip(exec))]
+pub(crate) async fn remove_subscriber_from_other_lists(
+    exec: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+    list_ids: &[Uuid],
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+        DELETE FROM subscription_lists
+        WHERE subscriber_id = $1 AND NOT (list_id = ANY($2))
+        "#,
+        subscriber_id,
+        list_ids
+    )
+    .execute(exec)
+    .await
+    .context("Failed to remove the subscriber from the lists.")?;
+    Ok(())
+}
+
+/// Targets the issue at the given lists, ignoring unknown ids.
+/// Returns how many lists the issue targets.
+#[tracing::instrument(skip(exec))]
+pub(crate) async fn set_issue_lists(
+    exec: impl PgExecutor<'_>,
+    issue_id: Uuid,
+    list_ids: &[Uuid],
+) -> anyhow::Result<u64> {
+    let r = sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issue_lists (newsletter_issue_id, list_id)
+        SELECT $1, id FROM lists WHERE id = ANY($2)
+        ON CONFLICT DO NOTHING
+        "#,
+        issue_id,
+        list_ids
+    )
+    .execute(exec)
+    .await
+    .context("Failed to target the issue at the lists.")?;
+    Ok(r.rows_affected())
+}
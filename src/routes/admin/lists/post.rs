@@ -0,0 +1,50 @@
+use crate::{auth::reject_non_admins, utils};
+use actix_web::{middleware::from_fn, post, web, Responder};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize)]
+struct FormData {
+    name: String,
+    #[serde(default)]
+    description: String,
+}
+
+#[post("/lists", wrap = "from_fn(reject_non_admins)")]
+#[tracing::instrument(name = "Create a list", skip_all, fields(name = %form.name))]
+pub async fn create_list(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let name = form.0.name.trim();
+    if name.is_empty() {
+        FlashMessage::error("Please name the list.").send();
+        return Ok(utils::see_other("/admin/lists"));
+    }
+
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO lists (id, name, description, created_at)
+        VALUES ($1, $2, $3, now())
+        ON CONFLICT (name) DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        name,
+        form.0.description.trim()
+    )
+    .execute(pool.as_ref())
+    .await
+    .context("Failed to create the list.")
+    .map_err(utils::e500)?
+    .rows_affected()
+        == 1;
+
+    if created {
+        FlashMessage::info(format!("The list {} has been created.", name)).send();
+    } else {
+        FlashMessage::error(format!("A list named {} already exists.", name)).send();
+    }
+    Ok(utils::see_other("/admin/lists"))
+}
@@ -9,3 +9,24 @@ pub use log_out::logout;
 
 mod newsletter;
 pub use newsletter::*;
+
+mod deliveries;
+pub use deliveries::*;
+
+mod users;
+pub use users::*;
+
+mod security;
+pub use security::*;
+
+mod api_keys;
+pub use api_keys::*;
+
+mod subscribers;
+pub use subscribers::*;
+
+mod lists;
+pub use lists::*;
+
+mod segments;
+pub use segments::*;
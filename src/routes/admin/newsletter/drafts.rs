@@ -0,0 +1,202 @@
+use super::{
+    post::{success_message, Publication},
+    schedule::parse_schedule,
+};
+use crate::{
+    auth::reject_non_editors, domain::SubscriberEmail, email_client::EmailTransport, utils,
+    workers::issue_delivery::enqueue_delivery_tasks,
+};
+use actix_web::{get, http::header, middleware::from_fn, post, web, HttpResponse, Responder};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use htmlescape::encode_minimal as escape;
+use serde::Deserialize;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+#[get("/newsletters/issues/{issue_id}/preview")]
+pub async fn preview_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let Some(issue) = get_issue_content(pool.as_ref(), *issue_id)
+        .await
+        .map_err(utils::e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Preview: {title}</title>
+    </head>
+    <body>
+        <h1>{title}</h1>
+        <h2>HTML</h2>
+        <div>{html_content}</div>
+        <h2>Plain text</h2>
+        <pre>{text_content}</pre>
+        <p><a href="/admin/newsletters/issues/{issue_id}">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+            title = escape(&issue.title),
+            html_content = issue.html_content,
+            text_content = escape(&issue.text_content),
+        )))
+}
+
+#[derive(Deserialize)]
+struct TestSendFormData {
+    email: String,
+}
+
+/// Mails an issue to a single address, leaving the delivery queue untouched.
+#[post(
+    "/newsletters/issues/{issue_id}/test",
+    wrap = "from_fn(reject_non_editors)"
+)]
+#[tracing::instrument(name = "Send a test issue", skip(form, pool, email_client))]
+pub async fn send_test_issue(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<TestSendFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<dyn EmailTransport>,
+) -> actix_web::Result<impl Responder> {
+    let location = format!("/admin/newsletters/issues/{}", issue_id);
+    let recipient = match SubscriberEmail::parse(form.0.email) {
+        Ok(r) => r,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other(&location));
+        }
+    };
+    let Some(issue) = get_issue_content(pool.as_ref(), *issue_id)
+        .await
+        .map_err(utils::e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    email_client
+        .send_email(
+            &recipient,
+            &format!("[Test] {}", issue.title),
+            &issue.html_content,
+            &issue.text_content,
+            &[],
+        )
+        .await
+        .context("Failed to send the test email.")
+        .map_err(utils::e500)?;
+
+    FlashMessage::info(format!("A test email has been sent to {}.", recipient)).send();
+    Ok(utils::see_other(&location))
+}
+
+#[derive(Deserialize)]
+struct PublishFormData {
+    /// Publishes the draft right away when left empty.
+    #[serde(default)]
+    scheduled_for: String,
+}
+
+#[post(
+    "/newsletters/issues/{issue_id}/publish",
+    wrap = "from_fn(reject_non_editors)"
+)]
+#[tracing::instrument(name = "Publish a draft issue", skip(form, pool))]
+pub async fn publish_draft(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<PublishFormData>,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let location = format!("/admin/newsletters/issues/{}", issue_id);
+    let publication = match form.scheduled_for.trim() {
+        "" => Publication::Now,
+        s => match parse_schedule(s) {
+            Ok(t) => Publication::At(t),
+            Err(e) => {
+                FlashMessage::error(e).send();
+                return Ok(utils::see_other(&location));
+            }
+        },
+    };
+
+    let mut txn = pool.begin().await.map_err(utils::e500)?;
+    if !mark_draft_as_published(txn.as_mut(), *issue_id, &publication)
+        .await
+        .map_err(utils::e500)?
+    {
+        FlashMessage::error("Only drafts can be published.").send();
+        return Ok(utils::see_other(&location));
+    }
+    if let Publication::Now = publication {
+        enqueue_delivery_tasks(*issue_id, txn.as_mut())
+            .await
+            .context("Failed to enqueue delivery tasks")
+            .map_err(utils::e500)?;
+    }
+    txn.commit().await.map_err(utils::e500)?;
+
+    success_message(&publication).send();
+    Ok(utils::see_other(&location))
+}
+
+struct IssueContent {
+    title: String,
+    text_content: String,
+    html_content: String,
+}
+
+#[tracing::instrument(skip(exec))]
+async fn get_issue_content(
+    exec: impl PgExecutor<'_>,
+    issue_id: Uuid,
+) -> anyhow::Result<Option<IssueContent>> {
+    let issue = sqlx::query_as!(
+        IssueContent,
+        r#"
+        SELECT title, text_content, html_content
+        FROM newsletter_issues
+        WHERE id = $1
+        "#,
+        issue_id
+    )
+    .fetch_optional(exec)
+    .await
+    .context("Failed to retrieve the newsletter issue.")?;
+    Ok(issue)
+}
+
+/// Publishes or schedules a draft, returning whether the issue was still a draft.
+#[tracing::instrument(skip(exec))]
+async fn mark_draft_as_published(
+    exec: impl PgExecutor<'_>,
+    issue_id: Uuid,
+    publication: &Publication,
+) -> anyhow::Result<bool> {
+    let r = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            status = $2,
+            scheduled_for = $3,
+            published_at = CASE WHEN $2 = 'published' THEN now() END
+        WHERE id = $1 AND status = 'draft'
+        "#,
+        issue_id,
+        publication.status(),
+        publication.scheduled_for()
+    )
+    .execute(exec)
+    .await
+    .context("Failed to publish the draft.")?;
+    Ok(r.rows_affected() == 1)
+}
@@ -1,17 +1,62 @@
-use actix_web::{get, http::header, HttpResponse, Responder};
+use crate::{
+    auth::reject_non_editors,
+    routes::admin::{get_lists, get_segments, list_checkboxes},
+    utils,
+    workers::issue_delivery::count_segment_members,
+};
+use actix_web::{get, http::header, middleware::from_fn, web, HttpResponse, Responder};
 use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
 use std::fmt::Write;
 use uuid::Uuid;
 
-#[get("/newsletters")]
+#[get("/newsletters", wrap = "from_fn(reject_non_editors)")]
 pub async fn newsletters_form(
     flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
 ) -> actix_web::Result<impl Responder> {
     let mut msg_html = String::new();
     for m in flash_messages.iter() {
         writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
     }
     let idempotency_key = Uuid::new_v4();
+    let segments_html = {
+        let mut options = String::new();
+        for s in get_segments(pool.as_ref()).await.map_err(utils::e500)? {
+            let n_members = count_segment_members(&s.segment, pool.as_ref())
+                .await
+                .map_err(utils::e500)?;
+            writeln!(
+                options,
+                r#"<option value="{}">{} ({} confirmed subscribers)</option>"#,
+                s.id,
+                htmlescape::encode_minimal(&s.name),
+                n_members
+            )
+            .unwrap();
+        }
+        if options.is_empty() {
+            String::new()
+        } else {
+            format!(
+                r#"<label>Segment
+                <select name="segment_id"><option value="">Everyone</option>{options}</select>
+            </label>
+            <br>"#
+            )
+        }
+    };
+    let lists_html = {
+        let all = get_lists(pool.as_ref()).await.map_err(utils::e500)?;
+        if all.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "<fieldset><legend>Send to (leave empty to send to every subscriber)</legend>{}</fieldset>",
+                list_checkboxes(&all, &[])
+            )
+        }
+    };
 
     Ok(HttpResponse::Ok()
         .content_type(header::ContentType::html())
@@ -38,9 +83,22 @@ pub async fn newsletters_form(
                 <input type="text" placeholder="..." name="html" >
             </label>
             <br>
+            <label>Publish at (UTC, leave empty to publish now)
+                <input type="datetime-local" name="scheduled_for">
+            </label>
+            <br>
+            {lists_html}
+            {segments_html}
+            <label>
+                <input type="checkbox" name="track" value="true">
+                Track opens and clicks
+            </label>
+            <br>
             <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
             <button type="submit">Publish</button>
+            <button type="submit" name="draft" value="true">Save as draft</button>
         </form>
+        <p><a href="/admin/newsletters/issues">Past and scheduled issues</a></p>
         <p><a href="/admin/dashboard">&lt;- Back</a></p>
     </body>
 </html>
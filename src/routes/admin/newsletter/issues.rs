@@ -0,0 +1,342 @@
+use crate::{utils, workers::issue_delivery::count_recipients};
+use actix_web::{get, http::header, web, HttpResponse, Responder};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal as escape;
+use serde::Deserialize;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Write;
+use uuid::Uuid;
+
+const PAGE_SIZE: i64 = 20;
+
+#[derive(Deserialize)]
+struct Pagination {
+    page: Option<i64>,
+}
+
+#[get("/newsletters/issues")]
+pub async fn list_issues(
+    pagination: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let page = pagination.page.unwrap_or(1).max(1);
+    let offset = (page - 1)
+        .checked_mul(PAGE_SIZE)
+        .ok_or_else(|| utils::e400(format!("Page {} is out of range.", page)))?;
+    let (issues, n_issues) = get_issues(pool.as_ref(), offset)
+        .await
+        .map_err(utils::e500)?;
+
+    let mut rows_html = String::new();
+    for i in issues {
+        writeln!(
+            rows_html,
+            r#"<tr>
+                <td><a href="/admin/newsletters/issues/{id}">{title}</a></td>
+                <td>{status}</td>
+                <td>{date}</td>
+                <td>{n_queued}</td>
+                <td>{n_delivered}</td>
+                <td>{n_failed}</td>
+            </tr>"#,
+            id = i.id,
+            title = escape(&i.title),
+            status = i.status,
+            date = i.date(),
+            n_queued = i.n_queued,
+            n_delivered = i.n_delivered,
+            n_failed = i.n_failed,
+        )
+        .unwrap();
+    }
+
+    let mut pages_html = String::new();
+    if page > 1 {
+        writeln!(
+            pages_html,
+            r#"<a href="/admin/newsletters/issues?page={}">Previous</a>"#,
+            page - 1
+        )
+        .unwrap();
+    }
+    if n_issues - offset > PAGE_SIZE {
+        writeln!(
+            pages_html,
+            r#"<a href="/admin/newsletters/issues?page={}">Next</a>"#,
+            page + 1
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Newsletter Issues</title>
+    </head>
+    <body>
+        <table>
+            <tr>
+                <th>Title</th>
+                <th>Status</th>
+                <th>Date</th>
+                <th>Queued</th>
+                <th>Sent</th>
+                <th>Failed</th>
+            </tr>
+            {rows_html}
+        </table>
+        <p>{pages_html}</p>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+        )))
+}
+
+#[get("/newsletters/issues/{issue_id}")]
+pub async fn issue_details(
+    issue_id: web::Path<Uuid>,
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let Some(issue) = get_issue(pool.as_ref(), *issue_id)
+        .await
+        .map_err(utils::e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let id = issue.summary.id;
+    let audience_html = match issue.summary.status.as_str() {
+        "draft" | "scheduled" => {
+            let mut conn = pool.acquire().await.map_err(utils::e500)?;
+            let n = count_recipients(id, &mut conn).await.map_err(utils::e500)?;
+            format!("<p>Recipients if sent now: {n}</p>")
+        }
+        _ => String::new(),
+    };
+    let engagement_html = match &issue.engagement {
+        Some(e) => {
+            let n_delivered = issue.summary.n_delivered;
+            format!(
+                "<li>Opened: {} ({})</li><li>Clicked: {} ({})</li>",
+                e.n_opened,
+                rate(e.n_opened, n_delivered),
+                e.n_clicked,
+                rate(e.n_clicked, n_delivered)
+            )
+        }
+        None => String::new(),
+    };
+    let actions_html = match issue.summary.status.as_str() {
+        "scheduled" => format!(
+            r#"<form action="/admin/newsletters/issues/{id}/reschedule" method="post">
+            <label>Publish at (UTC)
+                <input type="datetime-local" name="scheduled_for">
+            </label>
+            <button type="submit">Reschedule</button>
+        </form>
+        <form action="/admin/newsletters/issues/{id}/cancel" method="post">
+            <button type="submit">Cancel</button>
+        </form>"#
+        ),
+        "draft" => format!(
+            r#"<form action="/admin/newsletters/issues/{id}/test" method="post">
+            <label>Send a test to
+                <input type="email" name="email">
+            </label>
+            <button type="submit">Send test</button>
+        </form>
+        <form action="/admin/newsletters/issues/{id}/publish" method="post">
+            <label>Publish at (UTC, leave empty to publish now)
+                <input type="datetime-local" name="scheduled_for">
+            </label>
+            <button type="submit">Publish</button>
+        </form>"#
+        ),
+        _ => String::new(),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>{title}</title>
+    </head>
+    <body>
+        {msg_html}
+        <h1>{title}</h1>
+        <p>{status}: {date}</p>
+        <p><a href="/admin/newsletters/issues/{id}/preview">Preview</a></p>
+        {audience_html}
+        {actions_html}
+        <ul>
+            <li>Queued: {n_queued}</li>
+            <li>Sent: {n_delivered}</li>
+            <li>Failed: {n_failed}</li>
+            {engagement_html}
+        </ul>
+        <h2>Text content</h2>
+        <pre>{text_content}</pre>
+        <p><a href="/issues/{id}">View in the web archive</a></p>
+        <p><a href="/admin/newsletters/issues">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+            title = escape(&issue.summary.title),
+            status = issue.summary.status,
+            date = issue.summary.date(),
+            n_queued = issue.summary.n_queued,
+            n_delivered = issue.summary.n_delivered,
+            n_failed = issue.summary.n_failed,
+            text_content = escape(&issue.text_content),
+        )))
+}
+
+/// An issue along with the progress of its delivery.
+struct IssueSummary {
+    id: Uuid,
+    title: String,
+    status: String,
+    published_at: Option<DateTime<Utc>>,
+    scheduled_for: Option<DateTime<Utc>>,
+    n_queued: i64,
+    n_delivered: i32,
+    n_failed: i64,
+}
+
+impl IssueSummary {
+    /// When the issue has been, or is going to be, published.
+    fn date(&self) -> String {
+        self.published_at
+            .or(self.scheduled_for)
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default()
+    }
+}
+
+/// How many of the recipients of a tracked issue opened it, and followed one of its links.
+///
+/// A click counts as an open, since many email clients do not load the tracking pixel.
+struct Engagement {
+    n_opened: i64,
+    n_clicked: i64,
+}
+
+/// The share of the delivered emails, as a percentage.
+fn rate(n: i64, n_delivered: i32) -> String {
+    if n_delivered == 0 {
+        return "-".into();
+    }
+    format!("{:.1}%", n as f64 * 100. / n_delivered as f64)
+}
+
+struct Issue {
+    summary: IssueSummary,
+    text_content: String,
+    engagement: Option<Engagement>,
+}
+
+/// Returns a page of issues, most recent first, along with the total number of issues.
+#[tracing::instrument(skip(exec))]
+async fn get_issues(
+    exec: impl PgExecutor<'_> + Copy,
+    offset: i64,
+) -> anyhow::Result<(Vec<IssueSummary>, i64)> {
+    let issues = sqlx::query_as!(
+        IssueSummary,
+        r#"
+        SELECT
+            i.id,
+            i.title,
+            i.status,
+            i.published_at,
+            i.scheduled_for,
+            (SELECT COUNT(*) FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.id) AS "n_queued!",
+            i.n_delivered,
+            (SELECT COUNT(*) FROM failed_deliveries f
+                WHERE f.newsletter_issue_id = i.id) AS "n_failed!"
+        FROM newsletter_issues i
+        ORDER BY COALESCE(i.published_at, i.scheduled_for) DESC, i.id
+        LIMIT $1
+        OFFSET $2
+        "#,
+        PAGE_SIZE,
+        offset
+    )
+    .fetch_all(exec)
+    .await
+    .context("Failed to retrieve newsletter issues.")?;
+
+    let n_issues = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "n!" FROM newsletter_issues"#)
+        .fetch_one(exec)
+        .await
+        .context("Failed to count newsletter issues.")?;
+
+    Ok((issues, n_issues))
+}
+
+#[tracing::instrument(skip(exec))]
+async fn get_issue(exec: impl PgExecutor<'_>, issue_id: Uuid) -> anyhow::Result<Option<Issue>> {
+    let issue = sqlx::query!(
+        r#"
+        SELECT
+            i.id,
+            i.title,
+            i.status,
+            i.published_at,
+            i.scheduled_for,
+            (SELECT COUNT(*) FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.id) AS "n_queued!",
+            i.n_delivered,
+            (SELECT COUNT(*) FROM failed_deliveries f
+                WHERE f.newsletter_issue_id = i.id) AS "n_failed!",
+            i.text_content,
+            i.tracking_enabled,
+            (SELECT COUNT(DISTINCT e.subscriber_id) FROM tracking_events e
+                WHERE e.newsletter_issue_id = i.id) AS "n_opened!",
+            (SELECT COUNT(DISTINCT e.subscriber_id) FROM tracking_events e
+                WHERE e.newsletter_issue_id = i.id AND e.kind = 'click') AS "n_clicked!"
+        FROM newsletter_issues i
+        WHERE i.id = $1
+        "#,
+        issue_id
+    )
+    .fetch_optional(exec)
+    .await
+    .context("Failed to retrieve the newsletter issue.")?
+    .map(|r| Issue {
+        summary: IssueSummary {
+            id: r.id,
+            title: r.title,
+            status: r.status,
+            published_at: r.published_at,
+            scheduled_for: r.scheduled_for,
+            n_queued: r.n_queued,
+            n_delivered: r.n_delivered,
+            n_failed: r.n_failed,
+        },
+        text_content: r.text_content,
+        engagement: r.tracking_enabled.then_some(Engagement {
+            n_opened: r.n_opened,
+            n_clicked: r.n_clicked,
+        }),
+    });
+    Ok(issue)
+}
@@ -1,5 +1,13 @@
+mod drafts;
 mod get;
+mod issues;
 mod post;
+mod schedule;
 
+pub use drafts::{preview_issue, publish_draft, send_test_issue};
 pub use get::newsletters_form;
+pub use issues::{issue_details, list_issues};
 pub use post::publish_newsletter;
+pub(crate) use post::{insert_newsletter_issue, Publication};
+pub(crate) use schedule::parse_schedule;
+pub use schedule::{cancel_issue, reschedule_issue};
@@ -1,11 +1,15 @@
+use super::schedule::parse_schedule;
 use crate::{
-    auth::UserId,
+    auth::{reject_non_editors, UserId},
     idempotency::{self, IdempotencyKey, NextAction},
-    utils,
+    routes::admin::{set_issue_lists, set_issue_segment},
+    utils::{self, HtmlForm},
+    workers::issue_delivery::enqueue_delivery_tasks,
 };
-use actix_web::{post, web, Responder};
+use actix_web::{middleware::from_fn, post, web, Responder};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use sqlx::{PgExecutor, PgPool};
 use uuid::Uuid;
@@ -16,16 +20,56 @@ struct FormData {
     text: String,
     html: String,
     idempotency_key: String,
+    /// Publishes the issue right away when left empty.
+    #[serde(default)]
+    scheduled_for: String,
+    /// Saves the issue without publishing it.
+    #[serde(default)]
+    draft: bool,
+    /// Sends the issue to every confirmed subscriber when left empty.
+    #[serde(default)]
+    lists: Vec<Uuid>,
+    /// Narrows the audience down to a segment, if any.
+    #[serde(default)]
+    segment_id: String,
+    /// Tracks the opens of the issue and the clicks on its links.
+    #[serde(default)]
+    track: bool,
 }
 
-#[post("/newsletters")]
+/// What happens to a submitted issue.
+#[derive(Debug)]
+pub(crate) enum Publication {
+    Now,
+    At(DateTime<Utc>),
+    Draft,
+}
+
+impl Publication {
+    pub(crate) fn status(&self) -> &'static str {
+        match self {
+            Self::Now => "published",
+            Self::At(_) => "scheduled",
+            Self::Draft => "draft",
+        }
+    }
+
+    pub(crate) fn scheduled_for(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::At(t) => Some(*t),
+            _ => None,
+        }
+    }
+}
+
+#[post("/newsletters", wrap = "from_fn(reject_non_editors)")]
 #[tracing::instrument(
     name = "Pubish a newsletter issue",
     skip_all,
     fields( user_id = %*user_id)
 )]
 pub async fn publish_newsletter(
-    form: web::Form<FormData>,
+    form: HtmlForm<FormData>,
     pool: web::Data<PgPool>,
     user_id: web::ReqData<UserId>,
 ) -> actix_web::Result<impl Responder> {
@@ -35,8 +79,30 @@ pub async fn publish_newsletter(
         text,
         html,
         idempotency_key,
+        scheduled_for,
+        draft,
+        mut lists,
+        segment_id,
+        track,
     } = form.0;
+    lists.sort();
+    lists.dedup();
+    let segment_id = match segment_id.trim() {
+        "" => None,
+        s => Some(s.parse::<Uuid>().map_err(utils::e400)?),
+    };
     let idempotency_key: IdempotencyKey = idempotency_key.try_into().map_err(utils::e400)?;
+    let publication = match scheduled_for.trim() {
+        _ if draft => Publication::Draft,
+        "" => Publication::Now,
+        s => match parse_schedule(s) {
+            Ok(t) => Publication::At(t),
+            Err(e) => {
+                FlashMessage::error(e).send();
+                return Ok(utils::see_other("/admin/newsletters"));
+            }
+        },
+    };
 
     let mut txn = match idempotency::try_processing(*user_id, &idempotency_key, &pool)
         .await
@@ -44,24 +110,46 @@ pub async fn publish_newsletter(
     {
         NextAction::StartProcessing(t) => t,
         NextAction::ReturnSavedResponse(r) => {
-            success_message().send();
+            success_message(&publication).send();
             return Ok(r);
         }
     };
 
-    let issue_id = insert_newsletter_issue(&title, &text, &html, txn.as_mut())
+    let issue_id = insert_newsletter_issue(&title, &text, &html, &publication, track, txn.as_mut())
         .await
         .context("Failed to store newsletter issue details")
         .map_err(utils::e500)?;
-
-    enqueue_delivery_tasks(issue_id, txn.as_mut())
+    if set_issue_lists(txn.as_mut(), issue_id, &lists)
         .await
-        .context("Failed to enqueue delivery tasks")
-        .map_err(utils::e500)?;
+        .map_err(utils::e500)?
+        != lists.len() as u64
+    {
+        return Err(utils::e400("Some of the lists do not exist."));
+    }
+    if let Some(segment_id) = segment_id {
+        if !set_issue_segment(txn.as_mut(), issue_id, segment_id)
+            .await
+            .map_err(utils::e500)?
+        {
+            return Err(utils::e400("The segment does not exist."));
+        }
+    }
+
+    if let Publication::Now = publication {
+        enqueue_delivery_tasks(issue_id, txn.as_mut())
+            .await
+            .context("Failed to enqueue delivery tasks")
+            .map_err(utils::e500)?;
+    }
 
-    success_message().send();
+    success_message(&publication).send();
     let resp = {
-        let resp = utils::see_other("/admin/newsletters");
+        let resp = match publication {
+            Publication::Draft => {
+                utils::see_other(&format!("/admin/newsletters/issues/{}", issue_id))
+            }
+            _ => utils::see_other("/admin/newsletters"),
+        };
         idempotency::save_response(resp, *user_id, &idempotency_key, txn)
             .await
             .map_err(utils::e500)?
@@ -69,15 +157,26 @@ pub async fn publish_newsletter(
     Ok(resp)
 }
 
-fn success_message() -> FlashMessage {
-    FlashMessage::info("The newsletter issue has been accepted - emails will go out shortly.")
+pub(super) fn success_message(publication: &Publication) -> FlashMessage {
+    match publication {
+        Publication::Now => FlashMessage::info(
+            "The newsletter issue has been accepted - emails will go out shortly.",
+        ),
+        Publication::At(t) => FlashMessage::info(format!(
+            "The newsletter issue has been scheduled for {}.",
+            t.to_rfc3339()
+        )),
+        Publication::Draft => FlashMessage::info("The newsletter issue has been saved as a draft."),
+    }
 }
 
 #[tracing::instrument(skip_all)]
-async fn insert_newsletter_issue(
+pub(crate) async fn insert_newsletter_issue(
     title: &str,
     text_content: &str,
     html_content: &str,
+    publication: &Publication,
+    tracking_enabled: bool,
     exec: impl PgExecutor<'_>,
 ) -> anyhow::Result<Uuid> {
     let issue_id = Uuid::new_v4();
@@ -88,35 +187,25 @@ async fn insert_newsletter_issue(
         title,
         text_content,
         html_content,
+        status,
+        scheduled_for,
+        tracking_enabled,
         published_at
     )
-    VALUES ($1, $2, $3, $4, now())
+    VALUES (
+        $1, $2, $3, $4, $5, $6, $7,
+        CASE WHEN $5 = 'published' THEN now() END
+    )
     "#,
         issue_id,
         title,
         text_content,
-        html_content
+        html_content,
+        publication.status(),
+        publication.scheduled_for(),
+        tracking_enabled
     )
     .execute(exec)
     .await?;
     Ok(issue_id)
 }
-
-#[tracing::instrument(skip_all)]
-async fn enqueue_delivery_tasks(issue_id: Uuid, exec: impl PgExecutor<'_>) -> anyhow::Result<()> {
-    sqlx::query!(
-        r#"
-        INSERT INTO issue_delivery_queue (
-            newsletter_issue_id,
-            subscriber_email
-        )
-        SELECT $1, email
-        FROM subscriptions
-        WHERE status = 'confirmed'
-    "#,
-        issue_id
-    )
-    .execute(exec)
-    .await?;
-    Ok(())
-}
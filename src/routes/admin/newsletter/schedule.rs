@@ -0,0 +1,124 @@
+use crate::{auth::reject_non_editors, utils};
+use actix_web::{middleware::from_fn, post, web, Responder};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Deserialize;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+/// Parses a future publication time, either in RFC 3339 format
+/// or as sent by a `datetime-local` input, which is read as UTC.
+pub(crate) fn parse_schedule(s: &str) -> Result<DateTime<Utc>, String> {
+    let t = DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M").map(|t| t.and_utc()))
+        .map_err(|_| format!("{} is not a valid publication time.", s))?;
+    if t <= Utc::now() {
+        return Err("The publication time must be in the future.".into());
+    }
+    Ok(t)
+}
+
+#[derive(Deserialize)]
+struct FormData {
+    scheduled_for: String,
+}
+
+#[post(
+    "/newsletters/issues/{issue_id}/reschedule",
+    wrap = "from_fn(reject_non_editors)"
+)]
+#[tracing::instrument(name = "Reschedule a newsletter issue", skip(form, pool))]
+pub async fn reschedule_issue(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let location = format!("/admin/newsletters/issues/{}", issue_id);
+    let scheduled_for = match parse_schedule(form.scheduled_for.trim()) {
+        Ok(t) => t,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other(&location));
+        }
+    };
+
+    let rescheduled = reschedule(pool.as_ref(), *issue_id, scheduled_for)
+        .await
+        .map_err(utils::e500)?;
+
+    if rescheduled {
+        FlashMessage::info(format!(
+            "The newsletter issue has been rescheduled for {}.",
+            scheduled_for.to_rfc3339()
+        ))
+        .send();
+    } else {
+        FlashMessage::error("Only scheduled issues can be rescheduled.").send();
+    }
+    Ok(utils::see_other(&location))
+}
+
+#[post(
+    "/newsletters/issues/{issue_id}/cancel",
+    wrap = "from_fn(reject_non_editors)"
+)]
+#[tracing::instrument(name = "Cancel a newsletter issue", skip(pool))]
+pub async fn cancel_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let cancelled = cancel(pool.as_ref(), *issue_id)
+        .await
+        .map_err(utils::e500)?;
+
+    if cancelled {
+        FlashMessage::info("The newsletter issue has been cancelled.").send();
+    } else {
+        FlashMessage::error("Only scheduled issues can be cancelled.").send();
+    }
+    Ok(utils::see_other(&format!(
+        "/admin/newsletters/issues/{}",
+        issue_id
+    )))
+}
+
+/// Moves the publication time of an issue that has not been published yet.
+#[tracing::instrument(skip(exec))]
+async fn reschedule(
+    exec: impl PgExecutor<'_>,
+    issue_id: Uuid,
+    scheduled_for: DateTime<Utc>,
+) -> anyhow::Result<bool> {
+    let r = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET scheduled_for = $2
+        WHERE id = $1 AND status = 'scheduled'
+        "#,
+        issue_id,
+        scheduled_for
+    )
+    .execute(exec)
+    .await
+    .context("Failed to reschedule the newsletter issue.")?;
+    Ok(r.rows_affected() == 1)
+}
+
+/// Cancels an issue that has not been published yet.
+#[tracing::instrument(skip(exec))]
+async fn cancel(exec: impl PgExecutor<'_>, issue_id: Uuid) -> anyhow::Result<bool> {
+    let r = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'cancelled'
+        WHERE id = $1 AND status = 'scheduled'
+        "#,
+        issue_id
+    )
+    .execute(exec)
+    .await
+    .context("Failed to cancel the newsletter issue.")?;
+    Ok(r.rows_affected() == 1)
+}
@@ -0,0 +1,107 @@
+use crate::{
+    auth::{self, UserId},
+    routes::admin::dashboard::get_username,
+    session_state::Session,
+    utils,
+};
+use actix_web::{get, http::header, web, HttpResponse, Responder};
+use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::encode_minimal as escape;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+#[get("/security")]
+pub async fn security(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    session: Session,
+    user_id: web::ReqData<UserId>,
+) -> actix_web::Result<impl Responder> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let is_enabled = auth::get_totp_secret(**user_id, pool.as_ref())
+        .await
+        .map_err(utils::e500)?
+        .is_some();
+    let totp_html = if is_enabled {
+        r#"<p>Two-factor authentication is enabled.</p>
+        <form action="/admin/security/totp/disable" method="post">
+            <label>Authentication code or recovery code
+                <input type="text" name="code" autocomplete="one-time-code">
+            </label>
+            <button type="submit">Disable two-factor authentication</button>
+        </form>"#
+            .to_owned()
+    } else {
+        // Every visit starts a new enrollment, confirmed by posting a code below.
+        let secret = auth::generate_totp_secret();
+        let username = get_username(**user_id, pool.as_ref())
+            .await
+            .map_err(utils::e500)?;
+        let uri = auth::otpauth_uri(&secret, &username).map_err(utils::e500)?;
+        session
+            .totp_enrollment_secret()
+            .insert(secret.expose_secret().to_owned())
+            .map_err(utils::e500)?;
+        format!(
+            r#"<p>Two-factor authentication is disabled.</p>
+        <p>Add <a href="{uri}">this account</a> to your authenticator app,
+            or enter the secret <code>{secret}</code> manually.</p>
+        <form action="/admin/security/totp" method="post">
+            <label>Authentication code
+                <input type="text" name="code" autocomplete="one-time-code">
+            </label>
+            <button type="submit">Enable two-factor authentication</button>
+        </form>"#,
+            uri = escape(&uri),
+            secret = secret.expose_secret(),
+        )
+    };
+
+    let email = sqlx::query_scalar!("SELECT email FROM users WHERE id = $1", **user_id)
+        .fetch_one(pool.as_ref())
+        .await
+        .map_err(utils::e500)?;
+    let email_html = match email {
+        Some(email) => format!(
+            "<p>Password reset links are sent to <b>{}</b>.</p>",
+            escape(&email)
+        ),
+        None => {
+            "<p>No email address is set, so a forgotten password cannot be reset.</p>".to_owned()
+        }
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Security</title>
+    </head>
+    <body>
+        {msg_html}
+        {totp_html}
+        {email_html}
+        <form action="/admin/security/email" method="post">
+            <label>Email
+                <input type="email" name="email">
+            </label>
+            <label>Current password
+                <input type="password" name="current_password">
+            </label>
+            <button type="submit">Change email</button>
+        </form>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+        )))
+}
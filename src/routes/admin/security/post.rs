@@ -0,0 +1,160 @@
+use crate::{
+    auth::{self, AuthError, ChangeEmailError, Credentials, UserId},
+    domain::SubscriberEmail,
+    routes::admin::dashboard::get_username,
+    session_state::Session,
+    utils,
+};
+use actix_web::{http::header, post, web, HttpResponse, Responder};
+use actix_web_flash_messages::FlashMessage;
+use secrecy::SecretString;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+#[derive(serde::Deserialize)]
+struct FormData {
+    code: String,
+}
+
+/// Confirms the enrollment started by the security page and shows the recovery codes once.
+#[post("/security/totp")]
+#[tracing::instrument(name = "Enable two-factor authentication", skip_all, fields(user_id = %*user_id))]
+pub async fn enable_two_factor(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    session: Session,
+    user_id: web::ReqData<UserId>,
+) -> actix_web::Result<impl Responder> {
+    let Some(secret) = session
+        .totp_enrollment_secret()
+        .get()
+        .map_err(utils::e500)?
+        .map(SecretString::from)
+    else {
+        FlashMessage::error("Please start the enrollment again.").send();
+        return Ok(utils::see_other("/admin/security"));
+    };
+    if auth::verify_totp_code(&secret, &form.code)
+        .map_err(utils::e500)?
+        .is_none()
+    {
+        FlashMessage::error("The code is incorrect - please scan the new secret and try again.")
+            .send();
+        return Ok(utils::see_other("/admin/security"));
+    }
+
+    let mut txn = pool.begin().await.map_err(utils::e500)?;
+    let recovery_codes = auth::enable_totp(**user_id, &secret, &mut txn)
+        .await
+        .map_err(utils::e500)?;
+    txn.commit().await.map_err(utils::e500)?;
+    session.totp_enrollment_secret().remove();
+
+    let mut codes_html = String::new();
+    for c in recovery_codes {
+        writeln!(codes_html, "<li><code>{c}</code></li>").unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Recovery codes</title>
+    </head>
+    <body>
+        <p>Two-factor authentication has been enabled.</p>
+        <p>Store these recovery codes somewhere safe. Each of them lets you log in once
+            without your authenticator app. They will not be shown again.</p>
+        <ul>
+            {codes_html}
+        </ul>
+        <p><a href="/admin/security">Done</a></p>
+    </body>
+</html>
+            "#,
+        )))
+}
+
+#[post("/security/totp/disable")]
+#[tracing::instrument(name = "Disable two-factor authentication", skip_all, fields(user_id = %*user_id))]
+pub async fn disable_two_factor(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> actix_web::Result<impl Responder> {
+    let Some(secret) = auth::get_totp_secret(**user_id, pool.as_ref())
+        .await
+        .map_err(utils::e500)?
+    else {
+        return Ok(utils::see_other("/admin/security"));
+    };
+    let is_valid = auth::use_totp_code(**user_id, &secret, &form.code, pool.as_ref())
+        .await
+        .map_err(utils::e500)?
+        || auth::use_recovery_code(**user_id, &form.code, pool.as_ref())
+            .await
+            .map_err(utils::e500)?;
+    if !is_valid {
+        FlashMessage::error("The code is incorrect.").send();
+        return Ok(utils::see_other("/admin/security"));
+    }
+
+    let mut txn = pool.begin().await.map_err(utils::e500)?;
+    auth::disable_totp(**user_id, &mut txn)
+        .await
+        .map_err(utils::e500)?;
+    txn.commit().await.map_err(utils::e500)?;
+
+    FlashMessage::info("Two-factor authentication has been disabled.").send();
+    Ok(utils::see_other("/admin/security"))
+}
+
+#[derive(serde::Deserialize)]
+struct EmailFormData {
+    email: String,
+    current_password: SecretString,
+}
+
+/// Sets the address that password reset links are sent to.
+#[post("/security/email")]
+#[tracing::instrument(name = "Set the account email", skip_all, fields(user_id = %*user_id))]
+pub async fn set_email(
+    form: web::Form<EmailFormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> actix_web::Result<impl Responder> {
+    let form = form.into_inner();
+    let email = match SubscriberEmail::parse(form.email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/admin/security"));
+        }
+    };
+
+    let credentials = Credentials {
+        username: get_username(**user_id, pool.as_ref())
+            .await
+            .map_err(utils::e500)?,
+        password: form.current_password,
+    };
+    if let Err(e) = auth::validate_credentials(credentials, pool.as_ref()).await {
+        return match e {
+            AuthError::UnexpectedError(_) => Err(utils::e500(e)),
+            AuthError::InvalidCredentials(_) => {
+                FlashMessage::error("The current password is incorrect.").send();
+                Ok(utils::see_other("/admin/security"))
+            }
+        };
+    }
+
+    match auth::change_email(**user_id, &email, pool.as_ref()).await {
+        Ok(()) => FlashMessage::info("Your email has been changed.").send(),
+        Err(e @ ChangeEmailError::EmailTaken) => FlashMessage::error(e.to_string()).send(),
+        Err(e) => return Err(utils::e500(e)),
+    }
+    Ok(utils::see_other("/admin/security"))
+}
@@ -0,0 +1,86 @@
+use super::get_segments;
+use crate::{auth::reject_non_admins, utils, workers::issue_delivery::count_segment_members};
+use actix_web::{get, http::header, middleware::from_fn, web, HttpResponse, Responder};
+use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::encode_minimal as escape;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+#[get("/segments", wrap = "from_fn(reject_non_admins)")]
+pub async fn segments(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", escape(m.content())).unwrap();
+    }
+
+    let mut segments_html = String::new();
+    for s in get_segments(pool.as_ref()).await.map_err(utils::e500)? {
+        let n_members = count_segment_members(&s.segment, pool.as_ref())
+            .await
+            .map_err(utils::e500)?;
+        writeln!(
+            segments_html,
+            r#"<tr>
+                <td>{name}</td>
+                <td>{rules}</td>
+                <td>{n_members}</td>
+            </tr>"#,
+            name = escape(&s.name),
+            rules = escape(&s.segment.describe()),
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Segments</title>
+    </head>
+    <body>
+        {msg_html}
+        <table>
+            <tr>
+                <th>Name</th>
+                <th>Subscribers who</th>
+                <th>Confirmed subscribers</th>
+            </tr>
+            {segments_html}
+        </table>
+        <h2>Create a segment</h2>
+        <p>Subscribers must match every rule you fill in.</p>
+        <form action="/admin/segments" method="post">
+            <label>Name
+                <input type="text" name="name" placeholder="Recent pro users">
+            </label>
+            <br>
+            <label>Subscribed in the last (days)
+                <input type="number" min="0" name="subscribed_within_days">
+            </label>
+            <br>
+            <label>Name contains
+                <input type="text" name="name_contains">
+            </label>
+            <br>
+            <label>Attribute
+                <input type="text" name="attribute_key" placeholder="plan">
+            </label>
+            <label>is
+                <input type="text" name="attribute_value" placeholder="pro">
+            </label>
+            <br>
+            <button type="submit">Create</button>
+        </form>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+        )))
+}
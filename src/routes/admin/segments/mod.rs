@@ -0,0 +1,58 @@
+mod get;
+mod post;
+
+pub use get::segments;
+pub use post::create_segment;
+
+use crate::domain::Segment;
+use anyhow::Context;
+use sqlx::{types::Json, PgExecutor};
+use uuid::Uuid;
+
+pub(crate) struct SegmentRow {
+    pub(crate) id: Uuid,
+    pub(crate) name: String,
+    pub(crate) segment: Segment,
+}
+
+#[tracing::instrument(skip(exec))]
+pub(crate) async fn get_segments(exec: impl PgExecutor<'_>) -> anyhow::Result<Vec<SegmentRow>> {
+    let rows = sqlx::query!(
+        r#"SELECT id, name, definition AS "definition: Json<Segment>" FROM segments ORDER BY name"#
+    )
+    .fetch_all(exec)
+    .await
+    .context("Failed to retrieve the segments.")?
+    .into_iter()
+    .map(|r| SegmentRow {
+        id: r.id,
+        name: r.name,
+        segment: r.definition.0,
+    })
+    .collect();
+    Ok(rows)
+}
+
+/// Narrows the audience of the issue to the segment.
+/// Returns `false` if the segment does not exist.
+#[tracing::instrument(skip(exec))]
+pub(crate) async fn set_issue_segment(
+    exec: impl PgExecutor<'_>,
+    issue_id: Uuid,
+    segment_id: Uuid,
+) -> anyhow::Result<bool> {
+    let r = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues i
+        SET segment_id = g.id
+        FROM segments g
+        WHERE i.id = $1 AND g.id = $2
+        "#,
+        issue_id,
+        segment_id
+    )
+    .execute(exec)
+    .await
+    .context("Failed to target the issue at the segment.")?;
+    Ok(r.rows_affected() == 1)
+}
@@ -0,0 +1,94 @@
+use crate::{
+    auth::reject_non_admins,
+    domain::{Segment, SegmentRule},
+    utils,
+};
+use actix_web::{middleware::from_fn, post, web, Responder};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{types::Json, PgPool};
+use uuid::Uuid;
+
+#[derive(serde::Deserialize)]
+struct FormData {
+    name: String,
+    #[serde(default)]
+    subscribed_within_days: String,
+    #[serde(default)]
+    name_contains: String,
+    #[serde(default)]
+    attribute_key: String,
+    #[serde(default)]
+    attribute_value: String,
+}
+
+impl FormData {
+    /// One rule for every filled in field.
+    fn segment(&self) -> Result<Segment, String> {
+        let mut rules = Vec::new();
+        match self.subscribed_within_days.trim() {
+            "" => (),
+            s => rules.push(SegmentRule::SubscribedWithinDays {
+                days: s
+                    .parse()
+                    .map_err(|_| format!("{} is not a valid number of days.", s))?,
+            }),
+        }
+        match self.name_contains.trim() {
+            "" => (),
+            s => rules.push(SegmentRule::NameContains { text: s.into() }),
+        }
+        match (self.attribute_key.trim(), self.attribute_value.trim()) {
+            ("", "") => (),
+            (key, value) => rules.push(SegmentRule::AttributeEquals {
+                key: key.into(),
+                value: value.into(),
+            }),
+        }
+        Segment::parse(rules)
+    }
+}
+
+#[post("/segments", wrap = "from_fn(reject_non_admins)")]
+#[tracing::instrument(name = "Create a segment", skip_all, fields(name = %form.name))]
+pub async fn create_segment(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let name = form.name.trim();
+    if name.is_empty() {
+        FlashMessage::error("Please name the segment.").send();
+        return Ok(utils::see_other("/admin/segments"));
+    }
+    let segment = match form.segment() {
+        Ok(s) => s,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/admin/segments"));
+        }
+    };
+
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO segments (id, name, definition, created_at)
+        VALUES ($1, $2, $3, now())
+        ON CONFLICT (name) DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        name,
+        Json(&segment) as _
+    )
+    .execute(pool.as_ref())
+    .await
+    .context("Failed to create the segment.")
+    .map_err(utils::e500)?
+    .rows_affected()
+        == 1;
+
+    if created {
+        FlashMessage::info(format!("The segment {} has been created.", name)).send();
+    } else {
+        FlashMessage::error(format!("A segment named {} already exists.", name)).send();
+    }
+    Ok(utils::see_other("/admin/segments"))
+}
@@ -0,0 +1,155 @@
+use crate::{auth::reject_non_admins, routes::subscriptions::SUBSCRIPTION_STATUSES, utils};
+use actix_web::{
+    get,
+    http::header::{self, ContentDisposition, DispositionParam, DispositionType},
+    middleware::from_fn,
+    web, HttpResponse, Responder,
+};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal as escape;
+use serde::Deserialize;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Write;
+
+#[get("/subscribers", wrap = "from_fn(reject_non_admins)")]
+pub async fn subscribers(flash_messages: IncomingFlashMessages) -> impl Responder {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", escape(m.content())).unwrap();
+    }
+    let mut status_options_html = String::new();
+    for s in SUBSCRIPTION_STATUSES {
+        writeln!(status_options_html, r#"<option value="{s}">{s}</option>"#).unwrap();
+    }
+
+    HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Subscribers</title>
+    </head>
+    <body>
+        {msg_html}
+        <h2>Import subscribers</h2>
+        <p>Upload a CSV file with a header row naming its <code>email</code> and <code>name</code> columns.</p>
+        <form action="/admin/subscribers/import" method="post" enctype="multipart/form-data">
+            <label>
+                <input type="radio" name="mode" value="confirmation" checked>
+                Send them a confirmation email
+            </label>
+            <label>
+                <input type="radio" name="mode" value="confirmed">
+                Import them as confirmed
+            </label>
+            <br>
+            <input type="file" name="file" accept=".csv,text/csv">
+            <button type="submit">Import</button>
+        </form>
+        <h2>Export subscribers</h2>
+        <form action="/admin/subscribers/export" method="get">
+            <label>Status
+                <select name="status">
+                    <option value="">all</option>
+                    {status_options_html}
+                </select>
+            </label>
+            <button type="submit">Export as CSV</button>
+        </form>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+        ))
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportParameters {
+    #[serde(default)]
+    status: String,
+}
+
+struct SubscriberRow {
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    unsubscribed_at: Option<DateTime<Utc>>,
+}
+
+#[get("/subscribers/export", wrap = "from_fn(reject_non_admins)")]
+#[tracing::instrument(name = "Export subscribers", skip(pool))]
+pub async fn export_subscribers(
+    parameters: web::Query<ExportParameters>,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let status = match parameters.0.status.trim() {
+        "" => None,
+        s if SUBSCRIPTION_STATUSES.contains(&s) => Some(s.to_owned()),
+        s => return Err(utils::e400(format!("{} is not a valid status.", s))),
+    };
+    let rows = get_subscribers(pool.as_ref(), status.as_deref())
+        .await
+        .map_err(utils::e500)?;
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record([
+            "email",
+            "name",
+            "status",
+            "subscribed_at",
+            "unsubscribed_at",
+        ])
+        .map_err(utils::e500)?;
+    for r in rows {
+        writer
+            .write_record([
+                r.email,
+                r.name,
+                r.status,
+                r.subscribed_at.to_rfc3339(),
+                r.unsubscribed_at
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_default(),
+            ])
+            .map_err(utils::e500)?;
+    }
+    let csv = writer.into_inner().map_err(utils::e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "subscribers-{}.csv",
+                status.as_deref().unwrap_or("all")
+            ))],
+        })
+        .body(csv))
+}
+
+#[tracing::instrument(skip(exec))]
+async fn get_subscribers(
+    exec: impl PgExecutor<'_>,
+    status: Option<&str>,
+) -> anyhow::Result<Vec<SubscriberRow>> {
+    sqlx::query_as!(
+        SubscriberRow,
+        r#"
+        SELECT email, name, status, subscribed_at, unsubscribed_at
+        FROM subscriptions
+        WHERE ($1::text IS NULL OR status = $1)
+        ORDER BY subscribed_at, email
+        "#,
+        status
+    )
+    .fetch_all(exec)
+    .await
+    .context("Failed to retrieve the subscribers.")
+}
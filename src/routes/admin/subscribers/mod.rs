@@ -0,0 +1,6 @@
+mod get;
+mod post;
+mod records;
+
+pub use get::{export_subscribers, subscribers};
+pub use post::import_subscribers;
@@ -0,0 +1,312 @@
+use super::records::RecordReader;
+use crate::{
+    auth::reject_non_admins,
+    domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionToken},
+    routes::subscriptions::store_token,
+    utils,
+    workers::confirmation_email::enqueue_confirmation_email,
+};
+use actix_multipart::Multipart;
+use actix_web::{http::header, middleware::from_fn, post, web, HttpResponse, Responder};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use futures_util::TryStreamExt;
+use htmlescape::encode_minimal as escape;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Write;
+use uuid::Uuid;
+
+/// Past this size, the rest of an uploaded CSV file is not imported.
+const MAX_IMPORT_BYTES: usize = 5 * 1024 * 1024;
+/// Past this many rows, header included, the rest of a CSV file is not imported.
+const MAX_IMPORT_ROWS: usize = 10_000;
+
+/// How imported subscribers are let in.
+#[derive(Clone, Copy, Debug)]
+enum ImportMode {
+    /// They have already opted in elsewhere.
+    Confirmed,
+    /// They are sent the same confirmation email as people subscribing through the form.
+    SendConfirmation,
+}
+
+impl ImportMode {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "confirmed" => Ok(Self::Confirmed),
+            "confirmation" => Ok(Self::SendConfirmation),
+            other => Err(format!("{} is not a valid import mode.", other)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ImportReport {
+    n_imported: usize,
+    n_existing: usize,
+    errors: Vec<String>,
+}
+
+/// The position of the columns we need, read from the header row.
+struct Columns {
+    email: usize,
+    name: usize,
+}
+
+impl Columns {
+    fn parse(header: &[String]) -> Result<Self, String> {
+        let position = |column: &str| {
+            header.iter().position(|h| {
+                h.trim_start_matches('\u{feff}')
+                    .trim()
+                    .eq_ignore_ascii_case(column)
+            })
+        };
+        match (position("email"), position("name")) {
+            (Some(email), Some(name)) => Ok(Self { email, name }),
+            _ => Err("The first row of the CSV file must name its email and name columns.".into()),
+        }
+    }
+}
+
+/// Reads the uploaded CSV file as it arrives, importing every valid row.
+/// The `mode` field must come before the `file` field.
+#[post("/subscribers/import", wrap = "from_fn(reject_non_admins)")]
+#[tracing::instrument(name = "Import subscribers", skip_all)]
+pub async fn import_subscribers(
+    mut payload: Multipart,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let mut mode = None;
+    let mut report = None;
+    while let Some(mut field) = payload.try_next().await.map_err(utils::e400)? {
+        match field.name() {
+            Some("mode") => {
+                let mut value = Vec::new();
+                while let Some(chunk) = field.try_next().await.map_err(utils::e400)? {
+                    value.extend_from_slice(&chunk);
+                    if value.len() > 64 {
+                        return Ok(error_redirect("The import mode is not valid.".into()));
+                    }
+                }
+                match ImportMode::parse(&String::from_utf8_lossy(&value)) {
+                    Ok(m) => mode = Some(m),
+                    Err(e) => return Ok(error_redirect(e)),
+                }
+            }
+            Some("file") => {
+                let Some(mode) = mode else {
+                    return Ok(error_redirect("Please choose an import mode.".into()));
+                };
+                let mut importer = Importer {
+                    mode,
+                    pool: pool.as_ref(),
+                    columns: None,
+                    n_rows: 0,
+                    truncated: false,
+                    report: ImportReport::default(),
+                };
+                let mut reader = RecordReader::new();
+                let mut n_bytes = 0;
+                while let Some(chunk) = field.try_next().await.map_err(utils::e400)? {
+                    if importer.truncated {
+                        break;
+                    }
+                    n_bytes += chunk.len();
+                    if n_bytes > MAX_IMPORT_BYTES {
+                        importer.truncate(format!(
+                            "The file is larger than {} MiB - the rest of it has not been imported.",
+                            MAX_IMPORT_BYTES / 1024 / 1024
+                        ));
+                        break;
+                    }
+                    for record in reader.feed(&chunk) {
+                        importer.import(record).await.map_err(utils::e500)?;
+                    }
+                }
+                if !importer.truncated {
+                    for record in reader.finish() {
+                        importer.import(record).await.map_err(utils::e500)?;
+                    }
+                }
+                match importer.columns {
+                    None => return Ok(error_redirect("The CSV file is empty.".into())),
+                    Some(Err(e)) => return Ok(error_redirect(e)),
+                    Some(Ok(_)) => {}
+                }
+                report = Some(importer.report);
+            }
+            _ => {}
+        }
+    }
+    let Some(report) = report else {
+        return Ok(error_redirect("Please choose a CSV file to import.".into()));
+    };
+
+    let mut errors_html = String::new();
+    for e in &report.errors {
+        writeln!(errors_html, "<li>{}</li>", escape(e)).unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Import report</title>
+    </head>
+    <body>
+        <p>Imported: {n_imported}</p>
+        <p>Already subscribed: {n_existing}</p>
+        <p>Rows with errors: {n_errors}</p>
+        <ul>
+            {errors_html}
+        </ul>
+        <p><a href="/admin/subscribers">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+            n_imported = report.n_imported,
+            n_existing = report.n_existing,
+            n_errors = report.errors.len(),
+        )))
+}
+
+fn error_redirect(e: String) -> HttpResponse {
+    FlashMessage::error(e).send();
+    utils::see_other("/admin/subscribers")
+}
+
+struct Importer<'a> {
+    mode: ImportMode,
+    pool: &'a PgPool,
+    /// Known once the header row has been read.
+    columns: Option<Result<Columns, String>>,
+    n_rows: usize,
+    /// Set once the file has gone past the limits; the rows after that are ignored.
+    truncated: bool,
+    report: ImportReport,
+}
+
+impl Importer<'_> {
+    /// Stops importing rows, reporting why.
+    fn truncate(&mut self, reason: String) {
+        self.report.errors.push(reason);
+        self.truncated = true;
+    }
+
+    /// Imports a row, recording why it has been rejected if it has.
+    /// Fails only if the database cannot be reached.
+    async fn import(&mut self, record: Vec<String>) -> anyhow::Result<()> {
+        if self.truncated {
+            return Ok(());
+        }
+        self.n_rows += 1;
+        let row = self.n_rows;
+        if row > MAX_IMPORT_ROWS {
+            self.truncate(format!(
+                "The file has more than {} rows - the rest of it has not been imported.",
+                MAX_IMPORT_ROWS
+            ));
+            return Ok(());
+        }
+        let columns = match &self.columns {
+            Some(Ok(c)) => c,
+            // The rows cannot be read without a valid header
+            Some(Err(_)) => return Ok(()),
+            None => {
+                self.columns = Some(Columns::parse(&record));
+                return Ok(());
+            }
+        };
+        if record.iter().all(|f| f.trim().is_empty()) {
+            return Ok(());
+        }
+
+        let ns = match parse_row(&record, columns) {
+            Ok(ns) => ns,
+            Err(e) => {
+                self.report.errors.push(format!("Row {}: {}", row, e));
+                return Ok(());
+            }
+        };
+        match self.insert(&ns).await {
+            Ok(true) => self.report.n_imported += 1,
+            Ok(false) => self.report.n_existing += 1,
+            Err(e) => {
+                tracing::error!(error.cause_chain = ?e, error.message = %e, row, "Failed to import a subscriber");
+                self.report.errors.push(format!(
+                    "Row {}: the subscriber could not be imported.",
+                    row
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `false` if the email address is already subscribed.
+    async fn insert(&self, ns: &NewSubscriber) -> anyhow::Result<bool> {
+        let mut txn = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to acquire a Postgres connection from the pool")?;
+        let status = match self.mode {
+            ImportMode::Confirmed => "confirmed",
+            ImportMode::SendConfirmation => "pending_confirmation",
+        };
+        let Some(subscriber_id) = insert_subscriber(txn.as_mut(), ns, status).await? else {
+            return Ok(false);
+        };
+        if let ImportMode::SendConfirmation = self.mode {
+            let token = SubscriptionToken::generate();
+            store_token(txn.as_mut(), subscriber_id, &token)
+                .await
+                .context("Failed to store the confirmation token for an imported subscriber.")?;
+            enqueue_confirmation_email(subscriber_id, txn.as_mut())
+                .await
+                .context("Failed to queue a confirmation email.")?;
+        }
+        txn.commit()
+            .await
+            .context("Failed to commit SQL transaction to import a subscriber.")?;
+        Ok(true)
+    }
+}
+
+fn parse_row(record: &[String], columns: &Columns) -> Result<NewSubscriber, String> {
+    let (Some(email), Some(name)) = (record.get(columns.email), record.get(columns.name)) else {
+        return Err("the row is missing the email or the name.".into());
+    };
+    Ok(NewSubscriber {
+        email: SubscriberEmail::parse(email.trim().to_owned())?,
+        name: SubscriberName::parse(name.trim().to_owned())?,
+    })
+}
+
+/// Returns `None` if the email address is already subscribed.
+#[tracing::instrument(skip(exec, ns))]
+async fn insert_subscriber(
+    exec: impl PgExecutor<'_>,
+    ns: &NewSubscriber,
+    status: &str,
+) -> anyhow::Result<Option<Uuid>> {
+    let r = sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, $2, $3, now(), $4)
+        ON CONFLICT (email) DO NOTHING
+        RETURNING id
+        "#,
+        Uuid::new_v4(),
+        ns.email.as_ref(),
+        ns.name.as_ref(),
+        status
+    )
+    .fetch_optional(exec)
+    .await
+    .context("Failed to insert an imported subscriber.")?;
+    Ok(r.map(|r| r.id))
+}
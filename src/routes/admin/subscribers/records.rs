@@ -0,0 +1,116 @@
+use csv_core::{ReadRecordResult, Reader};
+
+/// Splits a CSV document arriving in chunks into records,
+/// holding no more than the record being read in memory.
+pub(super) struct RecordReader {
+    reader: Reader,
+    output: Vec<u8>,
+    output_len: usize,
+    ends: Vec<usize>,
+    ends_len: usize,
+}
+
+impl RecordReader {
+    pub(super) fn new() -> Self {
+        Self {
+            reader: Reader::new(),
+            output: vec![0; 1024],
+            output_len: 0,
+            ends: vec![0; 8],
+            ends_len: 0,
+        }
+    }
+
+    /// Returns the records completed by this chunk.
+    pub(super) fn feed(&mut self, chunk: &[u8]) -> Vec<Vec<String>> {
+        // An empty input tells the reader that the document is over
+        if chunk.is_empty() {
+            return Vec::new();
+        }
+        self.read(chunk)
+    }
+
+    /// Returns the last record, if the document does not end with a line break.
+    pub(super) fn finish(mut self) -> Vec<Vec<String>> {
+        self.read(&[])
+    }
+
+    fn read(&mut self, mut input: &[u8]) -> Vec<Vec<String>> {
+        let mut records = Vec::new();
+        loop {
+            let (result, n_in, n_out, n_ends) = self.reader.read_record(
+                input,
+                &mut self.output[self.output_len..],
+                &mut self.ends[self.ends_len..],
+            );
+            input = &input[n_in..];
+            self.output_len += n_out;
+            self.ends_len += n_ends;
+            match result {
+                ReadRecordResult::InputEmpty | ReadRecordResult::End => return records,
+                ReadRecordResult::OutputFull => self.output.resize(self.output.len() * 2, 0),
+                ReadRecordResult::OutputEndsFull => self.ends.resize(self.ends.len() * 2, 0),
+                ReadRecordResult::Record => records.push(self.take_record()),
+            }
+        }
+    }
+
+    fn take_record(&mut self) -> Vec<String> {
+        let mut start = 0;
+        let fields = self.ends[..self.ends_len]
+            .iter()
+            .map(|&end| {
+                let field = String::from_utf8_lossy(&self.output[start..end]).into_owned();
+                start = end;
+                field
+            })
+            .collect();
+        self.output_len = 0;
+        self.ends_len = 0;
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str =
+        "email,name\r\nursula@example.com,Ursula Le Guin\n\"ted@example.com\",\"Chiang, Ted\"\nlast@example.com,Last";
+
+    fn read_in_chunks(document: &str, chunk_size: usize) -> Vec<Vec<String>> {
+        let mut reader = RecordReader::new();
+        let mut records = Vec::new();
+        for chunk in document.as_bytes().chunks(chunk_size) {
+            records.extend(reader.feed(chunk));
+        }
+        records.extend(reader.finish());
+        records
+    }
+
+    #[test]
+    fn records_are_the_same_however_the_document_is_split() {
+        let expected = vec![
+            vec!["email", "name"],
+            vec!["ursula@example.com", "Ursula Le Guin"],
+            vec!["ted@example.com", "Chiang, Ted"],
+            vec!["last@example.com", "Last"],
+        ];
+        for chunk_size in 1..=DOCUMENT.len() {
+            assert_eq!(expected, read_in_chunks(DOCUMENT, chunk_size));
+        }
+    }
+
+    #[test]
+    fn records_longer_than_the_buffers_are_read_whole() {
+        let name = "a".repeat(5000);
+        let fields: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        let document = format!("{},{}\n", name, fields.join(","));
+
+        let records = read_in_chunks(&document, 7);
+
+        assert_eq!(1, records.len());
+        assert_eq!(name, records[0][0]);
+        assert_eq!(21, records[0].len());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{auth::reject_non_admins, domain::UserRole, utils};
+use actix_web::{get, http::header, middleware::from_fn, web, HttpResponse, Responder};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal as escape;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Write;
+use uuid::Uuid;
+
+#[get("/users", wrap = "from_fn(reject_non_admins)")]
+pub async fn manage_users(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", escape(m.content())).unwrap();
+    }
+
+    let mut users_html = String::new();
+    for u in get_users(pool.as_ref()).await.map_err(utils::e500)? {
+        writeln!(
+            users_html,
+            r#"<tr>
+                <td>{username}</td>
+                <td>{email}</td>
+                <td>
+                    <form action="/admin/users/{id}/role" method="post">
+                        {role_select}
+                        <button type="submit">Change role</button>
+                    </form>
+                </td>
+            </tr>"#,
+            id = u.id,
+            username = escape(&u.username),
+            email = escape(u.email.as_deref().unwrap_or_default()),
+            role_select = role_select(UserRole::parse(&u.role).ok()),
+        )
+        .unwrap();
+    }
+
+    let mut invitations_html = String::new();
+    for i in get_pending_invitations(pool.as_ref())
+        .await
+        .map_err(utils::e500)?
+    {
+        writeln!(
+            invitations_html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&i.email),
+            i.role,
+            i.expires_at.to_rfc3339(),
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Users</title>
+    </head>
+    <body>
+        {msg_html}
+        <h2>Users</h2>
+        <table>
+            <tr>
+                <th>Username</th>
+                <th>Email</th>
+                <th>Role</th>
+            </tr>
+            {users_html}
+        </table>
+        <h2>Pending invitations</h2>
+        <table>
+            <tr>
+                <th>Email</th>
+                <th>Role</th>
+                <th>Expires at</th>
+            </tr>
+            {invitations_html}
+        </table>
+        <h2>Invite a collaborator</h2>
+        <form action="/admin/users/invitations" method="post">
+            <label>Email
+                <input type="email" name="email">
+            </label>
+            {role_select}
+            <button type="submit">Send invitation</button>
+        </form>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>
+            "#,
+            role_select = role_select(None),
+        )))
+}
+
+fn role_select(selected: Option<UserRole>) -> String {
+    let mut html = String::from(r#"<select name="role">"#);
+    for role in UserRole::ALL {
+        let attr = if Some(role) == selected {
+            " selected"
+        } else {
+            ""
+        };
+        write!(html, r#"<option value="{role}"{attr}>{role}</option>"#).unwrap();
+    }
+    html.push_str("</select>");
+    html
+}
+
+struct User {
+    id: Uuid,
+    username: String,
+    email: Option<String>,
+    role: String,
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_users(exec: impl PgExecutor<'_>) -> anyhow::Result<Vec<User>> {
+    let users = sqlx::query_as!(
+        User,
+        r#"
+        SELECT id, username, email, role
+        FROM users
+        ORDER BY username
+        "#
+    )
+    .fetch_all(exec)
+    .await
+    .context("Failed to retrieve users.")?;
+    Ok(users)
+}
+
+struct Invitation {
+    email: String,
+    role: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_pending_invitations(exec: impl PgExecutor<'_>) -> anyhow::Result<Vec<Invitation>> {
+    let invitations = sqlx::query_as!(
+        Invitation,
+        r#"
+        SELECT email, role, expires_at
+        FROM user_invitations
+        WHERE expires_at > now()
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(exec)
+    .await
+    .context("Failed to retrieve pending invitations.")?;
+    Ok(invitations)
+}
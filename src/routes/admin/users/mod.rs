@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::manage_users;
+pub use post::{change_role, invite_user};
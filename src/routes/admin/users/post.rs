@@ -0,0 +1,177 @@
+use crate::{
+    app::AppBaseUrl,
+    auth::{reject_non_admins, UserId},
+    domain::{InvitationToken, SubscriberEmail, UserRole},
+    email_client::EmailTransport,
+    utils,
+};
+use actix_web::{middleware::from_fn, post, web, Responder};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+struct InvitationFormData {
+    email: String,
+    role: String,
+}
+
+#[post("/users/invitations", wrap = "from_fn(reject_non_admins)")]
+#[tracing::instrument(
+    name = "Invite a collaborator",
+    skip_all,
+    fields(user_id = %*user_id, email = %form.email, role = %form.role)
+)]
+pub async fn invite_user(
+    form: web::Form<InvitationFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<dyn EmailTransport>,
+    base_url: web::Data<AppBaseUrl>,
+    user_id: web::ReqData<UserId>,
+) -> actix_web::Result<impl Responder> {
+    let (email, role) = match parse_invitation(form.0) {
+        Ok(i) => i,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/admin/users"));
+        }
+    };
+    if is_registered(pool.as_ref(), &email)
+        .await
+        .map_err(utils::e500)?
+    {
+        FlashMessage::error(format!("{} already has an account.", email)).send();
+        return Ok(utils::see_other("/admin/users"));
+    }
+
+    let token = InvitationToken::generate();
+    let mut txn = pool.begin().await.map_err(utils::e500)?;
+    store_invitation(txn.as_mut(), &token, &email, role, **user_id)
+        .await
+        .map_err(utils::e500)?;
+    txn.commit().await.map_err(utils::e500)?;
+
+    if let Err(e) =
+        send_invitation_email(email_client.as_ref(), &email, role, &base_url.0, &token).await
+    {
+        tracing::error!(error.cause_chain = ?e, "Failed to send the invitation email.");
+        FlashMessage::error(format!(
+            "The invitation to {} has been saved, but its email could not be sent.",
+            email
+        ))
+        .send();
+        return Ok(utils::see_other("/admin/users"));
+    }
+    FlashMessage::info(format!("An invitation has been sent to {}.", email)).send();
+    Ok(utils::see_other("/admin/users"))
+}
+
+fn parse_invitation(form: InvitationFormData) -> Result<(SubscriberEmail, UserRole), String> {
+    let email = SubscriberEmail::parse(form.email)?;
+    let role = UserRole::parse(&form.role)?;
+    Ok((email, role))
+}
+
+#[tracing::instrument(skip(exec))]
+async fn is_registered(exec: impl PgExecutor<'_>, email: &SubscriberEmail) -> anyhow::Result<bool> {
+    let r = sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM users WHERE email = $1) AS "exists!""#,
+        email.as_ref()
+    )
+    .fetch_one(exec)
+    .await
+    .context("Failed to look up the invited email.")?;
+    Ok(r.exists)
+}
+
+#[tracing::instrument(skip(exec, token))]
+async fn store_invitation(
+    exec: impl PgExecutor<'_>,
+    token: &InvitationToken,
+    email: &SubscriberEmail,
+    role: UserRole,
+    invited_by: Uuid,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_invitations (token, email, role, invited_by, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, now(), now() + interval '7 days')
+        "#,
+        token.as_ref(),
+        email.as_ref(),
+        role.as_str(),
+        invited_by
+    )
+    .execute(exec)
+    .await
+    .context("Failed to store the invitation.")?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(ec, base_url, token))]
+async fn send_invitation_email(
+    ec: &dyn EmailTransport,
+    email: &SubscriberEmail,
+    role: UserRole,
+    base_url: &str,
+    token: &InvitationToken,
+) -> anyhow::Result<()> {
+    let invitation_link = format!("{}/invitations/accept?token={}", base_url, token.as_ref());
+
+    let html_body = format!(
+        "You have been invited to join our newsletter as a(n) {}.<br />\
+                Click <a href=\"{}\">here</a> to create your account. \
+                The invitation expires in 7 days.",
+        role, invitation_link
+    );
+
+    let text_body = format!(
+        "You have been invited to join our newsletter as a(n) {}.\n\
+                Visit {} to create your account. The invitation expires in 7 days.",
+        role, invitation_link
+    );
+
+    ec.send_email(email, "You have been invited!", &html_body, &text_body, &[])
+        .await
+}
+
+#[derive(Deserialize)]
+struct RoleFormData {
+    role: String,
+}
+
+#[post("/users/{user_id}/role", wrap = "from_fn(reject_non_admins)")]
+#[tracing::instrument(name = "Change a user's role", skip(form, pool, current_user_id))]
+pub async fn change_role(
+    user_id: web::Path<Uuid>,
+    form: web::Form<RoleFormData>,
+    pool: web::Data<PgPool>,
+    current_user_id: web::ReqData<UserId>,
+) -> actix_web::Result<impl Responder> {
+    let role = match UserRole::parse(&form.role) {
+        Ok(r) => r,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/admin/users"));
+        }
+    };
+    if *user_id == **current_user_id {
+        FlashMessage::error("You cannot change your own role.").send();
+        return Ok(utils::see_other("/admin/users"));
+    }
+
+    sqlx::query!(
+        "UPDATE users SET role = $2 WHERE id = $1",
+        *user_id,
+        role.as_str()
+    )
+    .execute(pool.as_ref())
+    .await
+    .context("Failed to change the user's role.")
+    .map_err(utils::e500)?;
+
+    FlashMessage::info("The role has been changed.").send();
+    Ok(utils::see_other("/admin/users"))
+}
@@ -0,0 +1,175 @@
+use super::ApiError;
+use crate::{
+    auth::ApiKey,
+    idempotency::{self, IdempotencyKey, NextAction},
+    routes::admin::{
+        insert_newsletter_issue, parse_schedule, set_issue_lists, set_issue_segment, Publication,
+    },
+    workers::issue_delivery::enqueue_delivery_tasks,
+};
+use actix_web::{get, http::header, post, web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+#[derive(Deserialize)]
+struct NewIssueBody {
+    title: String,
+    text: String,
+    html: String,
+    /// Publishes the issue right away when missing.
+    scheduled_for: Option<String>,
+    /// Saves the issue without publishing it.
+    #[serde(default)]
+    draft: bool,
+    /// The ids of the lists to send the issue to - every confirmed subscriber when empty.
+    #[serde(default)]
+    lists: Vec<Uuid>,
+    /// Narrows the audience down to a segment.
+    segment_id: Option<Uuid>,
+    /// Tracks the opens of the issue and the clicks on its links.
+    #[serde(default)]
+    tracking: bool,
+}
+
+#[derive(Serialize)]
+struct AcceptedIssue {
+    issue_id: Uuid,
+    status: &'static str,
+    status_url: String,
+}
+
+/// Retrying with the same `Idempotency-Key` header returns the response of the first attempt.
+#[post("/issues")]
+#[tracing::instrument(
+    name = "Publish a newsletter issue through the API",
+    skip_all,
+    fields(api_key_id = %api_key.id)
+)]
+pub async fn create_issue(
+    req: HttpRequest,
+    body: web::Json<NewIssueBody>,
+    pool: web::Data<PgPool>,
+    api_key: web::ReqData<ApiKey>,
+) -> Result<HttpResponse, ApiError> {
+    let idempotency_key: IdempotencyKey = req
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .ok_or_else(|| {
+            ApiError::InvalidRequest(format!(
+                "The {} header is required.",
+                IDEMPOTENCY_KEY_HEADER
+            ))
+        })?
+        .to_str()
+        .map_err(|e| ApiError::InvalidRequest(e.to_string()))?
+        .to_owned()
+        .try_into()
+        .map_err(|e: anyhow::Error| ApiError::InvalidRequest(e.to_string()))?;
+    let NewIssueBody {
+        title,
+        text,
+        html,
+        scheduled_for,
+        draft,
+        mut lists,
+        segment_id,
+        tracking,
+    } = body.0;
+    lists.sort();
+    lists.dedup();
+    let publication = match scheduled_for.as_deref().map(str::trim) {
+        _ if draft => Publication::Draft,
+        None | Some("") => Publication::Now,
+        Some(s) => Publication::At(parse_schedule(s).map_err(ApiError::InvalidRequest)?),
+    };
+
+    let user_id = api_key.created_by;
+    let mut txn = match idempotency::try_processing(user_id, &idempotency_key, &pool).await? {
+        NextAction::StartProcessing(t) => t,
+        NextAction::ReturnSavedResponse(r) => return Ok(r),
+    };
+
+    let issue_id =
+        insert_newsletter_issue(&title, &text, &html, &publication, tracking, txn.as_mut())
+            .await
+            .context("Failed to store newsletter issue details")?;
+    if set_issue_lists(txn.as_mut(), issue_id, &lists).await? != lists.len() as u64 {
+        return Err(ApiError::InvalidRequest(
+            "Some of the lists do not exist.".into(),
+        ));
+    }
+    if let Some(segment_id) = segment_id {
+        if !set_issue_segment(txn.as_mut(), issue_id, segment_id).await? {
+            return Err(ApiError::InvalidRequest(
+                "The segment does not exist.".into(),
+            ));
+        }
+    }
+    if let Publication::Now = publication {
+        enqueue_delivery_tasks(issue_id, txn.as_mut())
+            .await
+            .context("Failed to enqueue delivery tasks")?;
+    }
+
+    let status_url = format!("/api/v1/issues/{}", issue_id);
+    let resp = HttpResponse::Accepted()
+        .insert_header((header::LOCATION, status_url.clone()))
+        .json(AcceptedIssue {
+            issue_id,
+            status: publication.status(),
+            status_url,
+        });
+    let resp = idempotency::save_response(resp, user_id, &idempotency_key, txn).await?;
+    Ok(resp)
+}
+
+#[derive(Serialize)]
+struct IssueStatus {
+    id: Uuid,
+    title: String,
+    status: String,
+    published_at: Option<DateTime<Utc>>,
+    scheduled_for: Option<DateTime<Utc>>,
+    n_queued: i64,
+    n_delivered: i32,
+    n_failed: i64,
+}
+
+/// The delivery progress of an issue.
+#[get("/issues/{issue_id}")]
+#[tracing::instrument(name = "Get the status of a newsletter issue", skip(pool))]
+pub async fn get_issue_status(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let issue = sqlx::query_as!(
+        IssueStatus,
+        r#"
+        SELECT
+            i.id,
+            i.title,
+            i.status,
+            i.published_at,
+            i.scheduled_for,
+            (SELECT COUNT(*) FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.id) AS "n_queued!",
+            i.n_delivered,
+            (SELECT COUNT(*) FROM failed_deliveries f
+                WHERE f.newsletter_issue_id = i.id) AS "n_failed!"
+        FROM newsletter_issues i
+        WHERE i.id = $1
+        "#,
+        *issue_id
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to retrieve the newsletter issue.")?
+    .ok_or_else(|| ApiError::NotFound("There is no newsletter issue with this id.".into()))?;
+
+    Ok(HttpResponse::Ok().json(issue))
+}
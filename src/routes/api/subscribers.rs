@@ -0,0 +1,408 @@
+use super::ApiError;
+use crate::{
+    domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionToken},
+    routes::{
+        admin::add_subscriber_to_lists,
+        subscriptions::{store_token, SUBSCRIPTION_STATUSES},
+    },
+    suppression::is_suppressed,
+    utils::escape_like,
+    workers::confirmation_email::enqueue_confirmation_email,
+};
+use actix_web::{delete, get, http::header, post, put, web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, PgExecutor, PgPool};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+const PAGE_SIZE: i64 = 50;
+
+/// Free-form key/value pairs segments can filter on.
+type Attributes = BTreeMap<String, String>;
+
+#[derive(Serialize)]
+struct Subscriber {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    unsubscribed_at: Option<DateTime<Utc>>,
+    attributes: Json<Attributes>,
+}
+
+#[derive(Serialize)]
+struct SubscriberPage {
+    subscribers: Vec<Subscriber>,
+    page: i64,
+    total: i64,
+}
+
+#[derive(Deserialize)]
+struct ListParameters {
+    status: Option<String>,
+    /// Matched against both the email and the name, ignoring case.
+    search: Option<String>,
+    page: Option<i64>,
+}
+
+#[get("/subscribers")]
+#[tracing::instrument(name = "List subscribers", skip(parameters, pool))]
+pub async fn list_subscribers(
+    parameters: web::Query<ListParameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let ListParameters {
+        status,
+        search,
+        page,
+    } = parameters.0;
+    if let Some(status) = &status {
+        if !SUBSCRIPTION_STATUSES.contains(&status.as_str()) {
+            return Err(ApiError::InvalidRequest(format!(
+                "{} is not a valid status - expected one of {}.",
+                status,
+                SUBSCRIPTION_STATUSES.join(", ")
+            )));
+        }
+    }
+    let pattern = search.map(|s| format!("%{}%", escape_like(&s)));
+    let page = page.unwrap_or(1).max(1);
+    let offset = (page - 1)
+        .checked_mul(PAGE_SIZE)
+        .ok_or_else(|| ApiError::InvalidRequest(format!("Page {} is out of range.", page)))?;
+
+    let subscribers = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT id, email, name, status, subscribed_at, unsubscribed_at,
+            attributes AS "attributes: Json<Attributes>"
+        FROM subscriptions
+        WHERE ($1::text IS NULL OR status = $1)
+        AND ($2::text IS NULL OR email ILIKE $2 OR name ILIKE $2)
+        ORDER BY subscribed_at DESC, id
+        LIMIT $3
+        OFFSET $4
+        "#,
+        status,
+        pattern,
+        PAGE_SIZE,
+        offset
+    )
+    .fetch_all(pool.as_ref())
+    .await
+    .context("Failed to list subscribers.")?;
+
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "n!"
+        FROM subscriptions
+        WHERE ($1::text IS NULL OR status = $1)
+        AND ($2::text IS NULL OR email ILIKE $2 OR name ILIKE $2)
+        "#,
+        status,
+        pattern,
+    )
+    .fetch_one(pool.as_ref())
+    .await
+    .context("Failed to count subscribers.")?;
+
+    Ok(HttpResponse::Ok().json(SubscriberPage {
+        subscribers,
+        page,
+        total,
+    }))
+}
+
+#[get("/subscribers/{subscriber_id}")]
+#[tracing::instrument(name = "Get a subscriber", skip(pool))]
+pub async fn get_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT id, email, name, status, subscribed_at, unsubscribed_at,
+            attributes AS "attributes: Json<Attributes>"
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        *subscriber_id
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to retrieve the subscriber.")?
+    .ok_or_else(not_found)?;
+
+    Ok(HttpResponse::Ok().json(subscriber))
+}
+
+#[derive(Deserialize)]
+struct NewSubscriberBody {
+    email: String,
+    name: String,
+    /// Subscribers who already gave their consent elsewhere skip the confirmation email.
+    #[serde(default)]
+    confirmed: bool,
+    /// The ids of the lists the subscriber opts into.
+    #[serde(default)]
+    lists: Vec<Uuid>,
+    #[serde(default)]
+    attributes: Attributes,
+}
+
+#[post("/subscribers")]
+#[tracing::instrument(
+    name = "Create a subscriber",
+    skip_all,
+    fields(subscriber_email = %body.email, confirmed = body.confirmed)
+)]
+pub async fn create_subscriber(
+    body: web::Json<NewSubscriberBody>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let NewSubscriberBody {
+        email,
+        name,
+        confirmed,
+        mut lists,
+        attributes,
+    } = body.0;
+    lists.sort();
+    lists.dedup();
+    let ns = NewSubscriber {
+        email: SubscriberEmail::parse(email).map_err(ApiError::InvalidRequest)?,
+        name: SubscriberName::parse(name).map_err(ApiError::InvalidRequest)?,
+    };
+    let status = if confirmed {
+        "confirmed"
+    } else {
+        "pending_confirmation"
+    };
+
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    // Addresses that bounced or complained must stay out of the audience
+    if is_suppressed(ns.email.as_ref(), txn.as_mut())
+        .await
+        .context("Failed to check the suppression list.")?
+    {
+        return Err(ApiError::Conflict(format!(
+            "{} has bounced or complained.",
+            ns.email.as_ref()
+        )));
+    }
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status, attributes)
+        VALUES ($1, $2, $3, now(), $4, $5)
+        ON CONFLICT (email) DO NOTHING
+        RETURNING id, email, name, status, subscribed_at, unsubscribed_at,
+            attributes AS "attributes: Json<Attributes>"
+        "#,
+        Uuid::new_v4(),
+        ns.email.as_ref(),
+        ns.name.as_ref(),
+        status,
+        Json(&attributes) as _
+    )
+    .fetch_optional(txn.as_mut())
+    .await
+    .context("Failed to insert the new subscriber.")?
+    .ok_or_else(|| ApiError::Conflict(format!("{} is already a subscriber.", ns.email.as_ref())))?;
+    if add_subscriber_to_lists(txn.as_mut(), subscriber.id, &lists).await? != lists.len() as u64 {
+        return Err(ApiError::InvalidRequest(
+            "Some of the lists do not exist.".into(),
+        ));
+    }
+    if !confirmed {
+        let token = SubscriptionToken::generate();
+        store_token(txn.as_mut(), subscriber.id, &token)
+            .await
+            .context("Failed to store the confirmation token for a new subscriber.")?;
+        enqueue_confirmation_email(subscriber.id, txn.as_mut())
+            .await
+            .context("Failed to queue a confirmation email.")?;
+    }
+    txn.commit()
+        .await
+        .context("Failed to commit SQL transaction to store a new subscriber.")?;
+
+    Ok(HttpResponse::Created()
+        .insert_header((
+            header::LOCATION,
+            format!("/api/v1/subscribers/{}", subscriber.id),
+        ))
+        .json(subscriber))
+}
+
+#[post("/subscribers/{subscriber_id}/confirm")]
+#[tracing::instrument(name = "Confirm a subscriber", skip(pool))]
+pub async fn confirm_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    // Addresses that bounced or complained must stay out of the audience
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        UPDATE subscriptions s
+        SET status = 'confirmed'
+        WHERE id = $1
+            AND status = 'pending_confirmation'
+            AND NOT EXISTS (SELECT 1 FROM suppressed_emails x WHERE x.email = s.email)
+        RETURNING id, email, name, status, subscribed_at, unsubscribed_at,
+            attributes AS "attributes: Json<Attributes>"
+        "#,
+        *subscriber_id
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to confirm the subscriber.")?;
+
+    if let Some(subscriber) = subscriber {
+        return Ok(HttpResponse::Ok().json(subscriber));
+    }
+    match subscriber_status(pool.as_ref(), *subscriber_id).await? {
+        Some(status) if status == "pending_confirmation" => Err(ApiError::Conflict(
+            "The address of the subscriber has bounced or complained.".into(),
+        )),
+        Some(status) => Err(ApiError::Conflict(format!(
+            "Only pending subscribers can be confirmed, but this one is {}.",
+            status
+        ))),
+        None => Err(not_found()),
+    }
+}
+
+#[post("/subscribers/{subscriber_id}/unsubscribe")]
+#[tracing::instrument(name = "Unsubscribe a subscriber", skip(pool))]
+pub async fn unsubscribe_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        UPDATE subscriptions
+        SET status = 'unsubscribed', unsubscribed_at = COALESCE(unsubscribed_at, now())
+        WHERE id = $1
+        RETURNING id, email, name, status, subscribed_at, unsubscribed_at,
+            attributes AS "attributes: Json<Attributes>"
+        "#,
+        *subscriber_id
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to unsubscribe the subscriber.")?
+    .ok_or_else(not_found)?;
+
+    Ok(HttpResponse::Ok().json(subscriber))
+}
+
+/// Replaces every attribute of the subscriber.
+#[put("/subscribers/{subscriber_id}/attributes")]
+#[tracing::instrument(name = "Set the attributes of a subscriber", skip(pool, body))]
+pub async fn set_subscriber_attributes(
+    subscriber_id: web::Path<Uuid>,
+    body: web::Json<Attributes>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        UPDATE subscriptions
+        SET attributes = $2
+        WHERE id = $1
+        RETURNING id, email, name, status, subscribed_at, unsubscribed_at,
+            attributes AS "attributes: Json<Attributes>"
+        "#,
+        *subscriber_id,
+        Json(&body.0) as _
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to set the attributes of the subscriber.")?
+    .ok_or_else(not_found)?;
+
+    Ok(HttpResponse::Ok().json(subscriber))
+}
+
+#[delete("/subscribers/{subscriber_id}")]
+#[tracing::instrument(name = "Delete a subscriber", skip(pool))]
+pub async fn delete_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    remove_subscriber(txn.as_mut(), *subscriber_id)
+        .await?
+        .ok_or_else(not_found)?;
+    txn.commit()
+        .await
+        .context("Failed to commit SQL transaction to delete a subscriber.")?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Deletes the subscriber along with their confirmation token and pending deliveries,
+/// returning their email. Their other records go with them through `ON DELETE CASCADE`.
+#[tracing::instrument(skip(txn))]
+pub async fn remove_subscriber(
+    txn: &mut sqlx::PgConnection,
+    subscriber_id: Uuid,
+) -> anyhow::Result<Option<String>> {
+    sqlx::query!(
+        "DELETE FROM subscription_tokens WHERE subscriber_id = $1",
+        subscriber_id
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to delete the subscriber's confirmation token.")?;
+    let r = sqlx::query!(
+        "DELETE FROM subscriptions WHERE id = $1 RETURNING email",
+        subscriber_id
+    )
+    .fetch_optional(&mut *txn)
+    .await
+    .context("Failed to delete the subscriber.")?;
+    let Some(r) = r else {
+        return Ok(None);
+    };
+    // Pending deliveries would otherwise still go out
+    sqlx::query!(
+        "DELETE FROM issue_delivery_queue WHERE subscriber_email = $1",
+        r.email
+    )
+    .execute(&mut *txn)
+    .await
+    .context("Failed to remove the subscriber's pending deliveries.")?;
+    Ok(Some(r.email))
+}
+
+#[tracing::instrument(skip(exec))]
+async fn subscriber_status(
+    exec: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+) -> anyhow::Result<Option<String>> {
+    sqlx::query_scalar!(
+        "SELECT status FROM subscriptions WHERE id = $1",
+        subscriber_id
+    )
+    .fetch_optional(exec)
+    .await
+    .context("Failed to retrieve the status of the subscriber.")
+}
+
+fn not_found() -> ApiError {
+    ApiError::NotFound("There is no subscriber with this id.".into())
+}
@@ -1,6 +1,154 @@
-use actix_web::{get, HttpResponse, Responder};
+use crate::{app::LocalWorkers, config::HealthSettings, workers::INSTANCE_ID};
+use actix_session::storage::{RedisSessionStore, SessionStore};
+use actix_web::{get, web::Data, HttpResponse, Responder};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::{collections::BTreeMap, future::Future, time::Duration};
 
+/// Cheap liveness probe: answers as long as the server does.
 #[get("/health_check")]
 pub async fn health_check() -> impl Responder {
     HttpResponse::Ok()
 }
+
+/// How long each dependency may take to answer the readiness probe.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+struct Readiness {
+    ready: bool,
+    postgres: Check,
+    /// Only checked by the processes serving the web pages, which keep their sessions in it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    redis: Option<Check>,
+    workers: BTreeMap<&'static str, Check>,
+}
+
+#[derive(Serialize)]
+struct Check {
+    healthy: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_heartbeat: Option<DateTime<Utc>>,
+}
+
+impl Check {
+    fn from_result<T>(result: anyhow::Result<T>) -> Self {
+        Self {
+            healthy: result.is_ok(),
+            error: result.err().map(|e| format!("{:#}", e)),
+            last_heartbeat: None,
+        }
+    }
+}
+
+/// Readiness probe: checks Postgres, Redis and that every background worker this process
+/// runs has reported recently, answering with a 503 if any of them is unhealthy.
+#[get("/health/ready")]
+#[tracing::instrument(name = "Check readiness", skip_all)]
+pub async fn readiness(
+    pool: Data<PgPool>,
+    session_store: Option<Data<RedisSessionStore>>,
+    settings: Data<HealthSettings>,
+    local_workers: Data<LocalWorkers>,
+) -> impl Responder {
+    let postgres = Check::from_result(
+        with_timeout(sqlx::query("SELECT 1").execute(pool.as_ref()))
+            .await
+            .context("Postgres is unreachable"),
+    );
+    let redis = match session_store {
+        Some(store) => Some(Check::from_result(
+            with_timeout(ping_session_store(&store)).await,
+        )),
+        None => None,
+    };
+    let worker_checks = worker_checks(&pool, &local_workers.0, settings.worker_heartbeat_timeout());
+    let workers = match with_timeout(worker_checks).await {
+        Ok(w) => w,
+        Err(e) => {
+            let e = format!("{:#}", e.context("Failed to read the worker heartbeats"));
+            local_workers
+                .0
+                .iter()
+                .map(|&w| (w, Check::from_result::<()>(Err(anyhow::anyhow!(e.clone())))))
+                .collect()
+        }
+    };
+
+    let ready = postgres.healthy
+        && redis.as_ref().is_none_or(|c| c.healthy)
+        && workers.values().all(|c| c.healthy);
+    let readiness = Readiness {
+        ready,
+        postgres,
+        redis,
+        workers,
+    };
+    if ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        tracing::warn!(readiness = %serde_json::to_string(&readiness).unwrap_or_default(), "Not ready.");
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
+
+async fn with_timeout<T, E>(f: impl Future<Output = Result<T, E>>) -> anyhow::Result<T>
+where
+    E: Into<anyhow::Error>,
+{
+    tokio::time::timeout(CHECK_TIMEOUT, f)
+        .await
+        .context("Timed out")?
+        .map_err(Into::into)
+}
+
+/// Looks up a session that does not exist, which only succeeds if Redis answers.
+async fn ping_session_store(store: &RedisSessionStore) -> anyhow::Result<()> {
+    let key = "readiness-probe".to_owned().try_into()?;
+    store
+        .load(&key)
+        .await
+        .context("The session store is unreachable")?;
+    Ok(())
+}
+
+async fn worker_checks(
+    pool: &PgPool,
+    workers: &[&'static str],
+    timeout: Duration,
+) -> Result<BTreeMap<&'static str, Check>, sqlx::Error> {
+    let beats: BTreeMap<String, DateTime<Utc>> = sqlx::query!(
+        "SELECT worker, beat_at FROM worker_heartbeats WHERE instance = $1",
+        *INSTANCE_ID
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| (r.worker, r.beat_at))
+    .collect();
+    let now = Utc::now();
+    let checks = workers
+        .iter()
+        .map(|&w| {
+            let last_heartbeat = beats.get(w).copied();
+            let error = match last_heartbeat {
+                None => Some("No heartbeat has been recorded".to_owned()),
+                Some(t) if (now - t).to_std().unwrap_or_default() > timeout => {
+                    Some(format!("No heartbeat since {}", t.to_rfc3339()))
+                }
+                Some(_) => None,
+            };
+            let check = Check {
+                healthy: error.is_none(),
+                error,
+                last_heartbeat,
+            };
+            (w, check)
+        })
+        .collect();
+    Ok(checks)
+}
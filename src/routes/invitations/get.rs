@@ -0,0 +1,74 @@
+use super::{get_invitation, InvitationError};
+use crate::domain::InvitationToken;
+use actix_web::{
+    get,
+    http::header::ContentType,
+    web::{Data, Query},
+    HttpResponse, Responder,
+};
+use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::encode_minimal as escape;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+#[derive(Deserialize)]
+struct Parameters {
+    token: String,
+}
+
+#[get("/invitations/accept")]
+#[tracing::instrument(name = "Show the invitation form", skip_all)]
+pub async fn accept_invitation_form(
+    parameters: Query<Parameters>,
+    flash_messages: IncomingFlashMessages,
+    pool: Data<PgPool>,
+) -> Result<impl Responder, InvitationError> {
+    let token =
+        InvitationToken::parse(parameters.0.token).map_err(InvitationError::InvalidTokenFormat)?;
+    let invitation = get_invitation(pool.as_ref(), &token)
+        .await?
+        .ok_or(InvitationError::UnknownToken)?;
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", escape(m.content())).unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Create your account</title>
+    </head>
+    <body>
+        {msg_html}
+        <p>You have been invited as a(n) {role} with {email}.</p>
+        <form action="/invitations/accept" method="post">
+            <input hidden type="text" name="token" value="{token}">
+            <label>Username
+                <input type="text" name="username" placeholder="Enter Username">
+            </label>
+            <br>
+            <label>Password
+                <input type="password" name="password" placeholder="Enter Password">
+            </label>
+            <br>
+            <label>Confirm password
+                <input type="password" name="password_check" placeholder="Type the password again">
+            </label>
+            <br>
+            <button type="submit">Create account</button>
+        </form>
+    </body>
+</html>
+"#,
+            role = invitation.role,
+            email = escape(&invitation.email),
+            token = token.as_ref(),
+        )))
+}
@@ -0,0 +1,71 @@
+mod get;
+mod post;
+
+pub use get::accept_invitation_form;
+pub use post::accept_invitation;
+
+use crate::{
+    domain::{InvitationToken, UserRole},
+    utils,
+};
+use actix_web::http::StatusCode;
+use anyhow::Context;
+use sqlx::PgExecutor;
+use std::fmt::Debug;
+
+struct Invitation {
+    email: String,
+    role: UserRole,
+}
+
+/// Returns the invitation for the given token, unless it has expired or already been used.
+#[tracing::instrument(skip_all)]
+async fn get_invitation(
+    exec: impl PgExecutor<'_>,
+    token: &InvitationToken,
+) -> anyhow::Result<Option<Invitation>> {
+    let r = sqlx::query!(
+        r#"
+        SELECT email, role
+        FROM user_invitations
+        WHERE token = $1 AND expires_at > now()
+        "#,
+        token.as_ref()
+    )
+    .fetch_optional(exec)
+    .await
+    .context("Failed to retrieve the invitation.")?;
+    r.map(|r| {
+        Ok(Invitation {
+            email: r.email,
+            role: UserRole::parse(&r.role).map_err(anyhow::Error::msg)?,
+        })
+    })
+    .transpose()
+}
+
+#[derive(thiserror::Error)]
+pub enum InvitationError {
+    #[error("{0}")]
+    InvalidTokenFormat(String),
+    #[error("The invitation is unknown, has expired or has already been used.")]
+    UnknownToken,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for InvitationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        utils::error_chain_fmt(self, f)
+    }
+}
+
+impl actix_web::ResponseError for InvitationError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::InvalidTokenFormat(_) => StatusCode::BAD_REQUEST,
+            Self::UnknownToken => StatusCode::UNAUTHORIZED,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use super::InvitationError;
+use crate::{
+    auth::{self, CreateUserError, NewUser},
+    domain::{InvitationToken, UserRole, ValidPassword},
+    utils,
+};
+use actix_web::{
+    post,
+    web::{Data, Form},
+    Responder,
+};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use sqlx::{PgExecutor, PgPool};
+
+#[derive(Deserialize)]
+struct FormData {
+    token: String,
+    username: String,
+    password: SecretString,
+    password_check: SecretString,
+}
+
+#[post("/invitations/accept")]
+#[tracing::instrument(
+    name = "Accept an invitation",
+    skip_all,
+    fields(username = %form.username)
+)]
+pub async fn accept_invitation(
+    form: Form<FormData>,
+    pool: Data<PgPool>,
+) -> Result<impl Responder, InvitationError> {
+    let FormData {
+        token,
+        username,
+        password,
+        password_check,
+    } = form.0;
+    let token = InvitationToken::parse(token).map_err(InvitationError::InvalidTokenFormat)?;
+    let retry = |e: String| {
+        FlashMessage::error(e).send();
+        Ok(utils::see_other(&format!(
+            "/invitations/accept?token={}",
+            token.as_ref()
+        )))
+    };
+
+    if username.trim().is_empty() {
+        return retry("Please choose a username.".into());
+    }
+    if password.expose_secret() != password_check.expose_secret() {
+        return retry("You entered two different passwords - the field values must match.".into());
+    }
+    let password = match ValidPassword::parse(password) {
+        Ok(p) => p,
+        Err(e) => return retry(e.to_string()),
+    };
+
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let (email, role) = consume_invitation(txn.as_mut(), &token)
+        .await?
+        .ok_or(InvitationError::UnknownToken)?;
+    let user = NewUser {
+        username,
+        email,
+        role,
+        password,
+    };
+    match auth::create_user(user, txn.as_mut()).await {
+        Ok(_) => (),
+        Err(CreateUserError::UnexpectedError(e)) => return Err(e.into()),
+        Err(e) => return retry(e.to_string()),
+    }
+    txn.commit()
+        .await
+        .context("Failed to commit SQL transaction to create the invited user.")?;
+
+    FlashMessage::info("Your account has been created - you can now log in.").send();
+    Ok(utils::see_other("/login"))
+}
+
+/// Deletes the invitation so that it cannot be used twice, returning who it was for.
+#[tracing::instrument(skip_all)]
+async fn consume_invitation(
+    exec: impl PgExecutor<'_>,
+    token: &InvitationToken,
+) -> anyhow::Result<Option<(String, UserRole)>> {
+    let r = sqlx::query!(
+        r#"
+        DELETE FROM user_invitations
+        WHERE token = $1 AND expires_at > now()
+        RETURNING email, role
+        "#,
+        token.as_ref()
+    )
+    .fetch_optional(exec)
+    .await
+    .context("Failed to consume the invitation.")?;
+    r.map(|r| {
+        Ok((
+            r.email,
+            UserRole::parse(&r.role).map_err(anyhow::Error::msg)?,
+        ))
+    })
+    .transpose()
+}
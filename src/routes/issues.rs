@@ -0,0 +1,66 @@
+use crate::utils;
+use actix_web::{get, http::header, web, HttpResponse, Responder};
+use anyhow::Context;
+use htmlescape::encode_minimal as escape;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+/// Renders a published issue so that it can be read in a browser.
+#[get("/issues/{issue_id}")]
+#[tracing::instrument(name = "Show a newsletter issue", skip(pool))]
+pub async fn web_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> actix_web::Result<impl Responder> {
+    let Some(issue) = get_issue(pool.as_ref(), *issue_id)
+        .await
+        .map_err(utils::e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(header::ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>{title}</title>
+    </head>
+    <body>
+        <h1>{title}</h1>
+        <p>{published_at}</p>
+        {html_content}
+    </body>
+</html>
+"#,
+            title = escape(&issue.title),
+            published_at = issue.published_at.format("%B %-d, %Y"),
+            html_content = issue.html_content,
+        )))
+}
+
+struct WebIssue {
+    title: String,
+    html_content: String,
+    published_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[tracing::instrument(skip(exec))]
+async fn get_issue(exec: impl PgExecutor<'_>, issue_id: Uuid) -> anyhow::Result<Option<WebIssue>> {
+    let issue = sqlx::query_as!(
+        WebIssue,
+        r#"
+        SELECT title, html_content, published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE id = $1 AND status = 'published'
+        "#,
+        issue_id
+    )
+    .fetch_optional(exec)
+    .await
+    .context("Failed to retrieve the newsletter issue.")?;
+    Ok(issue)
+}
@@ -33,6 +33,7 @@ pub async fn login_form(flash_messages: IncomingFlashMessages) -> impl Responder
 
         <button type="submit">Login</button>
     </form>
+    <p><a href="/password-reset">Forgot your password?</a></p>
 </body>
 </html>
         "#
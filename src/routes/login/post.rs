@@ -1,13 +1,16 @@
 use crate::{
-    auth::{self, AuthError, Credentials},
+    app::TrustedProxies,
+    auth::{self, AuthError, Credentials, LoginThrottle, ThrottleKey},
     session_state::Session,
     utils,
 };
-use actix_web::{error::InternalError, http::header, post, web, HttpResponse, Responder};
+use actix_web::{
+    error::InternalError, http::header, post, web, HttpRequest, HttpResponse, Responder,
+};
 use actix_web_flash_messages::FlashMessage;
 use secrecy::SecretString;
 use sqlx::PgPool;
-use std::fmt::Debug;
+use std::{fmt::Debug, time::Duration};
 
 #[derive(serde::Deserialize)]
 pub struct FormData {
@@ -16,11 +19,14 @@ pub struct FormData {
 }
 
 #[post("/login")]
-#[tracing::instrument(skip(form, pool, session), fields(username = tracing::field::Empty, user_id = tracing::field::Empty))]
+#[tracing::instrument(skip(req, form, pool, session, throttle, trusted_proxies), fields(username = tracing::field::Empty, user_id = tracing::field::Empty))]
 pub async fn login(
+    req: HttpRequest,
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
     session: Session,
+    throttle: web::Data<LoginThrottle>,
+    trusted_proxies: web::Data<TrustedProxies>,
 ) -> Result<impl Responder, InternalError<LoginError>> {
     let credentials = Credentials {
         username: form.0.username,
@@ -28,9 +34,46 @@ pub async fn login(
     };
     tracing::Span::current().record("username", tracing::field::display(&credentials.username));
 
+    let client_ip = utils::client_ip(&req, &trusted_proxies.0);
+    let username = credentials.username.clone();
+    let throttle_keys = [
+        ThrottleKey::Username(&username),
+        ThrottleKey::Ip(&client_ip),
+    ];
+    if let Some(lockout) = throttle
+        .lockout(&throttle_keys)
+        .await
+        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?
+    {
+        return Err(login_redirect(LoginError::TooManyAttempts(lockout)));
+    }
+
     match auth::validate_credentials(credentials, pool.as_ref()).await {
         Ok(id) => {
+            throttle
+                .reset(&throttle_keys)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+            let has_second_factor = auth::get_totp_secret(id, pool.as_ref())
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?
+                .is_some();
             session.renew();
+            if has_second_factor {
+                session
+                    .pending_second_factor_user_id()
+                    .insert(id)
+                    .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
+                return Ok(utils::see_other("/login/two-factor"));
+            }
+            let session_epoch = auth::get_session_epoch(id, pool.as_ref())
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?
+                .unwrap_or_default();
+            session
+                .session_epoch()
+                .insert(session_epoch)
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
             session
                 .user_id()
                 .insert(id)
@@ -40,7 +83,14 @@ pub async fn login(
         }
         Err(e) => {
             let e = match e {
-                AuthError::InvalidCredentials(_) => LoginError::AuthError(e.into()),
+                AuthError::InvalidCredentials(_) => match throttle
+                    .record_failure(&throttle_keys)
+                    .await
+                    .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?
+                {
+                    Some(lockout) => LoginError::TooManyAttempts(lockout),
+                    None => LoginError::AuthError(e.into()),
+                },
                 AuthError::UnexpectedError(_) => LoginError::UnexpectedError(e.into()),
             };
             Err(login_redirect(e))
@@ -62,6 +112,8 @@ fn login_redirect(e: LoginError) -> InternalError<LoginError> {
 enum LoginError {
     #[error("Authentication failed")]
     AuthError(#[source] anyhow::Error),
+    #[error("{}", auth::lockout_message(*.0))]
+    TooManyAttempts(Duration),
     #[error("Something went wrong")]
     UnexpectedError(#[from] anyhow::Error),
 }
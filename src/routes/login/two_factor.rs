@@ -0,0 +1,133 @@
+use crate::{
+    app::TrustedProxies,
+    auth::{self, LoginThrottle, ThrottleKey},
+    session_state::Session,
+    utils,
+};
+use actix_web::{get, http::header::ContentType, post, web, HttpRequest, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+#[get("/login/two-factor")]
+pub async fn second_factor_form(
+    flash_messages: IncomingFlashMessages,
+    session: Session,
+) -> actix_web::Result<impl Responder> {
+    if session
+        .pending_second_factor_user_id()
+        .get()
+        .map_err(utils::e500)?
+        .is_none()
+    {
+        return Ok(utils::see_other("/login"));
+    }
+
+    let mut error_html = String::new();
+    for m in flash_messages.iter() {
+        let m = htmlescape::encode_minimal(m.content());
+        writeln!(error_html, "<p><i>{}</i></p>", m).unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Two-factor authentication</title>
+</head>
+<body>
+    {error_html}
+    <form action="/login/two-factor" method="post">
+        <label>Authentication code or recovery code
+                <input type="text" name="code" autocomplete="one-time-code">
+        </label>
+
+        <button type="submit">Verify</button>
+    </form>
+</body>
+</html>
+        "#
+        )))
+}
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    code: String,
+}
+
+#[post("/login/two-factor")]
+#[tracing::instrument(skip(req, form, pool, session, throttle, trusted_proxies), fields(user_id = tracing::field::Empty))]
+pub async fn verify_second_factor(
+    req: HttpRequest,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    session: Session,
+    throttle: web::Data<LoginThrottle>,
+    trusted_proxies: web::Data<TrustedProxies>,
+) -> actix_web::Result<impl Responder> {
+    let Some(user_id) = session
+        .pending_second_factor_user_id()
+        .get()
+        .map_err(utils::e500)?
+    else {
+        return Ok(utils::see_other("/login"));
+    };
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+    let client_ip = utils::client_ip(&req, &trusted_proxies.0);
+    let throttle_keys = [
+        ThrottleKey::SecondFactor(user_id),
+        ThrottleKey::Ip(&client_ip),
+    ];
+    if let Some(lockout) = throttle
+        .lockout(&throttle_keys)
+        .await
+        .map_err(utils::e500)?
+    {
+        FlashMessage::error(auth::lockout_message(lockout)).send();
+        return Ok(utils::see_other("/login/two-factor"));
+    }
+    let Some(secret) = auth::get_totp_secret(user_id, pool.as_ref())
+        .await
+        .map_err(utils::e500)?
+    else {
+        session.logout();
+        return Ok(utils::see_other("/login"));
+    };
+
+    let is_valid = auth::use_totp_code(user_id, &secret, &form.code, pool.as_ref())
+        .await
+        .map_err(utils::e500)?
+        || auth::use_recovery_code(user_id, &form.code, pool.as_ref())
+            .await
+            .map_err(utils::e500)?;
+    if !is_valid {
+        let message = match throttle
+            .record_failure(&throttle_keys)
+            .await
+            .map_err(utils::e500)?
+        {
+            Some(lockout) => auth::lockout_message(lockout),
+            None => "The code is incorrect.".to_string(),
+        };
+        FlashMessage::error(message).send();
+        return Ok(utils::see_other("/login/two-factor"));
+    }
+    throttle.reset(&throttle_keys).await.map_err(utils::e500)?;
+
+    session.renew();
+    session.pending_second_factor_user_id().remove();
+    let session_epoch = auth::get_session_epoch(user_id, pool.as_ref())
+        .await
+        .map_err(utils::e500)?
+        .unwrap_or_default();
+    session
+        .session_epoch()
+        .insert(session_epoch)
+        .map_err(utils::e500)?;
+    session.user_id().insert(user_id).map_err(utils::e500)?;
+    Ok(utils::see_other("/admin/dashboard"))
+}
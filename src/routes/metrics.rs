@@ -0,0 +1,68 @@
+use crate::{
+    app::MetricsToken,
+    metrics::{
+        render, DB_POOL_CONNECTIONS, DB_POOL_MAX_CONNECTIONS, DELIVERY_QUEUE_DEPTH,
+        DELIVERY_QUEUE_OLDEST_TASK_AGE,
+    },
+    utils,
+};
+use actix_web::{get, http::header, web::Data, HttpRequest, HttpResponse};
+use anyhow::Context;
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+/// Serves the metrics in the Prometheus text format to scrapers presenting the configured
+/// `Authorization: Bearer` token.
+#[get("/metrics")]
+#[tracing::instrument(name = "Scrape metrics", skip_all)]
+pub async fn prometheus_metrics(
+    req: HttpRequest,
+    token: Data<MetricsToken>,
+    pool: Data<PgPool>,
+) -> actix_web::Result<HttpResponse> {
+    let presented = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    // Comparing digests keeps the time taken from revealing how much of the token matched
+    if Sha256::digest(presented.trim()) != Sha256::digest(token.0.expose_secret()) {
+        return Ok(HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+            .finish());
+    }
+
+    sample_gauges(&pool).await.map_err(utils::e500)?;
+    let body = render().map_err(utils::e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(prometheus::TEXT_FORMAT)
+        .body(body))
+}
+
+#[tracing::instrument(skip_all)]
+async fn sample_gauges(pool: &PgPool) -> anyhow::Result<()> {
+    let queue = sqlx::query!(
+        r#"
+    SELECT
+        COUNT(*) AS "depth!",
+        EXTRACT(EPOCH FROM now() - MIN(created_at))::bigint AS oldest_task_age
+    FROM issue_delivery_queue
+    "#
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to inspect the delivery queue.")?;
+    DELIVERY_QUEUE_DEPTH.set(queue.depth);
+    DELIVERY_QUEUE_OLDEST_TASK_AGE.set(queue.oldest_task_age.unwrap_or_default());
+
+    let size = i64::from(pool.size());
+    let idle = pool.num_idle() as i64;
+    DB_POOL_CONNECTIONS.with_label_values(&["idle"]).set(idle);
+    DB_POOL_CONNECTIONS
+        .with_label_values(&["in_use"])
+        .set(size - idle);
+    DB_POOL_MAX_CONNECTIONS.set(i64::from(pool.options().get_max_connections()));
+    Ok(())
+}
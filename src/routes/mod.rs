@@ -1,15 +1,33 @@
 #![allow(hidden_glob_reexports)]
 #![allow(clippy::async_yields_async)]
 mod admin;
+mod api;
 mod health_check;
 mod home;
+mod invitations;
+mod issues;
 mod login;
+mod metrics;
+mod password_reset;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_preferences;
+mod subscriptions_unsubscribe;
+mod tracking;
+mod webhooks;
 
 pub use admin::*;
+pub use api::*;
 pub use health_check::*;
 pub use home::*;
+pub use invitations::*;
+pub use issues::*;
 pub use login::*;
+pub use metrics::*;
+pub use password_reset::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_preferences::*;
+pub use subscriptions_unsubscribe::*;
+pub use tracking::*;
+pub use webhooks::*;
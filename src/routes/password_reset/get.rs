@@ -0,0 +1,100 @@
+use super::{get_reset_user_id, PasswordResetError};
+use crate::domain::PasswordResetToken;
+use actix_web::{
+    get,
+    http::header::ContentType,
+    web::{Data, Query},
+    HttpResponse, Responder,
+};
+use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::encode_minimal as escape;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+#[get("/password-reset")]
+pub async fn password_reset_form(flash_messages: IncomingFlashMessages) -> impl Responder {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", escape(m.content())).unwrap();
+    }
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Forgot your password?</title>
+    </head>
+    <body>
+        {msg_html}
+        <p>Enter the email address of your account and we will send you a link to reset your password.</p>
+        <form action="/password-reset" method="post">
+            <label>Email
+                <input type="email" name="email" placeholder="Enter Email">
+            </label>
+            <button type="submit">Send reset link</button>
+        </form>
+        <p><a href="/login">&lt;- Back to login</a></p>
+    </body>
+</html>
+"#,
+        ))
+}
+
+#[derive(Deserialize)]
+struct Parameters {
+    token: String,
+}
+
+#[get("/password-reset/confirm")]
+#[tracing::instrument(name = "Show the new password form", skip_all)]
+pub async fn new_password_form(
+    parameters: Query<Parameters>,
+    flash_messages: IncomingFlashMessages,
+    pool: Data<PgPool>,
+) -> Result<impl Responder, PasswordResetError> {
+    let token = PasswordResetToken::parse(parameters.0.token)
+        .map_err(PasswordResetError::InvalidTokenFormat)?;
+    get_reset_user_id(pool.as_ref(), &token)
+        .await?
+        .ok_or(PasswordResetError::UnknownToken)?;
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", escape(m.content())).unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Reset your password</title>
+    </head>
+    <body>
+        {msg_html}
+        <form action="/password-reset/confirm" method="post">
+            <input hidden type="text" name="token" value="{token}">
+            <label>New password
+                <input type="password" name="new_password" placeholder="Enter new password">
+            </label>
+            <br>
+            <label>Confirm new password
+                <input type="password" name="new_password_check" placeholder="Type the new password again">
+            </label>
+            <br>
+            <button type="submit">Reset password</button>
+        </form>
+    </body>
+</html>
+"#,
+            token = token.as_ref(),
+        )))
+}
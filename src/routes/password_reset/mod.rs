@@ -0,0 +1,58 @@
+mod get;
+mod post;
+
+pub use get::{new_password_form, password_reset_form};
+pub use post::{request_password_reset, reset_password};
+
+use crate::{domain::PasswordResetToken, utils};
+use actix_web::http::StatusCode;
+use anyhow::Context;
+use sqlx::PgExecutor;
+use std::fmt::Debug;
+use uuid::Uuid;
+
+/// Returns the user the token lets reset the password of, unless it has expired or already been used.
+#[tracing::instrument(skip_all)]
+async fn get_reset_user_id(
+    exec: impl PgExecutor<'_>,
+    token: &PasswordResetToken,
+) -> anyhow::Result<Option<Uuid>> {
+    let r = sqlx::query!(
+        r#"
+        SELECT user_id
+        FROM password_reset_tokens
+        WHERE token = $1 AND expires_at > now()
+        "#,
+        token.as_ref()
+    )
+    .fetch_optional(exec)
+    .await
+    .context("Failed to retrieve the password reset token.")?;
+    Ok(r.map(|r| r.user_id))
+}
+
+#[derive(thiserror::Error)]
+pub enum PasswordResetError {
+    #[error("{0}")]
+    InvalidTokenFormat(String),
+    #[error("The password reset link is unknown, has expired or has already been used.")]
+    UnknownToken,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for PasswordResetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        utils::error_chain_fmt(self, f)
+    }
+}
+
+impl actix_web::ResponseError for PasswordResetError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::InvalidTokenFormat(_) => StatusCode::BAD_REQUEST,
+            Self::UnknownToken => StatusCode::UNAUTHORIZED,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
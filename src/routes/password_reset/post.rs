@@ -0,0 +1,250 @@
+use super::PasswordResetError;
+use crate::{
+    app::{AppBaseUrl, TrustedProxies},
+    auth::{self, LoginThrottle, ThrottleKey},
+    domain::{PasswordResetToken, SubscriberEmail, ValidPassword},
+    email_client::EmailTransport,
+    utils,
+};
+use actix_web::{
+    post,
+    web::{Data, Form},
+    HttpRequest, Responder,
+};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use sqlx::{PgExecutor, PgPool};
+use std::sync::Arc;
+use tracing::Instrument;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+struct RequestFormData {
+    email: String,
+}
+
+#[post("/password-reset")]
+#[tracing::instrument(name = "Request a password reset", skip_all, fields(email = %form.email))]
+pub async fn request_password_reset(
+    req: HttpRequest,
+    form: Form<RequestFormData>,
+    pool: Data<PgPool>,
+    email_client: Data<dyn EmailTransport>,
+    base_url: Data<AppBaseUrl>,
+    throttle: Data<LoginThrottle>,
+    trusted_proxies: Data<TrustedProxies>,
+) -> actix_web::Result<impl Responder> {
+    let email = match SubscriberEmail::parse(form.0.email) {
+        Ok(e) => e,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/password-reset"));
+        }
+    };
+
+    // Every request may send an email, so they are all counted
+    let client_ip = utils::client_ip(&req, &trusted_proxies.0);
+    let throttle_keys = [
+        ThrottleKey::PasswordReset(email.as_ref()),
+        ThrottleKey::PasswordResetIp(&client_ip),
+    ];
+    let lockout = match throttle
+        .lockout(&throttle_keys)
+        .await
+        .map_err(utils::e500)?
+    {
+        Some(lockout) => Some(lockout),
+        None => throttle
+            .record_failure(&throttle_keys)
+            .await
+            .map_err(utils::e500)?,
+    };
+    if let Some(lockout) = lockout {
+        FlashMessage::error(format!(
+            "Too many password reset requests - please try again in {} seconds.",
+            auth::lockout_secs(lockout)
+        ))
+        .send();
+        return Ok(utils::see_other("/password-reset"));
+    }
+
+    // Reply the same way whether or not the address is registered,
+    // so that the form cannot be used to find out who has an account
+    if let Some(user_id) = get_user_id_by_email(pool.as_ref(), &email)
+        .await
+        .map_err(utils::e500)?
+    {
+        let token = PasswordResetToken::generate();
+        store_reset_token(pool.as_ref(), &token, user_id)
+            .await
+            .map_err(utils::e500)?;
+        // Sent in the background, lest the time it takes or its failure give the account away
+        let email_client = email_client.into_inner();
+        let base_url = base_url.0.clone();
+        let email = email.clone();
+        tokio::spawn(
+            async move {
+                if let Err(e) = send_reset_email(email_client, &email, &base_url, &token).await {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to send the password reset email."
+                    );
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    FlashMessage::info(format!(
+        "If {} belongs to an account, we have sent it a link to reset the password.",
+        email
+    ))
+    .send();
+    Ok(utils::see_other("/login"))
+}
+
+#[tracing::instrument(skip(exec))]
+async fn get_user_id_by_email(
+    exec: impl PgExecutor<'_>,
+    email: &SubscriberEmail,
+) -> anyhow::Result<Option<Uuid>> {
+    let r = sqlx::query!("SELECT id FROM users WHERE email = $1", email.as_ref())
+        .fetch_optional(exec)
+        .await
+        .context("Failed to look up the user by email.")?;
+    Ok(r.map(|r| r.id))
+}
+
+#[tracing::instrument(skip(exec, token))]
+async fn store_reset_token(
+    exec: impl PgExecutor<'_>,
+    token: &PasswordResetToken,
+    user_id: Uuid,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO password_reset_tokens (token, user_id, created_at, expires_at)
+        VALUES ($1, $2, now(), now() + interval '1 hour')
+        "#,
+        token.as_ref(),
+        user_id
+    )
+    .execute(exec)
+    .await
+    .context("Failed to store the password reset token.")?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(ec, base_url, token))]
+async fn send_reset_email(
+    ec: Arc<dyn EmailTransport>,
+    email: &SubscriberEmail,
+    base_url: &str,
+    token: &PasswordResetToken,
+) -> anyhow::Result<()> {
+    let reset_link = format!(
+        "{}/password-reset/confirm?token={}",
+        base_url,
+        token.as_ref()
+    );
+
+    let html_body = format!(
+        "Somebody asked to reset the password of your account.<br />\
+                Click <a href=\"{}\">here</a> to choose a new one. \
+                The link expires in 1 hour. If it was not you, you can ignore this email.",
+        reset_link
+    );
+
+    let text_body = format!(
+        "Somebody asked to reset the password of your account.\n\
+                Visit {} to choose a new one. \
+                The link expires in 1 hour. If it was not you, you can ignore this email.",
+        reset_link
+    );
+
+    ec.send_email(email, "Reset your password", &html_body, &text_body, &[])
+        .await
+}
+
+#[derive(Deserialize)]
+struct ResetFormData {
+    token: String,
+    new_password: SecretString,
+    new_password_check: SecretString,
+}
+
+#[post("/password-reset/confirm")]
+#[tracing::instrument(name = "Reset a password", skip_all, fields(user_id = tracing::field::Empty))]
+pub async fn reset_password(
+    form: Form<ResetFormData>,
+    pool: Data<PgPool>,
+) -> Result<impl Responder, PasswordResetError> {
+    let ResetFormData {
+        token,
+        new_password,
+        new_password_check,
+    } = form.0;
+    let token = PasswordResetToken::parse(token).map_err(PasswordResetError::InvalidTokenFormat)?;
+    let retry = |e: String| {
+        FlashMessage::error(e).send();
+        Ok(utils::see_other(&format!(
+            "/password-reset/confirm?token={}",
+            token.as_ref()
+        )))
+    };
+
+    if new_password.expose_secret() != new_password_check.expose_secret() {
+        return retry(
+            "You entered two different new passwords - the field values must match.".into(),
+        );
+    }
+    let new_password = match ValidPassword::parse(new_password) {
+        Ok(p) => p,
+        Err(e) => return retry(e.to_string()),
+    };
+
+    let mut txn = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let user_id = consume_reset_token(txn.as_mut(), &token)
+        .await?
+        .ok_or(PasswordResetError::UnknownToken)?;
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+    auth::change_password(user_id, new_password, txn.as_mut()).await?;
+    auth::invalidate_sessions(user_id, txn.as_mut()).await?;
+    txn.commit()
+        .await
+        .context("Failed to commit SQL transaction to reset the password.")?;
+
+    FlashMessage::info("Your password has been reset - you can now log in.").send();
+    Ok(utils::see_other("/login"))
+}
+
+/// Deletes all the reset tokens of the user the token belongs to, so that none can be used
+/// once the password has been reset, returning who it belongs to.
+#[tracing::instrument(skip_all)]
+async fn consume_reset_token(
+    exec: impl PgExecutor<'_>,
+    token: &PasswordResetToken,
+) -> anyhow::Result<Option<Uuid>> {
+    let r = sqlx::query!(
+        r#"
+        DELETE FROM password_reset_tokens
+        WHERE user_id = (
+            SELECT user_id
+            FROM password_reset_tokens
+            WHERE token = $1 AND expires_at > now()
+        )
+        RETURNING user_id
+        "#,
+        token.as_ref()
+    )
+    .fetch_all(exec)
+    .await
+    .context("Failed to consume the password reset token.")?;
+    Ok(r.first().map(|r| r.user_id))
+}
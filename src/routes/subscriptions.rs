@@ -1,15 +1,11 @@
 use crate::{
-    app::AppBaseUrl,
     domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionToken},
-    email_client::EmailClient,
-    utils,
-};
-use actix_web::{
-    http::StatusCode,
-    post,
-    web::{Data, Form},
-    HttpResponse, Responder, ResponseError,
+    routes::admin::add_subscriber_to_lists,
+    suppression::is_suppressed,
+    utils::{self, HtmlForm},
+    workers::confirmation_email::enqueue_confirmation_email,
 };
+use actix_web::{http::StatusCode, post, web::Data, HttpResponse, Responder, ResponseError};
 use anyhow::Context;
 use chrono::Utc;
 use serde::Deserialize;
@@ -20,65 +16,86 @@ use std::{
 };
 use uuid::Uuid;
 
+/// Every value `subscriptions.status` can take.
+pub const SUBSCRIPTION_STATUSES: [&str; 5] = [
+    "pending_confirmation",
+    "confirmed",
+    "unsubscribed",
+    "bounced",
+    "complained",
+];
+
 #[derive(Deserialize)]
 pub struct SubscriptionForm {
     name: String,
     email: String,
+    /// The ids of the lists to opt into, unknown ones are ignored.
+    #[serde(default)]
+    lists: Vec<Uuid>,
 }
 
 #[post("/subscriptions")]
 #[tracing::instrument(
     name ="Adding a new subscriber",
-    skip(form, db_pool, email_client, base_url),
+    skip(form, db_pool),
     fields(
         subscriber_email = %form.email,
         subscriber_name = %form.name,
     )
 )]
 pub async fn subscribe(
-    form: Form<SubscriptionForm>,
+    form: HtmlForm<SubscriptionForm>,
     db_pool: Data<PgPool>,
-    email_client: Data<EmailClient>,
-    base_url: Data<AppBaseUrl>,
 ) -> Result<impl Responder, SubscribeError> {
-    let ns = form.0.try_into().map_err(SubscribeError::ValidationError)?;
+    let lists = form.0.lists.clone();
+    let ns: NewSubscriber = form.0.try_into().map_err(SubscribeError::ValidationError)?;
     let mut txn = db_pool
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool")?;
+    // Answer as usual so as not to disclose the address, but do not email it again
+    if is_suppressed(ns.email.as_ref(), txn.as_mut())
+        .await
+        .context("Failed to check the suppression list.")?
+    {
+        tracing::info!("Ignoring the subscription of a suppressed address.");
+        return Ok(HttpResponse::Ok());
+    }
     let subscriber_id = insert_subscriber(&ns, txn.as_mut())
         .await
         .context("Failed to insert new subscriber in the database.")?;
+    add_subscriber_to_lists(txn.as_mut(), subscriber_id, &lists).await?;
     let token = SubscriptionToken::generate();
     store_token(txn.as_mut(), subscriber_id, &token)
         .await
         .context("Failed to store the confirmation token for a new subscriber.")?;
-    send_confirmation_email(&email_client, &ns, &base_url.0, &token)
+    enqueue_confirmation_email(subscriber_id, txn.as_mut())
         .await
-        .context("Failed to send a confirmation email.")?;
+        .context("Failed to queue a confirmation email.")?;
     txn.commit()
         .await
         .context("Failed to commit SQL transaction to store a new subscriber.")?;
     Ok(HttpResponse::Ok())
 }
 
-/// Stores the given token. If the user already has a token assigned, overwrites it.
+/// Stores the given token, valid for 2 days. If the user already has a token assigned,
+/// overwrites it.
 #[tracing::instrument(
     name = "Storing the subscription token for the new subscriber in the database",
     skip(executor, token)
 )]
-async fn store_token(
+pub(crate) async fn store_token(
     executor: impl '_ + PgExecutor<'_>,
     subscriber_id: Uuid,
     token: &SubscriptionToken,
 ) -> Result<(), StoreTokenError> {
     sqlx::query!(
         r#"
-        INSERT INTO subscription_tokens (subscriber_id, token)
-        VALUES ($1, $2)
+        INSERT INTO subscription_tokens (subscriber_id, token, created_at, expires_at)
+        VALUES ($1, $2, now(), now() + interval '2 days')
         ON CONFLICT (subscriber_id)
         DO UPDATE
-        SET token = $2
+        SET token = $2, created_at = now(), expires_at = now() + interval '2 days'
         "#,
         subscriber_id,
         token.as_ref()
@@ -88,37 +105,6 @@ async fn store_token(
     Ok(())
 }
 
-#[tracing::instrument(
-    name = "Sending a confirmation email to a new subscriber",
-    skip(ec, ns, base_url, token)
-)]
-async fn send_confirmation_email(
-    ec: &EmailClient,
-    ns: &NewSubscriber,
-    base_url: &str,
-    token: &SubscriptionToken,
-) -> Result<(), reqwest::Error> {
-    let confirmation_link = format!(
-        "{}/subscriptions/confirm?token={}",
-        base_url,
-        token.as_ref()
-    );
-
-    let html_body = format!(
-        "Welcome to our newsletter!<br />\
-                Click <a href=\"{}\">here</a> to confirm your subscription.",
-        confirmation_link
-    );
-
-    let text_body = format!(
-        "Welcome to our newsletter!\nVisit {} to confirm your subscription.",
-        confirmation_link
-    );
-
-    ec.send_email(&ns.email, "Welcome!", &html_body, &text_body)
-        .await
-}
-
 impl TryFrom<SubscriptionForm> for NewSubscriber {
     type Error = String;
 
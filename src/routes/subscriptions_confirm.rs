@@ -1,11 +1,17 @@
-use crate::{domain::SubscriptionToken, utils};
+use crate::{
+    domain::SubscriptionToken, routes::subscriptions::store_token, utils,
+    workers::confirmation_email::enqueue_confirmation_email,
+};
 use actix_web::{
     get,
-    http::StatusCode,
-    web::{Data, Query},
+    http::{header::ContentType, StatusCode},
+    post,
+    web::{Data, Form, Query},
     HttpResponse, Responder,
 };
 use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal as escape;
 use serde::Deserialize;
 use sqlx::{PgExecutor, PgPool};
 use std::fmt::Debug;
@@ -28,6 +34,13 @@ pub async fn confirm(
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool")?;
+    let expires_at = get_token_expiry(txn.as_mut(), &token)
+        .await
+        .context("Failed to look up the confirmation token.")?
+        .ok_or(ConfirmSubscriberError::UnknownToken)?;
+    if expires_at <= Utc::now() {
+        return Ok(link_expired_page(&token));
+    }
     let id = consume_subscriber_id_from_token(txn.as_mut(), &token)
         .await
         .context("Failed to attempt token consumption for the specified user.")?
@@ -39,7 +52,121 @@ pub async fn confirm(
         .await
         .context("Failed to commit SQL transaction to store a new subscriber.")?;
 
-    Ok(HttpResponse::Ok())
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[derive(Deserialize)]
+struct ResendForm {
+    token: String,
+}
+
+/// Sends a fresh confirmation link to the subscriber owning `token`, expired or not.
+#[post("/subscriptions/confirm/resend")]
+#[tracing::instrument(name = "Resending a confirmation email", skip(form, db_pool))]
+pub async fn resend_confirmation(
+    form: Form<ResendForm>,
+    db_pool: Data<PgPool>,
+) -> Result<impl Responder, ConfirmSubscriberError> {
+    let token = SubscriptionToken::parse(form.0.token)
+        .map_err(ConfirmSubscriberError::InvalidTokenFormat)?;
+    let mut txn = db_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let subscriber = sqlx::query!(
+        r#"
+        SELECT s.id, s.status
+        FROM subscription_tokens t
+        JOIN subscriptions s ON s.id = t.subscriber_id
+        WHERE t.token = $1
+        "#,
+        token.as_ref()
+    )
+    .fetch_optional(txn.as_mut())
+    .await
+    .context("Failed to look up the subscriber for the confirmation token.")?
+    .ok_or(ConfirmSubscriberError::UnknownToken)?;
+
+    if subscriber.status != "pending_confirmation" {
+        return Ok(confirmation_page(
+            "Your subscription no longer needs to be confirmed.",
+        ));
+    }
+
+    let new_token = SubscriptionToken::generate();
+    store_token(txn.as_mut(), subscriber.id, &new_token)
+        .await
+        .context("Failed to store a new confirmation token.")?;
+    enqueue_confirmation_email(subscriber.id, txn.as_mut())
+        .await
+        .context("Failed to queue a confirmation email.")?;
+    txn.commit()
+        .await
+        .context("Failed to commit SQL transaction to store a new confirmation token.")?;
+
+    Ok(confirmation_page(
+        "We have sent you a new confirmation link - please check your inbox.",
+    ))
+}
+
+fn link_expired_page(token: &SubscriptionToken) -> HttpResponse {
+    HttpResponse::Gone()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Link expired</title>
+    </head>
+    <body>
+        <p>This confirmation link has expired.</p>
+        <form action="/subscriptions/confirm/resend" method="post">
+            <input hidden type="text" name="token" value="{}">
+            <button type="submit">Send me a new link</button>
+        </form>
+    </body>
+</html>
+"#,
+            escape(token.as_ref())
+        ))
+}
+
+fn confirmation_page(message: &str) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Confirm your subscription</title>
+    </head>
+    <body>
+        <p>{}</p>
+    </body>
+</html>
+"#,
+            escape(message)
+        ))
+}
+
+#[tracing::instrument(name = "Get the expiry of a confirmation token", skip_all)]
+async fn get_token_expiry(
+    executor: impl '_ + PgExecutor<'_>,
+    token: &SubscriptionToken,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let expires_at = sqlx::query!(
+        "SELECT expires_at FROM subscription_tokens WHERE token = $1 FOR UPDATE",
+        token.as_ref()
+    )
+    .fetch_optional(executor)
+    .await?
+    .map(|r| r.expires_at);
+
+    Ok(expires_at)
 }
 
 #[tracing::instrument(name = "Mark subscriber as confirmed", skip(subscriber_id, executor))]
@@ -48,7 +175,11 @@ async fn confirm_subscriber(
     subscriber_id: Uuid,
 ) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        "UPDATE subscriptions SET status = 'confirmed' WHERE id = $1",
+        r#"
+        UPDATE subscriptions
+        SET status = 'confirmed', unsubscribed_at = NULL
+        WHERE id = $1
+        "#,
         subscriber_id
     )
     .execute(executor)
@@ -0,0 +1,139 @@
+//! The preference center, reached through the same signed link as the unsubscribe form.
+use super::subscriptions_unsubscribe::{Parameters, UnsubscribeError};
+use crate::{
+    app::HmacSecret,
+    routes::admin::{
+        add_subscriber_to_lists, get_lists, list_checkboxes, remove_subscriber_from_other_lists,
+    },
+    utils::{self, HtmlForm},
+};
+use actix_web::{
+    get,
+    http::header::ContentType,
+    post,
+    web::{Data, Query},
+    HttpResponse, Responder,
+};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use htmlescape::encode_minimal as escape;
+use serde::Deserialize;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Write;
+use uuid::Uuid;
+
+#[get("/subscriptions/preferences")]
+#[tracing::instrument(name = "Show the subscription preferences", skip_all)]
+pub async fn preferences_form(
+    parameters: Query<Parameters>,
+    hmac_secret: Data<HmacSecret>,
+    db_pool: Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<impl Responder, UnsubscribeError> {
+    let subscriber_id = parameters.verify(&hmac_secret)?;
+    let token = &parameters.token;
+    let Some(email) = get_subscriber_email(db_pool.as_ref(), subscriber_id)
+        .await
+        .context("Failed to retrieve the subscriber.")?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let all = get_lists(db_pool.as_ref()).await?;
+    let checked = get_subscriber_lists(db_pool.as_ref(), subscriber_id)
+        .await
+        .context("Failed to retrieve the lists of the subscriber.")?;
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", escape(m.content())).unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Your subscription</title>
+    </head>
+    <body>
+        {msg_html}
+        <p>Choose what we send to {email}:</p>
+        <form action="/subscriptions/preferences?subscriber_id={subscriber_id}&amp;token={token}" method="post">
+            {lists_html}
+            <button type="submit">Save</button>
+        </form>
+        <p><a href="/subscriptions/unsubscribe?subscriber_id={subscriber_id}&amp;token={token}">Unsubscribe from everything</a></p>
+    </body>
+</html>
+"#,
+            email = escape(&email),
+            lists_html = list_checkboxes(&all, &checked),
+        )))
+}
+
+#[derive(Deserialize)]
+struct PreferencesForm {
+    #[serde(default)]
+    lists: Vec<Uuid>,
+}
+
+/// Replaces the lists of the subscriber with the ones ticked in the form.
+#[post("/subscriptions/preferences")]
+#[tracing::instrument(
+    name = "Save the subscription preferences",
+    skip_all,
+    fields(subscriber_id = %parameters.subscriber_id)
+)]
+pub async fn save_preferences(
+    parameters: Query<Parameters>,
+    form: HtmlForm<PreferencesForm>,
+    hmac_secret: Data<HmacSecret>,
+    db_pool: Data<PgPool>,
+) -> Result<impl Responder, UnsubscribeError> {
+    let subscriber_id = parameters.verify(&hmac_secret)?;
+    let mut txn = db_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    remove_subscriber_from_other_lists(txn.as_mut(), subscriber_id, &form.lists).await?;
+    add_subscriber_to_lists(txn.as_mut(), subscriber_id, &form.lists).await?;
+    txn.commit()
+        .await
+        .context("Failed to commit SQL transaction to save the preferences.")?;
+
+    FlashMessage::info("Your preferences have been saved.").send();
+    Ok(utils::see_other(&format!(
+        "/subscriptions/preferences?subscriber_id={}&token={}",
+        subscriber_id, parameters.token
+    )))
+}
+
+#[tracing::instrument(skip(exec))]
+async fn get_subscriber_email(
+    exec: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let email = sqlx::query_scalar!(
+        "SELECT email FROM subscriptions WHERE id = $1",
+        subscriber_id
+    )
+    .fetch_optional(exec)
+    .await?;
+    Ok(email)
+}
+
+#[tracing::instrument(skip(exec))]
+async fn get_subscriber_lists(
+    exec: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT list_id FROM subscription_lists WHERE subscriber_id = $1",
+        subscriber_id
+    )
+    .fetch_all(exec)
+    .await
+}
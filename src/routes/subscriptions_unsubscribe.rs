@@ -0,0 +1,141 @@
+use crate::{app::HmacSecret, domain::UnsubscribeToken, utils};
+use actix_web::{
+    get,
+    http::{header::ContentType, StatusCode},
+    post,
+    web::{Data, Query},
+    HttpResponse, Responder,
+};
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Debug;
+use uuid::Uuid;
+
+/// Identifies a subscriber through the signed link sent with every issue.
+#[derive(Deserialize)]
+pub(super) struct Parameters {
+    pub(super) subscriber_id: Uuid,
+    pub(super) token: String,
+}
+
+impl Parameters {
+    /// Returns the subscriber id if the token has been signed for it.
+    pub(super) fn verify(&self, secret: &HmacSecret) -> Result<Uuid, UnsubscribeError> {
+        let token = UnsubscribeToken::parse(self.token.clone())
+            .map_err(UnsubscribeError::InvalidTokenFormat)?;
+        if !token.verify(self.subscriber_id, &secret.0) {
+            return Err(UnsubscribeError::InvalidSignature);
+        }
+        Ok(self.subscriber_id)
+    }
+}
+
+#[get("/subscriptions/unsubscribe")]
+#[tracing::instrument(name = "Show the unsubscribe form", skip_all)]
+pub async fn unsubscribe_form(
+    parameters: Query<Parameters>,
+    hmac_secret: Data<HmacSecret>,
+) -> Result<impl Responder, UnsubscribeError> {
+    let subscriber_id = parameters.verify(&hmac_secret)?;
+    let token = &parameters.token;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Unsubscribe</title>
+    </head>
+    <body>
+        <p>Do you want to stop receiving our newsletter?</p>
+        <p>You can also <a href="/subscriptions/preferences?subscriber_id={subscriber_id}&amp;token={token}">choose the lists you receive</a> instead.</p>
+        <form action="/subscriptions/unsubscribe?subscriber_id={subscriber_id}&amp;token={token}" method="post">
+            <button type="submit">Unsubscribe</button>
+        </form>
+    </body>
+</html>
+"#
+        )))
+}
+
+/// Handles both the form above and RFC 8058 one-click requests sent by mail clients.
+#[post("/subscriptions/unsubscribe")]
+#[tracing::instrument(
+    name = "Unsubscribing a subscriber",
+    skip_all,
+    fields(subscriber_id = %parameters.subscriber_id)
+)]
+pub async fn unsubscribe(
+    parameters: Query<Parameters>,
+    hmac_secret: Data<HmacSecret>,
+    db_pool: Data<PgPool>,
+) -> Result<impl Responder, UnsubscribeError> {
+    let subscriber_id = parameters.verify(&hmac_secret)?;
+    mark_subscriber_as_unsubscribed(db_pool.as_ref(), subscriber_id)
+        .await
+        .context("Failed to unsubscribe the subscriber.")?;
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"
+<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8">
+        <title>Unsubscribed</title>
+    </head>
+    <body>
+        <p>You have been unsubscribed. You will not receive any further issues.</p>
+    </body>
+</html>
+"#,
+    ))
+}
+
+#[tracing::instrument(name = "Mark subscriber as unsubscribed", skip(executor))]
+async fn mark_subscriber_as_unsubscribed(
+    executor: impl '_ + PgExecutor<'_>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = 'unsubscribed', unsubscribed_at = now()
+        WHERE id = $1 AND status <> 'unsubscribed'
+        "#,
+        subscriber_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+#[derive(thiserror::Error)]
+pub enum UnsubscribeError {
+    #[error("{0}")]
+    InvalidTokenFormat(String),
+    #[error("The unsubscribe token has not been issued for this subscriber.")]
+    InvalidSignature,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for UnsubscribeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        utils::error_chain_fmt(self, f)
+    }
+}
+
+impl actix_web::ResponseError for UnsubscribeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::InvalidTokenFormat(_) => StatusCode::BAD_REQUEST,
+            Self::InvalidSignature => StatusCode::UNAUTHORIZED,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
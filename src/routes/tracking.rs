@@ -0,0 +1,125 @@
+use crate::{app::HmacSecret, domain::DeliveryId, utils};
+use actix_web::{
+    get,
+    http::{
+        header::{self, CacheControl, CacheDirective},
+        StatusCode,
+    },
+    web::{Data, Query},
+    HttpResponse, Responder,
+};
+use serde::Deserialize;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Debug;
+
+/// A transparent 1x1 GIF.
+const PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+#[derive(Deserialize)]
+struct OpenParameters {
+    d: String,
+}
+
+/// Serves the pixel embedded in tracked issues, recording that the issue has been opened.
+#[get("/track/open")]
+#[tracing::instrument(name = "Track an open", skip_all)]
+pub async fn track_open(
+    parameters: Query<OpenParameters>,
+    hmac_secret: Data<HmacSecret>,
+    db_pool: Data<PgPool>,
+) -> Result<impl Responder, TrackingError> {
+    let delivery = DeliveryId::verify(&parameters.d, &hmac_secret.0)
+        .map_err(TrackingError::InvalidDeliveryId)?;
+    record_event(db_pool.as_ref(), &delivery, "open", None).await;
+
+    Ok(HttpResponse::Ok()
+        .content_type("image/gif")
+        .insert_header(CacheControl(vec![
+            CacheDirective::NoCache,
+            CacheDirective::NoStore,
+            CacheDirective::MustRevalidate,
+        ]))
+        .body(PIXEL))
+}
+
+#[derive(Deserialize)]
+struct ClickParameters {
+    d: String,
+    url: String,
+    sig: String,
+}
+
+/// Records a click on a link of a tracked issue before redirecting to it.
+#[get("/track/click")]
+#[tracing::instrument(name = "Track a click", skip_all, fields(url = %parameters.url))]
+pub async fn track_click(
+    parameters: Query<ClickParameters>,
+    hmac_secret: Data<HmacSecret>,
+    db_pool: Data<PgPool>,
+) -> Result<impl Responder, TrackingError> {
+    let delivery = DeliveryId::verify(&parameters.d, &hmac_secret.0)
+        .map_err(TrackingError::InvalidDeliveryId)?;
+    if !delivery.verify_link(&parameters.url, &parameters.sig, &hmac_secret.0) {
+        return Err(TrackingError::InvalidLinkSignature);
+    }
+    record_event(db_pool.as_ref(), &delivery, "click", Some(&parameters.url)).await;
+
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, parameters.url.as_str()))
+        .finish())
+}
+
+/// Failing to record an event must not get in the way of the reader, so errors are only logged.
+async fn record_event(
+    exec: impl PgExecutor<'_>,
+    delivery: &DeliveryId,
+    kind: &str,
+    url: Option<&str>,
+) {
+    let r = sqlx::query!(
+        r#"
+    INSERT INTO tracking_events (newsletter_issue_id, subscriber_id, kind, url, occurred_at)
+    VALUES ($1, $2, $3, $4, now())
+    ON CONFLICT DO NOTHING
+    "#,
+        delivery.issue_id,
+        delivery.subscriber_id,
+        kind,
+        url
+    )
+    .execute(exec)
+    .await;
+    if let Err(e) = r {
+        tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            newsletter_issue_id = %delivery.issue_id,
+            subscriber_id = %delivery.subscriber_id,
+            "Failed to record a tracking event."
+        );
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum TrackingError {
+    #[error("{0}")]
+    InvalidDeliveryId(String),
+    #[error("The link has not been signed for this delivery.")]
+    InvalidLinkSignature,
+}
+
+impl Debug for TrackingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        utils::error_chain_fmt(self, f)
+    }
+}
+
+impl actix_web::ResponseError for TrackingError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{
+    app::WebhookCredentials,
+    suppression::{suppress_email, SuppressionReason},
+    utils,
+};
+use actix_web::{
+    http::{header, StatusCode},
+    post,
+    web::{Bytes, Data},
+    HttpRequest, HttpResponse, ResponseError,
+};
+use anyhow::Context;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Debug;
+
+/// The bounce types telling that the address will never accept our emails.
+const HARD_BOUNCE_TYPES: [&str; 2] = ["HardBounce", "BadEmailAddress"];
+
+/// The part of Postmark's webhook payloads we act upon.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(tag = "RecordType")]
+enum PostmarkEvent {
+    Bounce {
+        #[serde(rename = "Type")]
+        kind: String,
+        #[serde(rename = "Email")]
+        email: String,
+    },
+    SpamComplaint {
+        #[serde(rename = "Email")]
+        email: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl PostmarkEvent {
+    /// The address to suppress, if any.
+    fn suppression(&self) -> Option<(&str, SuppressionReason)> {
+        match self {
+            Self::Bounce { kind, email } if HARD_BOUNCE_TYPES.contains(&kind.as_str()) => {
+                Some((email, SuppressionReason::Bounced))
+            }
+            Self::SpamComplaint { email } => Some((email, SuppressionReason::Complained)),
+            _ => None,
+        }
+    }
+}
+
+/// Receives bounce and spam complaint notifications, authenticated with HTTP basic credentials.
+///
+/// Other notifications are acknowledged and ignored, so that Postmark does not retry them.
+#[post("/webhooks/postmark")]
+#[tracing::instrument(name = "Handle a Postmark webhook", skip_all)]
+pub async fn postmark_webhook(
+    req: HttpRequest,
+    body: Bytes,
+    credentials: Data<WebhookCredentials>,
+    db_pool: Data<PgPool>,
+) -> Result<HttpResponse, WebhookError> {
+    if !is_authorized(&req, &credentials) {
+        return Err(WebhookError::Unauthorized);
+    }
+    let event: PostmarkEvent =
+        serde_json::from_slice(&body).map_err(|e| WebhookError::InvalidPayload(e.to_string()))?;
+    let Some((email, reason)) = event.suppression() else {
+        return Ok(HttpResponse::Ok().finish());
+    };
+
+    let mut txn = db_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    suppress_email(email, reason, txn.as_mut())
+        .await
+        .context("Failed to suppress the address.")?;
+    mark_subscriber(email, reason, txn.as_mut())
+        .await
+        .context("Failed to update the status of the subscriber.")?;
+    txn.commit()
+        .await
+        .context("Failed to commit SQL transaction to suppress an address.")?;
+    tracing::info!(reason = reason.as_str(), "Suppressed an address.");
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn is_authorized(req: &HttpRequest, credentials: &WebhookCredentials) -> bool {
+    let Some(decoded) = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Basic "))
+        .and_then(|h| STANDARD.decode(h.trim()).ok())
+        .and_then(|b| String::from_utf8(b).ok())
+    else {
+        return false;
+    };
+    let Some((username, password)) = decoded.split_once(':') else {
+        return false;
+    };
+    // Comparing digests keeps the time taken from revealing how much of the password matched
+    username == credentials.username
+        && Sha256::digest(password) == Sha256::digest(credentials.password.expose_secret())
+}
+
+#[tracing::instrument(skip(exec))]
+async fn mark_subscriber(
+    email: &str,
+    reason: SuppressionReason,
+    exec: impl PgExecutor<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE subscriptions SET status = $2 WHERE email = $1",
+        email,
+        reason.as_str()
+    )
+    .execute(exec)
+    .await?;
+    Ok(())
+}
+
+#[derive(thiserror::Error)]
+pub enum WebhookError {
+    #[error("Invalid webhook credentials.")]
+    Unauthorized,
+    #[error("{0}")]
+    InvalidPayload(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for WebhookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        utils::error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for WebhookError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut resp = HttpResponse::build(self.status_code());
+        if let Self::Unauthorized = self {
+            resp.insert_header((header::WWW_AUTHENTICATE, r#"Basic realm="webhooks""#));
+        }
+        resp.body(self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(payload: serde_json::Value) -> PostmarkEvent {
+        serde_json::from_value(payload).unwrap()
+    }
+
+    #[test]
+    fn hard_bounces_suppress_the_address() {
+        let event = parse(json!({
+            "RecordType": "Bounce",
+            "Type": "HardBounce",
+            "TypeCode": 1,
+            "Email": "ursula@example.com",
+        }));
+        assert_eq!(
+            event.suppression(),
+            Some(("ursula@example.com", SuppressionReason::Bounced))
+        );
+    }
+
+    #[test]
+    fn soft_bounces_are_ignored() {
+        let event = parse(json!({
+            "RecordType": "Bounce",
+            "Type": "SoftBounce",
+            "Email": "ursula@example.com",
+        }));
+        assert_eq!(event.suppression(), None);
+    }
+
+    #[test]
+    fn spam_complaints_suppress_the_address() {
+        let event = parse(json!({
+            "RecordType": "SpamComplaint",
+            "Type": "SpamComplaint",
+            "Email": "ursula@example.com",
+        }));
+        assert_eq!(
+            event.suppression(),
+            Some(("ursula@example.com", SuppressionReason::Complained))
+        );
+    }
+
+    #[test]
+    fn other_record_types_are_ignored() {
+        let event = parse(json!({ "RecordType": "Delivery", "Recipient": "ursula@example.com" }));
+        assert_eq!(event, PostmarkEvent::Other);
+    }
+}
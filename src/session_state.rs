@@ -30,6 +30,10 @@ where
     pub fn insert(&self, value: T) -> Result<(), SessionInsertError> {
         self.session.insert(self.key, value)
     }
+
+    pub fn remove(&self) {
+        self.session.remove(self.key);
+    }
 }
 
 pub struct Session(actix_session::Session);
@@ -46,6 +50,23 @@ impl Session {
     pub fn user_id(&self) -> StateKey<'_, Uuid> {
         StateKey::<Uuid>::new(self, "user_id")
     }
+
+    /// The value of `users.session_epoch` when the user logged in.
+    /// The session is no longer valid once the two differ.
+    pub fn session_epoch(&self) -> StateKey<'_, i32> {
+        StateKey::<i32>::new(self, "session_epoch")
+    }
+
+    /// The user whose password has been verified but who still has to enter their second factor.
+    /// They are not logged in until `user_id` is set.
+    pub fn pending_second_factor_user_id(&self) -> StateKey<'_, Uuid> {
+        StateKey::<Uuid>::new(self, "pending_second_factor_user_id")
+    }
+
+    /// The TOTP secret being enrolled, until the user confirms it with a code.
+    pub fn totp_enrollment_secret(&self) -> StateKey<'_, String> {
+        StateKey::<String>::new(self, "totp_enrollment_secret")
+    }
 }
 
 impl FromRequest for Session {
@@ -0,0 +1,171 @@
+//! Keeps the background workers running and stops everything on shutdown.
+use std::{
+    fmt::{Debug, Display},
+    future::Future,
+    time::{Duration, Instant},
+};
+use tokio::task::JoinError;
+use tokio_util::sync::CancellationToken;
+
+/// How long to wait before restarting a task that has stopped.
+#[derive(Clone, Debug)]
+pub struct RestartPolicy {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Exponential backoff, given the number of restarts in a row so far.
+    pub fn backoff(&self, n_restarts: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(n_restarts))
+            .min(self.max_backoff)
+    }
+}
+
+/// Runs the task built by `spawn_task` until `shutdown` is cancelled, restarting it
+/// with backoff whenever it fails, panics or returns early.
+///
+/// A task that ran for longer than the maximum backoff is restarted right away.
+pub async fn supervise<F, Fut>(
+    name: String,
+    policy: RestartPolicy,
+    shutdown: CancellationToken,
+    mut spawn_task: F,
+) where
+    F: FnMut(CancellationToken) -> Fut,
+    Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let mut n_restarts = 0;
+    loop {
+        let started_at = Instant::now();
+        // Spawned so that panics are caught rather than unwinding through the supervisor
+        let outcome = tokio::spawn(spawn_task(shutdown.clone())).await;
+        report_exit(&name, outcome);
+        if shutdown.is_cancelled() {
+            return;
+        }
+
+        if started_at.elapsed() > policy.max_backoff {
+            n_restarts = 0;
+        }
+        let backoff = policy.backoff(n_restarts);
+        n_restarts = n_restarts.saturating_add(1);
+        tracing::warn!("Restarting {} in {:?}", name, backoff);
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(backoff) => (),
+        }
+    }
+}
+
+/// Completes once the process has been asked to stop with SIGINT or SIGTERM.
+pub async fn shutdown_signal() -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut sigterm = signal(SignalKind::terminate())?;
+        tokio::select! {
+            r = tokio::signal::ctrl_c() => r?,
+            _ = sigterm.recv() => (),
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+pub fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
+    match outcome {
+        Ok(Ok(_)) => tracing::info!("{} has exited", task_name),
+        Ok(Err(e)) => tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "{} failed",
+                task_name
+        ),
+        Err(e) => tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "{} task failed to complete",
+                task_name
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    fn policy() -> RestartPolicy {
+        RestartPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn the_backoff_doubles_up_to_the_maximum() {
+        assert_eq!(policy().backoff(0), Duration::from_millis(1));
+        assert_eq!(policy().backoff(1), Duration::from_millis(2));
+        assert_eq!(policy().backoff(5), Duration::from_millis(4));
+        assert_eq!(policy().backoff(u32::MAX), Duration::from_millis(4));
+    }
+
+    #[tokio::test]
+    async fn failing_and_panicking_tasks_are_restarted() {
+        let n_runs = Arc::new(AtomicU32::new(0));
+        let shutdown = CancellationToken::new();
+        let spawn_task = {
+            let n_runs = n_runs.clone();
+            move |shutdown: CancellationToken| {
+                let n_runs = n_runs.clone();
+                async move {
+                    match n_runs.fetch_add(1, Ordering::SeqCst) {
+                        0 => anyhow::bail!("Crashed"),
+                        1 => panic!("Crashed harder"),
+                        _ => {
+                            shutdown.cancel();
+                            Ok(())
+                        }
+                    }
+                }
+            }
+        };
+
+        supervise("test".into(), policy(), shutdown, spawn_task).await;
+
+        assert_eq!(n_runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn tasks_are_not_restarted_after_shutdown() {
+        let n_runs = Arc::new(AtomicU32::new(0));
+        let shutdown = CancellationToken::new();
+        shutdown.cancel();
+        let spawn_task = {
+            let n_runs = n_runs.clone();
+            move |_| {
+                n_runs.fetch_add(1, Ordering::SeqCst);
+                async { Ok(()) }
+            }
+        };
+
+        supervise("test".into(), policy(), shutdown, spawn_task).await;
+
+        assert_eq!(n_runs.load(Ordering::SeqCst), 1);
+    }
+}
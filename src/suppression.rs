@@ -0,0 +1,51 @@
+//! The addresses that must not be emailed anymore, after the email provider reported
+//! a hard bounce or a spam complaint for them.
+use sqlx::PgExecutor;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SuppressionReason {
+    Bounced,
+    Complained,
+}
+
+impl SuppressionReason {
+    /// Also the status the subscriber with this address is moved to.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bounced => "bounced",
+            Self::Complained => "complained",
+        }
+    }
+}
+
+/// Adds the address to the suppression list, keeping the first reason it was suppressed for.
+#[tracing::instrument(skip(exec))]
+pub async fn suppress_email(
+    email: &str,
+    reason: SuppressionReason,
+    exec: impl PgExecutor<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+    INSERT INTO suppressed_emails (email, reason, suppressed_at)
+    VALUES ($1, $2, now())
+    ON CONFLICT (email) DO NOTHING
+    "#,
+        email,
+        reason.as_str()
+    )
+    .execute(exec)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(exec))]
+pub async fn is_suppressed(email: &str, exec: impl PgExecutor<'_>) -> Result<bool, sqlx::Error> {
+    let r = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM suppressed_emails WHERE email = $1) AS "exists!""#,
+        email
+    )
+    .fetch_one(exec)
+    .await?;
+    Ok(r)
+}
@@ -1,15 +1,75 @@
-use actix_web::{http::header, HttpResponse};
+use actix_web::{dev::Payload, http::header, web, FromRequest, HttpRequest, HttpResponse};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
 use std::{
     error::Error,
     fmt::{Debug, Display},
+    net::IpAddr,
 };
 
+/// Like `web::Form`, but collects repeated fields, such as checkboxes, into a `Vec`.
+pub struct HtmlForm<T>(pub T);
+
+impl<T> std::ops::Deref for HtmlForm<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned> FromRequest for HtmlForm<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let body = web::Bytes::from_request(req, payload);
+        Box::pin(async move {
+            let body = body.await?;
+            serde_html_form::from_bytes(&body)
+                .map(HtmlForm)
+                .map_err(e400)
+        })
+    }
+}
+
 pub fn see_other(location: &str) -> HttpResponse {
     HttpResponse::SeeOther()
         .insert_header((header::LOCATION, location))
         .finish()
 }
 
+/// The address of the client. `X-Forwarded-For` is only believed for the hops added by
+/// trusted proxies, since anything before them may have been written by the client.
+pub fn client_ip(req: &HttpRequest, trusted_proxies: &[IpAddr]) -> String {
+    let Some(mut client) = req.peer_addr().map(|a| a.ip()) else {
+        return "unknown".into();
+    };
+    let mut hops = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev();
+    // Each proxy appends the address it got the request from
+    while trusted_proxies.contains(&client) {
+        match hops.next().map(|hop| hop.trim().parse()) {
+            Some(Ok(ip)) => client = ip,
+            _ => break,
+        }
+    }
+    client.to_string()
+}
+
+/// Escapes the wildcards of a `LIKE` pattern so that the search is literal.
+pub fn escape_like(s: &str) -> String {
+    s.replace('\\', r"\\")
+        .replace('%', r"\%")
+        .replace('_', r"\_")
+}
+
 pub fn error_chain_fmt(e: &dyn Error, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     writeln!(f, "{}\n", e)?;
     let mut e = Some(e);
@@ -30,3 +90,44 @@ pub fn e400(e: impl Debug + Display + 'static) -> actix_web::Error {
 pub fn e500(e: impl Debug + Display + 'static) -> actix_web::Error {
     actix_web::error::ErrorInternalServerError(e)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    const PROXY: [u8; 4] = [10, 0, 0, 1];
+
+    fn request(peer: [u8; 4], forwarded_for: Option<&str>) -> HttpRequest {
+        let mut req = TestRequest::default().peer_addr((peer, 4000).into());
+        if let Some(f) = forwarded_for {
+            req = req.insert_header(("x-forwarded-for", f));
+        }
+        req.to_http_request()
+    }
+
+    #[test]
+    fn the_header_is_ignored_without_trusted_proxies() {
+        let req = request([203, 0, 113, 7], Some("198.51.100.1"));
+        assert_eq!(client_ip(&req, &[]), "203.0.113.7");
+    }
+
+    #[test]
+    fn the_hop_added_by_a_trusted_proxy_is_the_client() {
+        let req = request(PROXY, Some("198.51.100.1, 203.0.113.7"));
+        assert_eq!(client_ip(&req, &[PROXY.into()]), "203.0.113.7");
+    }
+
+    #[test]
+    fn chained_trusted_proxies_are_skipped() {
+        let req = request(PROXY, Some("203.0.113.7, 10.0.0.2"));
+        let trusted = [PROXY.into(), [10, 0, 0, 2].into()];
+        assert_eq!(client_ip(&req, &trusted), "203.0.113.7");
+    }
+
+    #[test]
+    fn a_trusted_proxy_without_the_header_is_the_client() {
+        let req = request(PROXY, None);
+        assert_eq!(client_ip(&req, &[PROXY.into()]), "10.0.0.1");
+    }
+}
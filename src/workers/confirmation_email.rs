@@ -0,0 +1,267 @@
+use crate::{
+    config::Settings,
+    domain::{SubscriberEmail, SubscriptionToken},
+    email_client::{Email, EmailTransport},
+    workers::{
+        issue_delivery::{ExecutionOutcome, RetryPolicy},
+        pause, Heartbeat,
+    },
+};
+use sqlx::{PgExecutor, PgPool};
+use std::{sync::Arc, time::Duration};
+use tokio_util::sync::CancellationToken;
+use tracing::Span;
+use uuid::Uuid;
+
+pub const NAME: &str = "confirmation_email";
+
+/// Sends the confirmation emails queued in the outbox by the signup transactions.
+///
+/// Retries follow the same policy as issue deliveries.
+pub struct Worker {
+    pool: PgPool,
+    email_client: Arc<dyn EmailTransport>,
+    base_url: String,
+    retry_policy: RetryPolicy,
+    batch_size: i64,
+}
+
+impl Worker {
+    pub fn builder(config: &Settings) -> Self {
+        let pool = config.database.get_db_pool();
+        let email_client = config.email_client.client();
+        let base_url = config.application.base_url.clone();
+        let retry_policy = config.issue_delivery.retry_policy();
+        let batch_size = config.issue_delivery.batch_size.into();
+        Self {
+            pool,
+            email_client,
+            base_url,
+            retry_policy,
+            batch_size,
+        }
+    }
+
+    /// Runs until a shutdown is requested, letting the task at hand complete first.
+    pub async fn finish(self, shutdown: CancellationToken) -> anyhow::Result<()> {
+        let mut heartbeat = Heartbeat::new(NAME, self.pool.clone());
+        while !shutdown.is_cancelled() {
+            heartbeat.beat().await;
+            let idle = match self.try_execute_task().await {
+                Err(_) => Duration::from_secs(1),
+                Ok(ExecutionOutcome::EmptyQueue) => Duration::from_secs(10),
+                Ok(ExecutionOutcome::TaskCompleted) => Duration::ZERO,
+            };
+            pause(idle, &shutdown).await;
+        }
+        Ok(())
+    }
+
+    /// Sends the next batch of due confirmation emails, one request per email.
+    ///
+    /// Emails for subscribers who are no longer pending, whose token is gone or whose address
+    /// is suppressed, are dropped.
+    #[tracing::instrument(skip_all, fields(n_tasks=tracing::field::Empty), err)]
+    pub async fn try_execute_task(&self) -> anyhow::Result<ExecutionOutcome> {
+        let mut txn = self.pool.begin().await?;
+        let tasks = dequeue_tasks(txn.as_mut(), self.batch_size).await?;
+        if tasks.is_empty() {
+            return Ok(ExecutionOutcome::EmptyQueue);
+        }
+        Span::current().record("n_tasks", tasks.len());
+
+        let mut completed = Vec::new();
+        for task in tasks {
+            let Some(token) = task.token.clone().filter(|_| task.is_pending) else {
+                tracing::info!(
+                    subscriber_id = %task.subscriber_id,
+                    "Skipping a subscriber who no longer needs to confirm.",
+                );
+                completed.push(task.subscriber_id);
+                continue;
+            };
+            if task.is_suppressed {
+                tracing::info!(
+                    subscriber_id = %task.subscriber_id,
+                    "Skipping a subscriber whose address is suppressed.",
+                );
+                completed.push(task.subscriber_id);
+                continue;
+            }
+            let (recipient, token) = match (
+                SubscriberEmail::parse(task.email.clone()),
+                SubscriptionToken::parse(token),
+            ) {
+                (Ok(r), Ok(t)) => (r, t),
+                (Err(e), _) | (_, Err(e)) => {
+                    tracing::error!(
+                        error.message = %e,
+                        subscriber_id = %task.subscriber_id,
+                        "Skipping a pending subscriber. Their stored details are invalid",
+                    );
+                    completed.push(task.subscriber_id);
+                    continue;
+                }
+            };
+            let email = confirmation_email(recipient, &self.base_url, &token);
+            let outcome = self
+                .email_client
+                .send_email(
+                    &email.recipient,
+                    &email.subject,
+                    &email.html_body,
+                    &email.text_body,
+                    &email.headers,
+                )
+                .await;
+            match outcome {
+                Ok(()) => completed.push(task.subscriber_id),
+                Err(e) if task.n_retries as u32 >= self.retry_policy.max_retries => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        subscriber_id = %task.subscriber_id,
+                        n_retries = task.n_retries,
+                        "Failed to send a confirmation email. Giving up.",
+                    );
+                    completed.push(task.subscriber_id);
+                }
+                Err(e) => {
+                    let backoff = self.retry_policy.backoff(task.n_retries as u32);
+                    tracing::warn!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        subscriber_id = %task.subscriber_id,
+                        n_retries = task.n_retries,
+                        "Failed to send a confirmation email. Retrying in {:?}.",
+                        backoff
+                    );
+                    postpone_task(txn.as_mut(), task.subscriber_id, backoff).await?;
+                }
+            }
+        }
+        delete_tasks(txn.as_mut(), &completed).await?;
+
+        txn.commit().await?;
+        Ok(ExecutionOutcome::TaskCompleted)
+    }
+}
+
+fn confirmation_email(
+    recipient: SubscriberEmail,
+    base_url: &str,
+    token: &SubscriptionToken,
+) -> Email {
+    let confirmation_link = format!(
+        "{}/subscriptions/confirm?token={}",
+        base_url,
+        token.as_ref()
+    );
+    Email {
+        recipient,
+        subject: "Welcome!".into(),
+        html_body: format!(
+            "Welcome to our newsletter!<br />\
+                Click <a href=\"{}\">here</a> to confirm your subscription.",
+            confirmation_link
+        ),
+        text_body: format!(
+            "Welcome to our newsletter!\nVisit {} to confirm your subscription.",
+            confirmation_link
+        ),
+        headers: Vec::new(),
+    }
+}
+
+/// Queues a confirmation email carrying the subscriber's current token.
+///
+/// Meant to run in the transaction storing the token, so that the email is sent
+/// once it commits.
+#[tracing::instrument(skip(exec))]
+pub async fn enqueue_confirmation_email(
+    subscriber_id: Uuid,
+    exec: impl PgExecutor<'_>,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO confirmation_email_outbox (subscriber_id)
+        VALUES ($1)
+        ON CONFLICT (subscriber_id) DO UPDATE
+        SET n_retries = 0, execute_after = now()
+        "#,
+        subscriber_id
+    )
+    .execute(exec)
+    .await?;
+    Ok(())
+}
+
+struct Task {
+    subscriber_id: Uuid,
+    n_retries: i32,
+    email: String,
+    is_pending: bool,
+    is_suppressed: bool,
+    token: Option<String>,
+}
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_tasks(exec: impl PgExecutor<'_>, batch_size: i64) -> anyhow::Result<Vec<Task>> {
+    let r = sqlx::query_as!(
+        Task,
+        r#"
+    SELECT
+        o.subscriber_id,
+        o.n_retries,
+        s.email,
+        s.status = 'pending_confirmation' AS "is_pending!",
+        EXISTS (SELECT 1 FROM suppressed_emails x WHERE x.email = s.email) AS "is_suppressed!",
+        t.token AS "token?"
+    FROM confirmation_email_outbox o
+    JOIN subscriptions s ON s.id = o.subscriber_id
+    LEFT JOIN subscription_tokens t ON t.subscriber_id = o.subscriber_id
+    WHERE o.execute_after <= now()
+    FOR UPDATE OF o
+    SKIP LOCKED
+    LIMIT $1
+    "#,
+        batch_size
+    )
+    .fetch_all(exec)
+    .await?;
+    Ok(r)
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_tasks(exec: impl PgExecutor<'_>, subscriber_ids: &[Uuid]) -> anyhow::Result<()> {
+    sqlx::query!(
+        "DELETE FROM confirmation_email_outbox WHERE subscriber_id = ANY($1)",
+        subscriber_ids
+    )
+    .execute(exec)
+    .await?;
+    Ok(())
+}
+
+/// Schedules another attempt once the backoff has elapsed.
+#[tracing::instrument(skip_all)]
+async fn postpone_task(
+    exec: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+    backoff: Duration,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+    UPDATE confirmation_email_outbox
+    SET
+        n_retries = n_retries + 1,
+        execute_after = now() + $2 * interval '1 millisecond'
+    WHERE subscriber_id = $1
+    "#,
+        subscriber_id,
+        backoff.as_millis() as f64
+    )
+    .execute(exec)
+    .await?;
+    Ok(())
+}
@@ -1,33 +1,89 @@
-use crate::config::Settings;
-use sqlx::{PgExecutor, PgPool};
+use crate::{
+    config::Settings,
+    workers::{pause, Heartbeat},
+};
+use anyhow::Context;
+use sqlx::PgPool;
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+pub const NAME: &str = "expiration";
 
 pub struct Worker {
     pool: PgPool,
+    unconfirmed_retention_days: u32,
 }
 
 impl Worker {
     pub fn builder(config: &Settings) -> Self {
         let pool = config.database.get_db_pool();
-        Self { pool }
+        Self {
+            pool,
+            unconfirmed_retention_days: config.subscriptions.unconfirmed_retention_days,
+        }
     }
 
-    pub async fn finish(self) -> anyhow::Result<()> {
-        loop {
+    /// Runs until a shutdown is requested or a clean-up fails.
+    pub async fn finish(self, shutdown: CancellationToken) -> anyhow::Result<()> {
+        let mut heartbeat = Heartbeat::new(NAME, self.pool.clone());
+        while !shutdown.is_cancelled() {
+            heartbeat.beat().await;
             delete_expired(&self.pool).await?;
-            tokio::time::sleep(Duration::from_secs(120)).await;
+            delete_unconfirmed(&self.pool, self.unconfirmed_retention_days).await?;
+            pause(Duration::from_secs(120), &shutdown).await;
         }
+        Ok(())
     }
 }
 
-pub async fn delete_expired(exec: impl PgExecutor<'_>) -> anyhow::Result<()> {
+pub async fn delete_expired(pool: &PgPool) -> anyhow::Result<()> {
     sqlx::query!(
         r#"
     DELETE FROM idempotency
     WHERE created_at < now() - interval '1 day'
     "#
     )
-    .execute(exec)
+    .execute(pool)
+    .await?;
+    // Left behind by processes that have stopped
+    sqlx::query!(
+        r#"
+    DELETE FROM worker_heartbeats
+    WHERE beat_at < now() - interval '1 day'
+    "#
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Deletes confirmation tokens that expired more than `retention_days` ago, then the
+/// subscriptions that were never confirmed within that window and have no live token.
+pub async fn delete_unconfirmed(pool: &PgPool, retention_days: u32) -> anyhow::Result<()> {
+    let retention_days = i32::try_from(retention_days).context("Retention period too long")?;
+    let mut txn = pool.begin().await?;
+    sqlx::query!(
+        r#"
+    DELETE FROM subscription_tokens
+    WHERE expires_at < now() - make_interval(days => $1)
+    "#,
+        retention_days
+    )
+    .execute(txn.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+    DELETE FROM subscriptions
+    WHERE status = 'pending_confirmation'
+        AND subscribed_at < now() - make_interval(days => $1)
+        AND NOT EXISTS (
+            SELECT 1 FROM subscription_tokens WHERE subscriber_id = subscriptions.id
+        )
+    "#,
+        retention_days
+    )
+    .execute(txn.as_mut())
     .await?;
+    txn.commit().await?;
     Ok(())
 }
@@ -1,125 +1,554 @@
-use crate::{config::Settings, domain::SubscriberEmail, email_client::EmailClient};
-use sqlx::{PgExecutor, PgPool};
-use std::time::Duration;
+use crate::{
+    config::Settings,
+    domain::{DeliveryId, Segment, SubscriberEmail, UnsubscribeToken},
+    email_client::{Email, EmailHeader, EmailTransport},
+    workers::{pause, Heartbeat},
+};
+use anyhow::Context;
+use reqwest::Url;
+use secrecy::SecretString;
+use sqlx::{types::Json, PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+use tokio_util::sync::CancellationToken;
 use tracing::Span;
 use uuid::Uuid;
 
+pub const NAME: &str = "issue_delivery";
+
 pub struct Worker {
     pool: PgPool,
-    email_client: EmailClient,
+    email_client: Arc<dyn EmailTransport>,
+    base_url: String,
+    hmac_secret: SecretString,
+    retry_policy: RetryPolicy,
+    batch_size: i64,
 }
 impl Worker {
     pub fn builder(config: &Settings) -> Self {
         let pool = config.database.get_db_pool();
         let email_client = config.email_client.client();
-        Self { pool, email_client }
+        let base_url = config.application.base_url.clone();
+        let hmac_secret = config.application.hmac_secret.clone();
+        let retry_policy = config.issue_delivery.retry_policy();
+        let batch_size = config.issue_delivery.batch_size.into();
+        Self {
+            pool,
+            email_client,
+            base_url,
+            hmac_secret,
+            retry_policy,
+            batch_size,
+        }
+    }
+
+    /// Runs until a shutdown is requested, letting the task at hand complete first.
+    pub async fn finish(self, shutdown: CancellationToken) -> anyhow::Result<()> {
+        let mut heartbeat = Heartbeat::new(NAME, self.pool.clone());
+        while !shutdown.is_cancelled() {
+            heartbeat.beat().await;
+            let idle = match self.try_execute_task().await {
+                Err(_) => Duration::from_secs(1),
+                Ok(ExecutionOutcome::EmptyQueue) => Duration::from_secs(10),
+                Ok(ExecutionOutcome::TaskCompleted) => Duration::ZERO,
+            };
+            pause(idle, &shutdown).await;
+        }
+        Ok(())
     }
 
-    pub async fn finish(self) -> anyhow::Result<()> {
-        loop {
-            match try_execute_task(&self.pool, &self.email_client).await {
-                Err(_) => tokio::time::sleep(Duration::from_secs(1)).await,
-                Ok(ExecutionOutcome::EmptyQueue) => {
-                    tokio::time::sleep(Duration::from_secs(10)).await
+    /// Delivers the next batch of due tasks.
+    ///
+    /// Only the emails that could not be delivered are retried.
+    #[tracing::instrument(skip_all, fields(n_tasks=tracing::field::Empty), err)]
+    pub async fn try_execute_task(&self) -> anyhow::Result<ExecutionOutcome> {
+        let mut txn = self.pool.begin().await?;
+        let tasks = dequeue_tasks(txn.as_mut(), self.batch_size).await?;
+        if tasks.is_empty() {
+            return Ok(ExecutionOutcome::EmptyQueue);
+        }
+        Span::current().record("n_tasks", tasks.len());
+
+        let subscriber_ids = {
+            let emails: Vec<_> = tasks.iter().map(|t| t.subscriber_email.clone()).collect();
+            get_confirmed_subscriber_ids(txn.as_mut(), &emails).await?
+        };
+        let mut issues = HashMap::new();
+        let mut completed = Vec::new();
+        let mut pending = Vec::new();
+        let mut emails = Vec::new();
+        for task in tasks {
+            let Some(&subscriber_id) = subscriber_ids.get(&task.subscriber_email) else {
+                tracing::info!(
+                    subscriber_email = %task.subscriber_email,
+                    "Skipping a subscriber who is no longer confirmed.",
+                );
+                completed.push(task);
+                continue;
+            };
+            let recipient = match SubscriberEmail::parse(task.subscriber_email.clone()) {
+                Ok(r) => r,
+                Err(e) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        subscriber_email = %task.subscriber_email,
+                        "Skipping a confirmed subscriber. Their stored contact details are invalid",
+                    );
+                    completed.push(task);
+                    continue;
+                }
+            };
+            let issue = match issues.entry(task.newsletter_issue_id) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => {
+                    e.insert(get_issue(txn.as_mut(), task.newsletter_issue_id).await?)
+                }
+            };
+            emails.push(self.issue_email(issue, recipient, subscriber_id));
+            pending.push(task);
+        }
+
+        let outcomes = self.email_client.send_batch(&emails).await;
+        let mut n_delivered: HashMap<Uuid, i32> = HashMap::new();
+        for (task, outcome) in pending.into_iter().zip(outcomes) {
+            match outcome {
+                Ok(()) => {
+                    *n_delivered.entry(task.newsletter_issue_id).or_default() += 1;
+                    completed.push(task);
+                }
+                Err(e) if task.n_retries as u32 >= self.retry_policy.max_retries => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        newsletter_issue_id = %task.newsletter_issue_id,
+                        subscriber_email = %task.subscriber_email,
+                        n_retries = task.n_retries,
+                        "Failed to deliver issue to a confirmed subscriber. Giving up.",
+                    );
+                    dead_letter_task(txn.as_mut(), &task, &e.to_string()).await?;
+                    completed.push(task);
+                }
+                Err(e) => {
+                    let backoff = self.retry_policy.backoff(task.n_retries as u32);
+                    tracing::warn!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        newsletter_issue_id = %task.newsletter_issue_id,
+                        subscriber_email = %task.subscriber_email,
+                        n_retries = task.n_retries,
+                        "Failed to deliver issue to a confirmed subscriber. Retrying in {:?}.",
+                        backoff
+                    );
+                    postpone_task(txn.as_mut(), &task, backoff).await?;
                 }
-                Ok(ExecutionOutcome::TaskCompleted) => (),
             }
         }
+        delete_tasks(txn.as_mut(), &completed).await?;
+        record_deliveries(txn.as_mut(), &n_delivered).await?;
+
+        txn.commit().await?;
+        Ok(ExecutionOutcome::TaskCompleted)
+    }
+
+    fn issue_email(
+        &self,
+        issue: &NewsletterIssue,
+        recipient: SubscriberEmail,
+        subscriber_id: Uuid,
+    ) -> Email {
+        let unsubscribe_link = self.subscriber_link("unsubscribe", subscriber_id);
+        let preferences_link = self.subscriber_link("preferences", subscriber_id);
+        let html_content = if issue.tracking_enabled {
+            self.tracked_html(issue, subscriber_id)
+        } else {
+            issue.html_content.clone()
+        };
+        Email {
+            recipient,
+            subject: issue.title.clone(),
+            html_body: format!(
+                "{}<p><a href=\"{}\">Manage your preferences</a> | <a href=\"{}\">Unsubscribe</a></p>",
+                html_content, preferences_link, unsubscribe_link
+            ),
+            text_body: format!(
+                "{}\n\nManage your preferences: {}\nUnsubscribe: {}",
+                issue.text_content, preferences_link, unsubscribe_link
+            ),
+            headers: unsubscribe_headers(&unsubscribe_link),
+        }
+    }
+
+    /// The HTML content of the issue with its links going through the click tracker
+    /// and an open tracking pixel appended.
+    fn tracked_html(&self, issue: &NewsletterIssue, subscriber_id: Uuid) -> String {
+        let delivery = DeliveryId::new(issue.id, subscriber_id);
+        let d = delivery.sign(&self.hmac_secret);
+        let mut html = rewrite_links(&issue.html_content, |url| {
+            let sig = delivery.sign_link(url, &self.hmac_secret);
+            Url::parse_with_params(
+                &format!("{}/track/click", self.base_url),
+                [("d", d.as_str()), ("url", url), ("sig", &sig)],
+            )
+            .ok()
+            .map(String::from)
+        });
+        if let Ok(pixel) =
+            Url::parse_with_params(&format!("{}/track/open", self.base_url), [("d", &d)])
+        {
+            html.push_str(&format!(
+                "<img src=\"{}\" width=\"1\" height=\"1\" alt=\"\">",
+                pixel.as_str().replace('&', "&amp;")
+            ));
+        }
+        html
+    }
+
+    /// A link to one of the `/subscriptions/{page}` pages signed for the subscriber.
+    fn subscriber_link(&self, page: &str, subscriber_id: Uuid) -> String {
+        let token = UnsubscribeToken::sign(subscriber_id, &self.hmac_secret);
+        format!(
+            "{}/subscriptions/{}?subscriber_id={}&token={}",
+            self.base_url,
+            page,
+            subscriber_id,
+            token.as_ref()
+        )
     }
 }
 
 #[must_use]
 pub enum ExecutionOutcome {
+    /// A batch of tasks has been processed.
     TaskCompleted,
     EmptyQueue,
 }
 
-#[tracing::instrument(
-    skip_all,
-    fields(
-        newsletter_issue_id=tracing::field::Empty,
-        subscriber_email=tracing::field::Empty,
-    )
-    err
-)]
-pub async fn try_execute_task(
-    pool: &PgPool,
-    email_client: &EmailClient,
-) -> anyhow::Result<ExecutionOutcome> {
-    let mut txn = pool.begin().await?;
-    let (issue_id, email) = match dequeue_task(txn.as_mut()).await? {
-        Some(v) => v,
-        None => return Ok(ExecutionOutcome::EmptyQueue),
-    };
-    Span::current()
-        .record("newsletter_issue_id", tracing::field::display(issue_id))
-        .record("subscriber_email", tracing::field::display(&email));
-    match SubscriberEmail::parse(email.clone()) {
-        Err(e) => {
-            tracing::error!(
-                error.cause_chain = ?e,
-                error.message = %e,
-                "Skipping a confirmed subscriber. Their stored contact details are invalid",
-            );
-        }
-        Ok(email) => {
-            let issue = get_issue(txn.as_mut(), issue_id).await?;
-            if let Err(e) = email_client
-                .send_email(
-                    &email,
-                    &issue.title,
-                    &issue.html_content,
-                    &issue.text_content,
-                )
-                .await
-            {
-                tracing::error!(
-                    error.cause_chain = ?e,
-                    error.message = %e,
-                    "Failed to deliver issue to a confirmed subscriber. Skipping.",
-                );
-            }
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff before the next attempt, given the number of retries so far.
+    pub fn backoff(&self, n_retries: u32) -> Duration {
+        let factor = 2u32.saturating_pow(n_retries);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Headers enabling one-click unsubscription as described in RFC 8058.
+fn unsubscribe_headers(unsubscribe_link: &str) -> Vec<EmailHeader> {
+    vec![
+        EmailHeader::new("List-Unsubscribe", format!("<{}>", unsubscribe_link)),
+        EmailHeader::new("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+    ]
+}
+
+/// Replaces the absolute `http(s)` URLs of the double-quoted `href` attributes of `html`
+/// with the result of `rewrite`, leaving the links it returns `None` for untouched.
+fn rewrite_links(html: &str, rewrite: impl Fn(&str) -> Option<String>) -> String {
+    const HREF: &str = "href=\"";
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(HREF) {
+        let (before, after) = rest.split_at(start + HREF.len());
+        out.push_str(before);
+        let Some(end) = after.find('"') else {
+            rest = after;
+            break;
+        };
+        let link = &after[..end];
+        let url = link.replace("&amp;", "&");
+        let is_web_link = ["http://", "https://"].iter().any(|p| {
+            url.get(..p.len())
+                .is_some_and(|s| s.eq_ignore_ascii_case(p))
+        });
+        match is_web_link.then(|| rewrite(&url)).flatten() {
+            Some(tracked) => out.push_str(&tracked.replace('&', "&amp;")),
+            None => out.push_str(link),
         }
+        rest = &after[end..];
     }
-    delete_task(txn.as_mut(), issue_id, &email).await?;
+    out.push_str(rest);
+    out
+}
 
-    txn.commit().await?;
-    Ok(ExecutionOutcome::TaskCompleted)
+struct Task {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+    n_retries: i32,
+}
+
+/// Queues the delivery of an issue to its audience.
+#[tracing::instrument(skip_all)]
+pub async fn enqueue_delivery_tasks(issue_id: Uuid, conn: &mut PgConnection) -> anyhow::Result<()> {
+    let segment = get_issue_segment(&mut *conn, issue_id).await?;
+    let mut qb = QueryBuilder::new(
+        "INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email) SELECT ",
+    );
+    qb.push_bind(issue_id).push(", s.email");
+    push_audience(&mut qb, Some(issue_id), segment.as_ref());
+    qb.build().execute(conn).await?;
+    Ok(())
+}
+
+/// Moves the failed deliveries matching the filters back to the delivery queue, returning
+/// how many were moved. Deliveries that are already queued are left where they are.
+#[tracing::instrument(skip(exec))]
+pub async fn requeue_failed_tasks(
+    newsletter_issue_id: Option<Uuid>,
+    subscriber_email: Option<&str>,
+    exec: impl PgExecutor<'_>,
+) -> anyhow::Result<u64> {
+    let n = sqlx::query!(
+        r#"
+        WITH requeued AS (
+            INSERT INTO issue_delivery_queue (
+                newsletter_issue_id,
+                subscriber_email
+            )
+            SELECT newsletter_issue_id, subscriber_email
+            FROM failed_deliveries
+            WHERE ($1::uuid IS NULL OR newsletter_issue_id = $1)
+                AND ($2::text IS NULL OR subscriber_email = $2)
+            ON CONFLICT DO NOTHING
+            RETURNING newsletter_issue_id, subscriber_email
+        )
+        DELETE FROM failed_deliveries f
+        USING requeued r
+        WHERE f.newsletter_issue_id = r.newsletter_issue_id
+            AND f.subscriber_email = r.subscriber_email
+        "#,
+        newsletter_issue_id,
+        subscriber_email
+    )
+    .execute(exec)
+    .await
+    .context("Failed to re-queue failed deliveries.")?
+    .rows_affected();
+    Ok(n)
+}
+
+/// Counts the subscribers an issue would be delivered to if it went out now.
+#[tracing::instrument(skip_all)]
+pub async fn count_recipients(issue_id: Uuid, conn: &mut PgConnection) -> anyhow::Result<i64> {
+    let segment = get_issue_segment(&mut *conn, issue_id).await?;
+    let mut qb = QueryBuilder::new("SELECT COUNT(*)");
+    push_audience(&mut qb, Some(issue_id), segment.as_ref());
+    let n = qb.build_query_scalar().fetch_one(conn).await?;
+    Ok(n)
 }
 
+/// Counts the confirmed subscribers of a segment.
 #[tracing::instrument(skip_all)]
-async fn dequeue_task(exec: impl PgExecutor<'_>) -> anyhow::Result<Option<(Uuid, String)>> {
+pub async fn count_segment_members(
+    segment: &Segment,
+    exec: impl PgExecutor<'_>,
+) -> anyhow::Result<i64> {
+    let mut qb = QueryBuilder::new("SELECT COUNT(*)");
+    push_audience(&mut qb, None, Some(segment));
+    let n = qb.build_query_scalar().fetch_one(exec).await?;
+    Ok(n)
+}
+
+/// Appends the `FROM` and `WHERE` clauses selecting the confirmed subscribers, as `s`,
+/// of the lists targeted by the issue - all of them if it targets none - and of the segment,
+/// leaving out suppressed addresses.
+fn push_audience(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    issue_id: Option<Uuid>,
+    segment: Option<&Segment>,
+) {
+    qb.push(
+        " FROM subscriptions s WHERE s.status = 'confirmed' \
+            AND NOT EXISTS (SELECT 1 FROM suppressed_emails x WHERE x.email = s.email)",
+    );
+    if let Some(issue_id) = issue_id {
+        qb.push(
+            " AND (NOT EXISTS (SELECT 1 FROM newsletter_issue_lists WHERE newsletter_issue_id = ",
+        )
+        .push_bind(issue_id)
+        .push(
+            ") OR EXISTS (SELECT 1 FROM newsletter_issue_lists il \
+                JOIN subscription_lists sl ON sl.list_id = il.list_id \
+                WHERE sl.subscriber_id = s.id AND il.newsletter_issue_id = ",
+        )
+        .push_bind(issue_id)
+        .push("))");
+    }
+    if let Some(segment) = segment {
+        segment.push_conditions(qb);
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_issue_segment(
+    exec: impl PgExecutor<'_>,
+    issue_id: Uuid,
+) -> anyhow::Result<Option<Segment>> {
     let r = sqlx::query!(
         r#"
+    SELECT g.definition AS "definition: Json<Segment>"
+    FROM newsletter_issues i
+    JOIN segments g ON g.id = i.segment_id
+    WHERE i.id = $1
+    "#,
+        issue_id
+    )
+    .fetch_optional(exec)
+    .await?;
+    Ok(r.map(|r| r.definition.0))
+}
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_tasks(exec: impl PgExecutor<'_>, batch_size: i64) -> anyhow::Result<Vec<Task>> {
+    let r = sqlx::query_as!(
+        Task,
+        r#"
     SELECT 
         newsletter_issue_id,
-        subscriber_email
+        subscriber_email,
+        n_retries
     FROM issue_delivery_queue
+    WHERE execute_after <= now()
     FOR UPDATE
     SKIP LOCKED
-    LIMIT 1
-    "#
+    LIMIT $1
+    "#,
+        batch_size
     )
-    .fetch_optional(exec)
-    .await?
-    .map(|r| (r.newsletter_issue_id, r.subscriber_email));
+    .fetch_all(exec)
+    .await?;
     Ok(r)
 }
 
+/// Maps the given emails to the ids of the subscribers who are still confirmed.
 #[tracing::instrument(skip_all)]
-async fn delete_task(
+async fn get_confirmed_subscriber_ids(
     exec: impl PgExecutor<'_>,
-    newsletter_issue_id: Uuid,
-    email: &str,
+    emails: &[String],
+) -> anyhow::Result<HashMap<String, Uuid>> {
+    let ids = sqlx::query!(
+        r#"
+    SELECT id, email
+    FROM subscriptions
+    WHERE email = ANY($1)
+        AND status = 'confirmed'
+    "#,
+        emails
+    )
+    .fetch_all(exec)
+    .await?
+    .into_iter()
+    .map(|r| (r.email, r.id))
+    .collect();
+    Ok(ids)
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_tasks(exec: impl PgExecutor<'_>, tasks: &[Task]) -> anyhow::Result<()> {
+    let (issue_ids, emails): (Vec<_>, Vec<_>) = tasks
+        .iter()
+        .map(|t| (t.newsletter_issue_id, t.subscriber_email.clone()))
+        .unzip();
+    sqlx::query!(
+        r#"
+    DELETE FROM issue_delivery_queue q
+    USING UNNEST($1::uuid[], $2::text[]) AS t(newsletter_issue_id, subscriber_email)
+    WHERE q.newsletter_issue_id = t.newsletter_issue_id
+        AND q.subscriber_email = t.subscriber_email
+    "#,
+        &issue_ids,
+        &emails
+    )
+    .execute(exec)
+    .await?;
+    Ok(())
+}
+
+/// Adds the emails that have just been sent to each issue's delivery count.
+#[tracing::instrument(skip_all)]
+async fn record_deliveries(
+    exec: impl PgExecutor<'_>,
+    n_delivered: &HashMap<Uuid, i32>,
+) -> anyhow::Result<()> {
+    let (issue_ids, counts): (Vec<Uuid>, Vec<i32>) =
+        n_delivered.iter().map(|(id, n)| (*id, *n)).unzip();
+    sqlx::query!(
+        r#"
+    UPDATE newsletter_issues i
+    SET n_delivered = i.n_delivered + t.n
+    FROM UNNEST($1::uuid[], $2::int[]) AS t(id, n)
+    WHERE i.id = t.id
+    "#,
+        &issue_ids,
+        &counts
+    )
+    .execute(exec)
+    .await?;
+    Ok(())
+}
+
+/// Schedules another attempt for the task once the backoff has elapsed.
+#[tracing::instrument(skip_all)]
+async fn postpone_task(
+    exec: impl PgExecutor<'_>,
+    task: &Task,
+    backoff: Duration,
 ) -> anyhow::Result<()> {
     sqlx::query!(
         r#"
-    DELETE FROM issue_delivery_queue
+    UPDATE issue_delivery_queue
+    SET
+        n_retries = n_retries + 1,
+        execute_after = now() + $3 * interval '1 millisecond'
     WHERE newsletter_issue_id = $1
         AND subscriber_email = $2
     "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        backoff.as_millis() as f64
+    )
+    .execute(exec)
+    .await?;
+    Ok(())
+}
+
+/// Records a task that has exhausted its retries so that it can be inspected and re-queued.
+#[tracing::instrument(skip_all)]
+async fn dead_letter_task(
+    exec: impl PgExecutor<'_>,
+    task: &Task,
+    last_error: &str,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+    INSERT INTO failed_deliveries (
         newsletter_issue_id,
-        email
+        subscriber_email,
+        n_retries,
+        last_error,
+        failed_at
+    )
+    VALUES ($1, $2, $3, $4, now())
+    ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+    SET
+        n_retries = EXCLUDED.n_retries,
+        last_error = EXCLUDED.last_error,
+        failed_at = EXCLUDED.failed_at
+    "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        task.n_retries,
+        last_error
     )
     .execute(exec)
     .await?;
@@ -127,9 +556,11 @@ async fn delete_task(
 }
 
 struct NewsletterIssue {
+    id: Uuid,
     title: String,
     text_content: String,
     html_content: String,
+    tracking_enabled: bool,
 }
 
 #[tracing::instrument(skip_all)]
@@ -137,7 +568,7 @@ async fn get_issue(exec: impl PgExecutor<'_>, issue_id: Uuid) -> anyhow::Result<
     let issue = sqlx::query_as!(
         NewsletterIssue,
         r#"
-        SELECT title, text_content, html_content
+        SELECT id, title, text_content, html_content, tracking_enabled
         FROM newsletter_issues
         WHERE
             id = $1
@@ -148,3 +579,58 @@ async fn get_issue(exec: impl PgExecutor<'_>, issue_id: Uuid) -> anyhow::Result<
     .await?;
     Ok(issue)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(300),
+        }
+    }
+
+    #[test]
+    fn the_first_retry_waits_for_the_initial_backoff() {
+        assert_eq!(policy().backoff(0), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn the_backoff_doubles_after_each_retry() {
+        assert_eq!(policy().backoff(1), Duration::from_secs(60));
+        assert_eq!(policy().backoff(2), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn the_backoff_is_capped() {
+        assert_eq!(policy().backoff(4), Duration::from_secs(300));
+        assert_eq!(policy().backoff(u32::MAX), Duration::from_secs(300));
+    }
+
+    fn track(url: &str) -> Option<String> {
+        Some(format!("https://tracker/?d=1&to={}", url))
+    }
+
+    #[test]
+    fn web_links_are_rewritten() {
+        let html = r#"<p><a href="https://a.example/?q=1&amp;r=2">A</a> <a href="HTTP://b.example">B</a></p>"#;
+        assert_eq!(
+            rewrite_links(html, track),
+            r#"<p><a href="https://tracker/?d=1&amp;to=https://a.example/?q=1&amp;r=2">A</a> <a href="https://tracker/?d=1&amp;to=HTTP://b.example">B</a></p>"#
+        );
+    }
+
+    #[test]
+    fn other_links_are_left_untouched() {
+        let html = r##"<a href="mailto:me@example.com">Me</a><a href="/relative">R</a><a href="#top">T</a>"##;
+        assert_eq!(rewrite_links(html, track), html);
+    }
+
+    #[test]
+    fn unterminated_attributes_are_left_untouched() {
+        let html = r#"<a href="https://a.example>A</a>"#;
+        assert_eq!(rewrite_links(html, track), html);
+    }
+}
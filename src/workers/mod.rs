@@ -1,2 +1,87 @@
+pub mod confirmation_email;
 pub mod expiration;
 pub mod issue_delivery;
+pub mod publishing;
+
+use sqlx::{PgExecutor, PgPool};
+use std::{
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// The names the workers spawned by `main` report their heartbeats under.
+pub const WORKER_NAMES: [&str; 4] = [
+    confirmation_email::NAME,
+    expiration::NAME,
+    issue_delivery::NAME,
+    publishing::NAME,
+];
+
+/// Identifies this process in the heartbeats, so that its readiness probe only looks at
+/// the workers it runs itself.
+pub static INSTANCE_ID: LazyLock<Uuid> = LazyLock::new(Uuid::new_v4);
+
+/// Heartbeats are written at most this often, so that busy workers do not hammer the database.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Tells the readiness probe that a worker's loop is still turning.
+pub struct Heartbeat {
+    worker: &'static str,
+    pool: PgPool,
+    last_beat: Option<Instant>,
+}
+
+impl Heartbeat {
+    pub fn new(worker: &'static str, pool: PgPool) -> Self {
+        Self {
+            worker,
+            pool,
+            last_beat: None,
+        }
+    }
+
+    /// Failing to record a heartbeat is logged rather than stopping the worker.
+    pub async fn beat(&mut self) {
+        if self
+            .last_beat
+            .is_some_and(|t| t.elapsed() < HEARTBEAT_INTERVAL)
+        {
+            return;
+        }
+        match record_heartbeat(self.worker, &self.pool).await {
+            Ok(()) => self.last_beat = Some(Instant::now()),
+            Err(e) => tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                worker = self.worker,
+                "Failed to record a worker heartbeat."
+            ),
+        }
+    }
+}
+
+/// Sleeps for `duration`, waking up early if a shutdown is requested.
+async fn pause(duration: Duration, shutdown: &CancellationToken) {
+    tokio::select! {
+        _ = shutdown.cancelled() => (),
+        _ = tokio::time::sleep(duration) => (),
+    }
+}
+
+/// Records that the worker is running in this process.
+pub async fn record_heartbeat(worker: &str, exec: impl PgExecutor<'_>) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+    INSERT INTO worker_heartbeats (worker, instance, beat_at)
+    VALUES ($1, $2, now())
+    ON CONFLICT (worker, instance) DO UPDATE SET beat_at = EXCLUDED.beat_at
+    "#,
+        worker,
+        *INSTANCE_ID
+    )
+    .execute(exec)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use crate::{
+    config::Settings,
+    workers::{
+        issue_delivery::{enqueue_delivery_tasks, ExecutionOutcome},
+        pause, Heartbeat,
+    },
+};
+use sqlx::{PgExecutor, PgPool};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+pub const NAME: &str = "publishing";
+
+/// Publishes scheduled issues once their time has come.
+pub struct Worker {
+    pool: PgPool,
+}
+
+impl Worker {
+    pub fn builder(config: &Settings) -> Self {
+        let pool = config.database.get_db_pool();
+        Self { pool }
+    }
+
+    /// Runs until a shutdown is requested, letting the task at hand complete first.
+    pub async fn finish(self, shutdown: CancellationToken) -> anyhow::Result<()> {
+        let mut heartbeat = Heartbeat::new(NAME, self.pool.clone());
+        while !shutdown.is_cancelled() {
+            heartbeat.beat().await;
+            let idle = match self.try_execute_task().await {
+                Err(_) => Duration::from_secs(1),
+                Ok(ExecutionOutcome::EmptyQueue) => Duration::from_secs(10),
+                Ok(ExecutionOutcome::TaskCompleted) => Duration::ZERO,
+            };
+            pause(idle, &shutdown).await;
+        }
+        Ok(())
+    }
+
+    /// Publishes the next scheduled issue that is due, enqueueing its delivery.
+    #[tracing::instrument(skip_all, fields(newsletter_issue_id=tracing::field::Empty), err)]
+    pub async fn try_execute_task(&self) -> anyhow::Result<ExecutionOutcome> {
+        let mut txn = self.pool.begin().await?;
+        let Some(issue_id) = dequeue_due_issue(txn.as_mut()).await? else {
+            return Ok(ExecutionOutcome::EmptyQueue);
+        };
+        tracing::Span::current().record("newsletter_issue_id", tracing::field::display(issue_id));
+
+        mark_issue_as_published(txn.as_mut(), issue_id).await?;
+        enqueue_delivery_tasks(issue_id, txn.as_mut()).await?;
+
+        txn.commit().await?;
+        Ok(ExecutionOutcome::TaskCompleted)
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_due_issue(exec: impl PgExecutor<'_>) -> anyhow::Result<Option<Uuid>> {
+    let r = sqlx::query!(
+        r#"
+    SELECT id
+    FROM newsletter_issues
+    WHERE status = 'scheduled'
+        AND scheduled_for <= now()
+    FOR UPDATE
+    SKIP LOCKED
+    LIMIT 1
+    "#,
+    )
+    .fetch_optional(exec)
+    .await?;
+    Ok(r.map(|r| r.id))
+}
+
+#[tracing::instrument(skip(exec))]
+async fn mark_issue_as_published(exec: impl PgExecutor<'_>, issue_id: Uuid) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+    UPDATE newsletter_issues
+    SET status = 'published', published_at = now()
+    WHERE id = $1
+    "#,
+        issue_id
+    )
+    .execute(exec)
+    .await?;
+    Ok(())
+}
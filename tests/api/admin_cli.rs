@@ -0,0 +1,336 @@
+use crate::helpers::{self, TestApp};
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+use uuid::Uuid;
+
+impl TestApp {
+    /// Runs the admin binary against the test database, feeding it `stdin`.
+    fn run_admin(&self, args: &[&str], stdin: &str) -> Output {
+        let database = self
+            .db_pool
+            .connect_options()
+            .get_database()
+            .unwrap()
+            .to_owned();
+        let mut child = Command::new(env!("CARGO_BIN_EXE_admin"))
+            .args(args)
+            .env("APP__DATABASE__NAME", database)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to run the admin binary.");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    async fn publish_an_issue_and_get_its_id(&self) -> Uuid {
+        self.login_as_test_user().await;
+        let resp = self
+            .post_newsletters(&serde_json::json!({
+                "title": "Newsletter title",
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+                "idempotency_key": Uuid::new_v4(),
+            }))
+            .await;
+        helpers::assert_redirects_to(&resp, "/admin/newsletters");
+        sqlx::query_scalar!("SELECT id FROM newsletter_issues")
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[tokio::test]
+async fn created_users_can_log_in() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let password = Uuid::new_v4().to_string();
+
+    // Act
+    let output = app.run_admin(
+        &[
+            "user",
+            "create",
+            "ursula",
+            "--email",
+            "ursula_le_guin@gmail.com",
+            "--role",
+            "editor",
+            "--password-stdin",
+        ],
+        &format!("{}\n", password),
+    );
+
+    // Assert
+    assert!(output.status.success(), "{:?}", output);
+    let role = sqlx::query_scalar!("SELECT role FROM users WHERE username = 'ursula'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(role, "editor");
+    let resp = app
+        .post_login(&serde_json::json!({
+            "username": "ursula",
+            "password": password,
+        }))
+        .await;
+    helpers::assert_redirects_to(&resp, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn users_cannot_be_created_with_a_taken_username_or_a_short_password() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let password = format!("{}\n", Uuid::new_v4());
+    let cases = [
+        (
+            app.test_user.username.as_str(),
+            password.as_str(),
+            "taken username",
+        ),
+        ("ursula", "short\n", "short password"),
+    ];
+
+    for (username, password, description) in cases {
+        // Act
+        let output = app.run_admin(
+            &[
+                "user",
+                "create",
+                username,
+                "--email",
+                "ursula_le_guin@gmail.com",
+                "--role",
+                "admin",
+                "--password-stdin",
+            ],
+            password,
+        );
+
+        // Assert
+        assert!(
+            !output.status.success(),
+            "The admin CLI did not fail with a {}.",
+            description
+        );
+    }
+    let n_created = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "n!" FROM users WHERE email = 'ursula_le_guin@gmail.com'"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(n_created, 0);
+}
+
+#[tokio::test]
+async fn reset_passwords_replace_the_old_ones() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let new_password = Uuid::new_v4().to_string();
+
+    // Act
+    let output = app.run_admin(
+        &[
+            "user",
+            "reset-password",
+            &app.test_user.username,
+            "--password-stdin",
+        ],
+        &format!("{}\n", new_password),
+    );
+
+    // Assert
+    assert!(output.status.success(), "{:?}", output);
+    let resp = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+    helpers::assert_redirects_to(&resp, "/login");
+    let resp = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": new_password,
+        }))
+        .await;
+    helpers::assert_redirects_to(&resp, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn subscribers_can_be_listed_by_status() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await;
+
+    // Act
+    let pending = app.run_admin(
+        &["subscriber", "list", "--status", "pending_confirmation"],
+        "",
+    );
+    let confirmed = app.run_admin(&["subscriber", "list", "--status", "confirmed"], "");
+
+    // Assert
+    assert!(stdout(&pending).contains("ursula_le_guin@gmail.com"));
+    assert!(!stdout(&confirmed).contains("ursula_le_guin@gmail.com"));
+}
+
+#[tokio::test]
+async fn pending_subscribers_can_be_confirmed() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await;
+
+    // Act
+    let output = app.run_admin(&["subscriber", "confirm", "ursula_le_guin@gmail.com"], "");
+
+    // Assert
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        app.subscriber_status("ursula_le_guin@gmail.com")
+            .await
+            .as_deref(),
+        Some("confirmed")
+    );
+    let n_pending_emails =
+        sqlx::query_scalar!(r#"SELECT COUNT(*) AS "n!" FROM confirmation_email_outbox"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(n_pending_emails, 0);
+}
+
+#[tokio::test]
+async fn only_pending_subscribers_can_be_confirmed() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await;
+    sqlx::query!("UPDATE subscriptions SET status = 'bounced'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let output = app.run_admin(&["subscriber", "confirm", "ursula_le_guin@gmail.com"], "");
+
+    // Assert
+    assert!(!output.status.success());
+    assert_eq!(
+        app.subscriber_status("ursula_le_guin@gmail.com")
+            .await
+            .as_deref(),
+        Some("bounced")
+    );
+}
+
+#[tokio::test]
+async fn subscribers_are_only_removed_when_confirmed_with_yes() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await;
+
+    // Act - Part 1 - Without --yes
+    let output = app.run_admin(&["subscriber", "remove", "ursula_le_guin@gmail.com"], "");
+
+    // Assert - Part 1
+    assert!(!output.status.success());
+    assert!(app
+        .subscriber_status("ursula_le_guin@gmail.com")
+        .await
+        .is_some());
+
+    // Act - Part 2 - With --yes
+    let output = app.run_admin(
+        &["subscriber", "remove", "ursula_le_guin@gmail.com", "--yes"],
+        "",
+    );
+
+    // Assert - Part 2
+    assert!(output.status.success(), "{:?}", output);
+    assert!(app
+        .subscriber_status("ursula_le_guin@gmail.com")
+        .await
+        .is_none());
+}
+
+#[tokio::test]
+async fn queue_stats_count_the_queued_deliveries_of_each_issue() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.create_confirmed_subscriber().await;
+    let issue_id = app.publish_an_issue_and_get_its_id().await;
+
+    // Act
+    let output = app.run_admin(&["queue", "stats"], "");
+
+    // Assert
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = stdout(&output);
+    let row = stdout
+        .lines()
+        .find(|l| l.starts_with(&issue_id.to_string()))
+        .expect("The issue is missing from the stats.");
+    let columns: Vec<_> = row.split_whitespace().collect();
+    // ISSUE, TITLE (two words), QUEUED, RETRYING, FAILED, OLDEST
+    assert_eq!(&columns[3..6], ["2", "0", "0"]);
+}
+
+#[tokio::test]
+async fn queued_deliveries_can_be_purged() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.publish_an_issue_and_get_its_id().await;
+    assert_eq!(app.count_queued_deliveries().await, 1);
+
+    // Act
+    let output = app.run_admin(&["queue", "purge", "--yes"], "");
+
+    // Assert
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(app.count_queued_deliveries().await, 0);
+}
+
+#[tokio::test]
+async fn failed_deliveries_can_be_requeued() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let issue_id = app.publish_an_issue_and_get_its_id().await;
+    sqlx::query!(
+        r#"
+        INSERT INTO failed_deliveries
+            (newsletter_issue_id, subscriber_email, n_retries, last_error, failed_at)
+        VALUES ($1, 'ursula_le_guin@gmail.com', 5, 'Timed out', now())
+        "#,
+        issue_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let output = app.run_admin(&["queue", "requeue", "--issue", &issue_id.to_string()], "");
+
+    // Assert
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(app.count_queued_deliveries().await, 1);
+}
@@ -0,0 +1,197 @@
+use crate::helpers::{self, assert_api_error, TestApp, RQST_FAIL};
+use reqwest::{Method, Response};
+use serde_json::Value;
+use uuid::Uuid;
+use wiremock::{matchers, Mock};
+
+impl TestApp {
+    async fn api_create_issue(&self, key: &str, idempotency_key: &str, body: &Value) -> Response {
+        self.api_request(Method::POST, "/issues", key)
+            .header("Idempotency-Key", idempotency_key)
+            .json(body)
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+}
+
+fn issue_body() -> Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text": "Newsletter body as plain text",
+        "html": "<p>Newsletter body as HTML</p>",
+    })
+}
+
+#[tokio::test]
+async fn issues_published_through_the_api_are_delivered() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    let key = app.create_api_key().await;
+    Mock::given(matchers::path("/email/batch"))
+        .and(matchers::method("POST"))
+        .respond_with(helpers::batch_delivered)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Publish
+    let resp = app
+        .api_create_issue(&key, &Uuid::new_v4().to_string(), &issue_body())
+        .await;
+    assert_eq!(202, resp.status().as_u16());
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!("published", body["status"]);
+    let status_url = body["status_url"].as_str().unwrap();
+    assert_eq!(
+        format!("/api/v1/issues/{}", body["issue_id"].as_str().unwrap()),
+        status_url
+    );
+    app.dispatch_all_pending_emails().await;
+
+    // Act - Part 2 - Follow the delivery
+    let status: Value = app
+        .api_request(Method::GET, status_url.trim_start_matches("/api/v1"), &key)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!("Newsletter title", status["title"]);
+    assert_eq!(0, status["n_queued"]);
+    assert_eq!(1, status["n_delivered"]);
+}
+
+#[tokio::test]
+async fn retrying_with_the_same_idempotency_key_publishes_once() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    let key = app.create_api_key().await;
+    let idempotency_key = Uuid::new_v4().to_string();
+    Mock::given(matchers::path("/email/batch"))
+        .and(matchers::method("POST"))
+        .respond_with(helpers::batch_delivered)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let first = app
+        .api_create_issue(&key, &idempotency_key, &issue_body())
+        .await;
+    let second = app
+        .api_create_issue(&key, &idempotency_key, &issue_body())
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(202, second.status().as_u16());
+    assert_eq!(first.text().await.unwrap(), second.text().await.unwrap());
+    let n_issues = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "n!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(1, n_issues);
+}
+
+#[tokio::test]
+async fn the_idempotency_key_header_is_required() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+
+    // Act
+    let resp = app
+        .api_request(Method::POST, "/issues", &key)
+        .json(&issue_body())
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+    assert_api_error(&resp.json().await.unwrap(), "invalid_request");
+}
+
+#[tokio::test]
+async fn issues_can_be_scheduled_through_the_api() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    let mut body = issue_body();
+    body["scheduled_for"] = "2999-01-01T09:00:00Z".into();
+
+    // Act
+    let resp = app
+        .api_create_issue(&key, &Uuid::new_v4().to_string(), &body)
+        .await;
+
+    // Assert
+    assert_eq!(202, resp.status().as_u16());
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!("scheduled", body["status"]);
+}
+
+#[tokio::test]
+async fn past_schedules_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    let mut body = issue_body();
+    body["scheduled_for"] = "2000-01-01T09:00:00Z".into();
+
+    // Act
+    let resp = app
+        .api_create_issue(&key, &Uuid::new_v4().to_string(), &body)
+        .await;
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+    assert_api_error(&resp.json().await.unwrap(), "invalid_request");
+}
+
+#[tokio::test]
+async fn the_status_of_an_unknown_issue_is_not_found() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+
+    // Act
+    let resp = app
+        .api_request(Method::GET, &format!("/issues/{}", Uuid::new_v4()), &key)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(404, resp.status().as_u16());
+    assert_api_error(&resp.json().await.unwrap(), "not_found");
+}
+
+#[tokio::test]
+async fn issues_targeting_unknown_lists_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    let mut body = issue_body();
+    body["lists"] = serde_json::json!([Uuid::new_v4()]);
+
+    // Act
+    let resp = app
+        .api_create_issue(&key, &Uuid::new_v4().to_string(), &body)
+        .await;
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+    assert_api_error(&resp.json().await.unwrap(), "invalid_request");
+    let n_issues = sqlx::query_scalar!("SELECT COUNT(*) FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(Some(0), n_issues);
+}
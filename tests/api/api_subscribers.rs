@@ -0,0 +1,489 @@
+use crate::helpers::{assert_api_error, assert_redirects_to, TestApp, TestUser, RQST_FAIL};
+use reqwest::{Method, Response};
+use serde_json::Value;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+impl TestApp {
+    async fn get_api_keys_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/api-keys", self.base_addr))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+            .text()
+            .await
+            .unwrap()
+    }
+
+    async fn api_create_subscriber(&self, key: &str, body: &Value) -> Response {
+        self.api_request(Method::POST, "/subscribers", key)
+            .json(body)
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+}
+
+#[tokio::test]
+async fn requests_without_an_api_key_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let resp = reqwest::get(format!("{}/api/v1/subscribers", app.base_addr))
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(401, resp.status().as_u16());
+    assert_api_error(&resp.json().await.unwrap(), "unauthorized");
+}
+
+#[tokio::test]
+async fn requests_with_an_unknown_api_key_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let resp = app
+        .api_request(Method::GET, "/subscribers", "not-a-key")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(401, resp.status().as_u16());
+    assert_api_error(&resp.json().await.unwrap(), "unauthorized");
+}
+
+#[tokio::test]
+async fn revoked_api_keys_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    let key_id = sqlx::query_scalar!("SELECT id FROM api_keys")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act - Part 1 - Revoke the key
+    let resp = app
+        .api_client
+        .post(format!(
+            "{}/admin/api-keys/{}/revoke",
+            app.base_addr, key_id
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_redirects_to(&resp, "/admin/api-keys");
+    assert!(app
+        .get_api_keys_html()
+        .await
+        .contains("<p><i>The API key has been revoked.</i></p>"));
+
+    // Act - Part 2 - Use it
+    let resp = app
+        .api_request(Method::GET, "/subscribers", &key)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(401, resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn api_keys_stop_working_once_their_creator_is_no_longer_an_admin() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    sqlx::query!(
+        "UPDATE users SET role = 'viewer' WHERE id = $1",
+        app.test_user.id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let resp = app
+        .api_request(Method::GET, "/subscribers", &key)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(401, resp.status().as_u16());
+    assert_api_error(&resp.json().await.unwrap(), "unauthorized");
+}
+
+#[tokio::test]
+async fn only_admins_can_create_api_keys() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let editor = TestUser::generate_with_role("editor");
+    editor.store(&app.db_pool).await;
+    app.login_as(&editor).await;
+
+    // Act
+    let resp = app.post_api_key("CRM sync").await;
+
+    // Assert
+    assert_eq!(403, resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn api_keys_are_stored_hashed_and_listed_with_their_last_use() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+
+    // Act
+    app.api_request(Method::GET, "/subscribers", &key)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    let r = sqlx::query!("SELECT key_hash, last_used_at FROM api_keys")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_ne!(key, r.key_hash);
+    let last_used_at = r.last_used_at.unwrap().to_rfc3339();
+    let html = app.get_api_keys_html().await;
+    assert!(html.contains("CRM sync"));
+    assert!(html.contains(&last_used_at));
+}
+
+#[tokio::test]
+async fn creating_a_subscriber_sends_a_confirmation_email() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let resp = app
+        .api_create_subscriber(
+            &key,
+            &serde_json::json!({ "email": "ursula@example.com", "name": "Ursula" }),
+        )
+        .await;
+    app.send_all_confirmation_emails().await;
+
+    // Assert
+    assert_eq!(201, resp.status().as_u16());
+    let location = resp.headers()["Location"].to_str().unwrap().to_owned();
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!("pending_confirmation", body["status"]);
+    assert_eq!(
+        format!("/api/v1/subscribers/{}", body["id"].as_str().unwrap()),
+        location
+    );
+}
+
+#[tokio::test]
+async fn subscribers_created_as_confirmed_get_no_confirmation_email() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    Mock::given(matchers::any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let resp = app
+        .api_create_subscriber(
+            &key,
+            &serde_json::json!({
+                "email": "ursula@example.com",
+                "name": "Ursula",
+                "confirmed": true,
+            }),
+        )
+        .await;
+    app.send_all_confirmation_emails().await;
+
+    // Assert
+    assert_eq!(201, resp.status().as_u16());
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!("confirmed", body["status"]);
+}
+
+#[tokio::test]
+async fn creating_an_existing_subscriber_is_a_conflict() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    let body = serde_json::json!({
+        "email": "ursula@example.com",
+        "name": "Ursula",
+        "confirmed": true,
+    });
+    app.api_create_subscriber(&key, &body).await;
+
+    // Act
+    let resp = app.api_create_subscriber(&key, &body).await;
+
+    // Assert
+    assert_eq!(409, resp.status().as_u16());
+    assert_api_error(&resp.json().await.unwrap(), "conflict");
+}
+
+#[tokio::test]
+async fn invalid_subscribers_are_rejected_with_a_json_error() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    let test_cases = [
+        (
+            serde_json::json!({ "email": "not-an-email", "name": "Ursula" }),
+            "an invalid email",
+        ),
+        (
+            serde_json::json!({ "email": "ursula@example.com", "name": "" }),
+            "an empty name",
+        ),
+        (serde_json::json!({ "name": "Ursula" }), "a missing email"),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let resp = app.api_create_subscriber(&key, &body).await;
+
+        // Assert
+        assert_eq!(
+            400,
+            resp.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            description
+        );
+        assert_api_error(&resp.json().await.unwrap(), "invalid_request");
+    }
+}
+
+#[tokio::test]
+async fn subscribers_can_be_filtered_by_status_and_searched() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    for (email, name) in [
+        ("ursula@example.com", "Ursula Le Guin"),
+        ("octavia@example.com", "Octavia Butler"),
+        ("ted@example.com", "Ted Chiang"),
+    ] {
+        app.api_create_subscriber(
+            &key,
+            &serde_json::json!({ "email": email, "name": name, "confirmed": true }),
+        )
+        .await;
+    }
+    let id = sqlx::query_scalar!("SELECT id FROM subscriptions WHERE email = 'ted@example.com'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    app.api_request(
+        Method::POST,
+        &format!("/subscribers/{}/unsubscribe", id),
+        &key,
+    )
+    .send()
+    .await
+    .unwrap();
+
+    // Act
+    let confirmed: Value = app
+        .api_request(Method::GET, "/subscribers?status=confirmed", &key)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let searched: Value = app
+        .api_request(Method::GET, "/subscribers?search=BUTLER", &key)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(2, confirmed["total"]);
+    assert_eq!(1, searched["total"]);
+    assert_eq!("octavia@example.com", searched["subscribers"][0]["email"]);
+}
+
+#[tokio::test]
+async fn unknown_statuses_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+
+    // Act
+    let resp = app
+        .api_request(Method::GET, "/subscribers?status=gone", &key)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+    assert_api_error(&resp.json().await.unwrap(), "invalid_request");
+}
+
+#[tokio::test]
+async fn out_of_range_pages_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+
+    // Act
+    let resp = app
+        .api_request(
+            Method::GET,
+            &format!("/subscribers?page={}", i64::MAX),
+            &key,
+        )
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+    assert_api_error(&resp.json().await.unwrap(), "invalid_request");
+}
+
+#[tokio::test]
+async fn pending_subscribers_can_be_confirmed() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    Mock::given(matchers::any())
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let created: Value = app
+        .api_create_subscriber(
+            &key,
+            &serde_json::json!({ "email": "ursula@example.com", "name": "Ursula" }),
+        )
+        .await
+        .json()
+        .await
+        .unwrap();
+    let id = created["id"].as_str().unwrap();
+
+    // Act
+    let resp = app
+        .api_request(Method::POST, &format!("/subscribers/{}/confirm", id), &key)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(200, resp.status().as_u16());
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!("confirmed", body["status"]);
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_cannot_be_confirmed() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    let created: Value = app
+        .api_create_subscriber(
+            &key,
+            &serde_json::json!({ "email": "ursula@example.com", "name": "Ursula", "confirmed": true }),
+        )
+        .await
+        .json()
+        .await
+        .unwrap();
+    let id = created["id"].as_str().unwrap();
+    app.api_request(
+        Method::POST,
+        &format!("/subscribers/{}/unsubscribe", id),
+        &key,
+    )
+    .send()
+    .await
+    .unwrap();
+
+    // Act
+    let resp = app
+        .api_request(Method::POST, &format!("/subscribers/{}/confirm", id), &key)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(409, resp.status().as_u16());
+    assert_eq!(
+        Some("unsubscribed"),
+        app.subscriber_status("ursula@example.com").await.as_deref()
+    );
+}
+
+#[tokio::test]
+async fn deleted_subscribers_are_gone() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    let created: Value = app
+        .api_create_subscriber(
+            &key,
+            &serde_json::json!({ "email": "ursula@example.com", "name": "Ursula", "confirmed": true }),
+        )
+        .await
+        .json()
+        .await
+        .unwrap();
+    let path = format!("/subscribers/{}", created["id"].as_str().unwrap());
+
+    // Act
+    let resp = app
+        .api_request(Method::DELETE, &path, &key)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(204, resp.status().as_u16());
+    let resp = app
+        .api_request(Method::GET, &path, &key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(404, resp.status().as_u16());
+    assert_api_error(&resp.json().await.unwrap(), "not_found");
+}
+
+#[tokio::test]
+async fn malformed_ids_are_rejected_with_a_json_error() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+
+    // Act
+    let resp = app
+        .api_request(Method::GET, "/subscribers/not-a-uuid", &key)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+    assert_api_error(&resp.json().await.unwrap(), "invalid_request");
+}
@@ -0,0 +1,240 @@
+use crate::helpers::{self, TestApp};
+use wiremock::{matchers, Mock, MockBuilder, Request, ResponseTemplate};
+
+fn when_sending_an_issue() -> MockBuilder {
+    Mock::given(matchers::path("/email/batch")).and(matchers::method("POST"))
+}
+
+impl TestApp {
+    async fn publish_an_issue(&self) {
+        let resp = self
+            .post_newsletters(&serde_json::json!({
+                "title": "Newsletter title",
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+                "idempotency_key": uuid::Uuid::new_v4(),
+            }))
+            .await;
+        helpers::assert_redirects_to(&resp, "/admin/newsletters");
+    }
+
+    async fn count_failed_deliveries(&self) -> i64 {
+        sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM failed_deliveries"#)
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+            .count
+    }
+}
+
+#[tokio::test]
+async fn transient_delivery_failures_are_retried() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+
+    when_sending_an_issue()
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    when_sending_an_issue()
+        .respond_with(helpers::batch_delivered)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.publish_an_issue().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(0, app.count_failed_deliveries().await);
+}
+
+#[tokio::test]
+async fn only_the_failed_emails_of_a_batch_are_retried() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+
+    when_sending_an_issue()
+        .respond_with(|r: &Request| helpers::batch_delivered_except(r, &[1]))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    when_sending_an_issue()
+        .respond_with(helpers::batch_delivered)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.publish_an_issue().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let requests = app.email_server.received_requests().await.unwrap();
+    let retried: Vec<serde_json::Value> = requests.last().unwrap().body_json().unwrap();
+    assert_eq!(1, retried.len());
+    assert_eq!(0, app.count_failed_deliveries().await);
+}
+
+#[tokio::test]
+async fn deliveries_exhausting_their_retries_are_dead_lettered() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+
+    // The first attempt plus two retries
+    when_sending_an_issue()
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.publish_an_issue().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(1, app.count_failed_deliveries().await);
+    let html = app.get_failed_deliveries_html().await;
+    assert!(html.contains("Newsletter title"));
+}
+
+#[tokio::test]
+async fn failed_deliveries_can_be_requeued() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+
+    when_sending_an_issue()
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(3)
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+    when_sending_an_issue()
+        .respond_with(helpers::batch_delivered)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_an_issue().await;
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(1, app.count_failed_deliveries().await);
+
+    // Act 1: Re-queue every failed delivery
+    let resp = app
+        .post_requeue_failed_deliveries(&serde_json::json!({}))
+        .await;
+    helpers::assert_redirects_to(&resp, "/admin/deliveries/failed");
+
+    // Act 2: Follow the redirect
+    let html = app.get_failed_deliveries_html().await;
+    assert!(html.contains("<p><i>1 failed deliveries have been re-queued.</i></p>"));
+
+    // Assert
+    assert_eq!(0, app.count_failed_deliveries().await);
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_failed_deliveries() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let resp = app
+        .api_client
+        .get(format!("{}/admin/deliveries/failed", app.base_addr))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    helpers::assert_redirects_to(&resp, "/login");
+}
+
+#[tokio::test]
+async fn failed_deliveries_can_be_requeued_for_a_single_subscriber() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    app.publish_an_issue().await;
+    for email in ["ursula_le_guin@gmail.com", "octavia_butler@gmail.com"] {
+        sqlx::query!(
+            r#"
+            INSERT INTO failed_deliveries
+                (newsletter_issue_id, subscriber_email, n_retries, last_error, failed_at)
+            SELECT id, $1, 3, 'Timed out', now()
+            FROM newsletter_issues
+            "#,
+            email
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+
+    // Act
+    let resp = app
+        .post_requeue_failed_deliveries(&serde_json::json!({
+            "subscriber_email": "ursula_le_guin@gmail.com",
+        }))
+        .await;
+
+    // Assert
+    helpers::assert_redirects_to(&resp, "/admin/deliveries/failed");
+    let remaining = sqlx::query_scalar!("SELECT subscriber_email FROM failed_deliveries")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(vec!["octavia_butler@gmail.com"], remaining);
+}
+
+#[tokio::test]
+async fn failed_deliveries_already_queued_again_are_kept() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    app.publish_an_issue().await;
+    sqlx::query!(
+        r#"
+        INSERT INTO failed_deliveries
+            (newsletter_issue_id, subscriber_email, n_retries, last_error, failed_at)
+        SELECT id, 'ursula_le_guin@gmail.com', 3, 'Timed out', now()
+        FROM newsletter_issues
+        "#
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email)
+        SELECT id, 'ursula_le_guin@gmail.com'
+        FROM newsletter_issues
+        "#
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    app.post_requeue_failed_deliveries(&serde_json::json!({}))
+        .await;
+
+    // Assert
+    let html = app.get_failed_deliveries_html().await;
+    assert!(html.contains("<p><i>0 failed deliveries have been re-queued.</i></p>"));
+    assert_eq!(1, app.count_failed_deliveries().await);
+}
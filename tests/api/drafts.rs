@@ -0,0 +1,153 @@
+use crate::helpers::{self, assert_redirects_to, TestApp};
+use uuid::Uuid;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+impl TestApp {
+    async fn save_a_draft(&self) -> Uuid {
+        let resp = self
+            .post_newsletters(&serde_json::json!({
+                "title": "Draft issue",
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+                "idempotency_key": Uuid::new_v4(),
+                "draft": true,
+            }))
+            .await;
+        let issue_id = sqlx::query!("SELECT id FROM newsletter_issues WHERE title = 'Draft issue'")
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+            .id;
+        assert_redirects_to(&resp, &format!("/admin/newsletters/issues/{issue_id}"));
+        issue_id
+    }
+}
+
+#[tokio::test]
+async fn drafts_are_not_delivered() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+
+    Mock::given(matchers::any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let issue_id = app.save_a_draft().await;
+    app.publish_all_due_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let html = app.get_admin_issue(issue_id).await.text().await.unwrap();
+    assert!(html.contains("The newsletter issue has been saved as a draft."));
+    assert_eq!(0, app.count_queued_deliveries().await);
+    assert_eq!(404, app.get_web_issue(issue_id).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn the_preview_shows_both_versions_of_the_issue() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let issue_id = app.save_a_draft().await;
+
+    // Act
+    let html = app.get_issue_preview_html(issue_id).await;
+
+    // Assert
+    assert!(html.contains("<p>Newsletter body as HTML</p>"));
+    assert!(html.contains("<pre>Newsletter body as plain text</pre>"));
+}
+
+#[tokio::test]
+async fn a_test_send_only_mails_the_given_address() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+    let issue_id = app.save_a_draft().await;
+
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let resp = app
+        .post_test_issue(
+            issue_id,
+            &serde_json::json!({ "email": "editor@example.com" }),
+        )
+        .await;
+
+    // Assert
+    assert_redirects_to(&resp, &format!("/admin/newsletters/issues/{issue_id}"));
+    let received_requests = app.email_server.received_requests().await.unwrap();
+    let email_request = received_requests.last().unwrap();
+    let body: serde_json::Value = email_request.body_json().unwrap();
+    assert_eq!("editor@example.com", body["To"]);
+    assert_eq!("[Test] Draft issue", body["Subject"]);
+    assert_eq!(0, app.count_queued_deliveries().await);
+
+    let html = app.get_admin_issue(issue_id).await.text().await.unwrap();
+    assert!(html.contains("A test email has been sent to editor@example.com."));
+}
+
+#[tokio::test]
+async fn a_test_send_to_an_invalid_address_is_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let issue_id = app.save_a_draft().await;
+
+    Mock::given(matchers::any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_test_issue(issue_id, &serde_json::json!({ "email": "not-an-email" }))
+        .await;
+
+    // Assert
+    let html = app.get_admin_issue(issue_id).await.text().await.unwrap();
+    assert!(html.contains("not-an-email is not a valid subscriber email."));
+}
+
+#[tokio::test]
+async fn published_drafts_are_delivered_to_every_subscriber() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+    let issue_id = app.save_a_draft().await;
+
+    Mock::given(matchers::path("/email/batch"))
+        .respond_with(helpers::batch_delivered)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Publish the draft
+    let resp = app
+        .post_publish_draft(issue_id, &serde_json::json!({ "scheduled_for": "" }))
+        .await;
+    assert_redirects_to(&resp, &format!("/admin/newsletters/issues/{issue_id}"));
+    app.dispatch_all_pending_emails().await;
+
+    // Act - Part 2 - Publish it again
+    app.post_publish_draft(issue_id, &serde_json::json!({ "scheduled_for": "" }))
+        .await;
+
+    // Assert
+    let html = app.get_admin_issue(issue_id).await.text().await.unwrap();
+    assert!(html.contains("Only drafts can be published."));
+    assert_eq!(200, app.get_web_issue(issue_id).await.status().as_u16());
+}
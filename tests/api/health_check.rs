@@ -1,5 +1,11 @@
 use crate::helpers::*;
 use reqwest::Client;
+use uuid::Uuid;
+use zero2prod::{
+    app::App,
+    config,
+    workers::{publishing, record_heartbeat, WORKER_NAMES},
+};
 
 #[tokio::test]
 async fn health_check_works() {
@@ -18,3 +24,156 @@ async fn health_check_works() {
     assert!(response.status().is_success());
     assert_eq!(Some(0), response.content_length())
 }
+
+impl TestApp {
+    async fn get_readiness(&self) -> (u16, serde_json::Value) {
+        let resp = Client::new()
+            .get(format!("{}/health/ready", self.base_addr))
+            .send()
+            .await
+            .expect(RQST_FAIL);
+        (resp.status().as_u16(), resp.json().await.unwrap())
+    }
+
+    async fn beat_every_worker(&self) {
+        for worker in WORKER_NAMES {
+            record_heartbeat(worker, &self.db_pool).await.unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn readiness_succeeds_when_dependencies_and_workers_are_up() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.beat_every_worker().await;
+
+    // Act
+    let (status, body) = app.get_readiness().await;
+
+    // Assert
+    assert_eq!(200, status);
+    assert_eq!(true, body["ready"]);
+    assert_eq!(true, body["postgres"]["healthy"]);
+    assert_eq!(true, body["redis"]["healthy"]);
+    for worker in WORKER_NAMES {
+        assert_eq!(true, body["workers"][worker]["healthy"]);
+        assert!(body["workers"][worker]["last_heartbeat"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn readiness_fails_until_every_worker_has_reported() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    record_heartbeat(WORKER_NAMES[0], &app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let (status, body) = app.get_readiness().await;
+
+    // Assert
+    assert_eq!(503, status);
+    assert_eq!(false, body["ready"]);
+    assert_eq!(true, body["postgres"]["healthy"]);
+    assert_eq!(true, body["workers"][WORKER_NAMES[0]]["healthy"]);
+    assert_eq!(false, body["workers"][WORKER_NAMES[1]]["healthy"]);
+    assert!(body["workers"][WORKER_NAMES[1]]["error"].is_string());
+}
+
+#[tokio::test]
+async fn readiness_fails_when_a_worker_has_stopped_beating() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.beat_every_worker().await;
+    sqlx::query!(
+        "UPDATE worker_heartbeats SET beat_at = now() - interval '1 hour' WHERE worker = $1",
+        WORKER_NAMES[2]
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let (status, body) = app.get_readiness().await;
+
+    // Assert
+    assert_eq!(503, status);
+    assert_eq!(false, body["workers"][WORKER_NAMES[2]]["healthy"]);
+}
+
+#[tokio::test]
+async fn heartbeats_from_other_processes_do_not_count() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    for worker in WORKER_NAMES {
+        sqlx::query!(
+            "INSERT INTO worker_heartbeats (worker, instance, beat_at) VALUES ($1, $2, now())",
+            worker,
+            Uuid::new_v4()
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+
+    // Act
+    let (status, body) = app.get_readiness().await;
+
+    // Assert
+    assert_eq!(503, status);
+    for worker in WORKER_NAMES {
+        assert_eq!(false, body["workers"][worker]["healthy"]);
+    }
+}
+
+#[tokio::test]
+async fn worker_processes_serve_their_own_readiness_and_metrics() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let mut config = config::get().expect("Failed to read configuration");
+    config.database.name = app
+        .db_pool
+        .connect_options()
+        .get_database()
+        .unwrap()
+        .to_owned();
+    config.metrics.port = 0;
+    let monitoring = App::build_monitoring(&config, &[publishing::NAME]).unwrap();
+    let addr = format!("http://{}", monitoring.addr());
+    tokio::spawn(monitoring.run_until_stopped());
+    record_heartbeat(publishing::NAME, &app.db_pool)
+        .await
+        .unwrap();
+    let client = Client::new();
+
+    // Act - Part 1 - Readiness
+    let resp = client
+        .get(format!("{}/health/ready", addr))
+        .send()
+        .await
+        .expect(RQST_FAIL);
+
+    // Assert
+    assert_eq!(200, resp.status().as_u16());
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(1, body["workers"].as_object().unwrap().len());
+    assert_eq!(true, body["workers"][publishing::NAME]["healthy"]);
+
+    // Act - Part 2 - Metrics
+    let resp = client
+        .get(format!("{}/metrics", addr))
+        .bearer_auth("my-metrics-token")
+        .send()
+        .await
+        .expect(RQST_FAIL);
+
+    // Assert
+    assert_eq!(200, resp.status().as_u16());
+    assert!(resp
+        .text()
+        .await
+        .unwrap()
+        .contains("issue_delivery_queue_depth"));
+}
@@ -4,7 +4,8 @@ use fake::{
     Fake,
 };
 use linkify::{LinkFinder, LinkKind};
-use reqwest::{Body, Response, Url};
+use reqwest::{Body, Method, RequestBuilder, Response, Url};
+use serde_json::Value;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use std::{env, io, net::SocketAddr, sync::LazyLock};
 use uuid::Uuid;
@@ -12,10 +13,9 @@ use wiremock::{matchers, Mock, ResponseTemplate};
 use wiremock::{MockServer, Request};
 use zero2prod::{
     app::App,
-    config::{self, DatabaseSettings},
-    email_client::EmailClient,
+    config::{self, DatabaseSettings, EmailTransportKind},
     telemetry,
-    workers::issue_delivery,
+    workers::{confirmation_email, issue_delivery, publishing, WORKER_NAMES},
 };
 
 const DB_CONNECTION_FAIL: &str = "Failed to connect to Postgres";
@@ -35,15 +35,23 @@ pub struct ConfirmationLinks {
 pub struct TestUser {
     pub id: Uuid,
     pub username: String,
+    pub email: String,
     pub password: String,
+    pub role: &'static str,
 }
 
 impl TestUser {
     pub fn generate() -> Self {
+        Self::generate_with_role("admin")
+    }
+
+    pub fn generate_with_role(role: &'static str) -> Self {
         Self {
             id: Uuid::new_v4(),
             username: Uuid::new_v4().to_string(),
+            email: SafeEmail().fake(),
             password: Uuid::new_v4().to_string(),
+            role,
         }
     }
 
@@ -64,12 +72,14 @@ impl TestUser {
         };
         sqlx::query!(
             r#"
-            INSERT INTO users (id, username, password_hash)
-            VALUES ($1, $2, $3)
+            INSERT INTO users (id, username, email, password_hash, role)
+            VALUES ($1, $2, $3, $4, $5)
             "#,
             self.id,
             self.username,
-            password_hash
+            self.email,
+            password_hash,
+            self.role
         )
         .execute(pool)
         .await
@@ -83,7 +93,9 @@ pub struct TestApp {
     pub socket_addr: SocketAddr,
     pub db_pool: PgPool,
     pub email_server: MockServer,
-    pub email_client: EmailClient,
+    pub delivery_worker: issue_delivery::Worker,
+    pub confirmation_worker: confirmation_email::Worker,
+    pub publishing_worker: publishing::Worker,
     pub test_user: TestUser,
     pub api_client: reqwest::Client,
 }
@@ -106,14 +118,23 @@ impl TestApp {
             raw.application.port = 0;
 
             // Replace the email server
-            raw.email_client.base_url = email_server.uri();
+            raw.email_client.transport = EmailTransportKind::Postmark;
+            raw.email_client.postmark.base_url = email_server.uri();
+
+            // Keep login failures from leaking across test cases and runs
+            raw.login_throttle.key_prefix = format!("test_{}", Uuid::new_v4());
+
+            // Retry failed deliveries right away
+            raw.issue_delivery.max_retries = 2;
+            raw.issue_delivery.initial_backoff_ms = 0;
+            raw.issue_delivery.max_backoff_ms = 0;
 
             raw
         };
 
         // Create the database and application
         Self::init_db(&config.database).await;
-        let app = App::build(&config)
+        let app = App::build(&config, &WORKER_NAMES)
             .await
             .expect("Failed to build application.");
         let socket_addr = app.addr();
@@ -130,7 +151,9 @@ impl TestApp {
         let test_app = TestApp {
             db_pool: config.database.get_db_pool(),
             base_addr,
-            email_client: config.email_client.client(),
+            delivery_worker: issue_delivery::Worker::builder(&config),
+            confirmation_worker: confirmation_email::Worker::builder(&config),
+            publishing_worker: publishing::Worker::builder(&config),
             email_server,
             socket_addr,
             test_user: TestUser::generate(),
@@ -247,6 +270,32 @@ impl TestApp {
         ConfirmationLinks { html, text }
     }
 
+    /// Extract the one-click unsubscribe link from the headers of the first email in a request
+    /// to the batch email API.
+    pub fn get_unsubscribe_link(&self, email_request: &Request) -> Url {
+        let body: serde_json::Value = email_request.body_json().unwrap();
+        let header = |name: &str| {
+            body[0]["Headers"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|h| h["Name"] == name)
+                .and_then(|h| h["Value"].as_str())
+                .unwrap()
+                .to_owned()
+        };
+        assert_eq!(
+            header("List-Unsubscribe-Post"),
+            "List-Unsubscribe=One-Click"
+        );
+
+        let raw = header("List-Unsubscribe");
+        let mut url = Url::parse(raw.trim_start_matches('<').trim_end_matches('>')).unwrap();
+        assert_eq!(url.host_str().unwrap(), "127.0.0.1");
+        url.set_port(Some(self.socket_addr.port())).unwrap();
+        url
+    }
+
     pub async fn post_login<T>(&self, body: &T) -> Response
     where
         T: serde::Serialize + ?Sized,
@@ -271,10 +320,14 @@ impl TestApp {
     }
 
     pub async fn login_as_test_user(&self) {
+        self.login_as(&self.test_user).await;
+    }
+
+    pub async fn login_as(&self, user: &TestUser) {
         let resp = self
             .post_login(&serde_json::json!({
-                    "username": &self.test_user.username,
-                    "password": &self.test_user.password,
+                    "username": &user.username,
+                    "password": &user.password,
             }))
             .await;
         assert_redirects_to(&resp, "/admin/dashboard");
@@ -316,6 +369,188 @@ impl TestApp {
             .expect(RQST_FAIL)
     }
 
+    pub async fn get_failed_deliveries_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/deliveries/failed", self.base_addr))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_requeue_failed_deliveries<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize + ?Sized,
+    {
+        self.api_client
+            .post(format!(
+                "{}/admin/deliveries/failed/requeue",
+                self.base_addr
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    pub async fn get_admin_issues(&self, page: i64) -> Response {
+        self.api_client
+            .get(format!("{}/admin/newsletters/issues", self.base_addr))
+            .query(&[("page", page)])
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    pub async fn get_admin_issue(&self, issue_id: Uuid) -> Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/issues/{}",
+                self.base_addr, issue_id
+            ))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    pub async fn get_issue_preview_html(&self, issue_id: Uuid) -> String {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/issues/{}/preview",
+                self.base_addr, issue_id
+            ))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_test_issue<Body>(&self, issue_id: Uuid, body: &Body) -> Response
+    where
+        Body: serde::Serialize + ?Sized,
+    {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/issues/{}/test",
+                self.base_addr, issue_id
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    pub async fn post_publish_draft<Body>(&self, issue_id: Uuid, body: &Body) -> Response
+    where
+        Body: serde::Serialize + ?Sized,
+    {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/issues/{}/publish",
+                self.base_addr, issue_id
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    pub async fn post_reschedule_issue<Body>(&self, issue_id: Uuid, body: &Body) -> Response
+    where
+        Body: serde::Serialize + ?Sized,
+    {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/issues/{}/reschedule",
+                self.base_addr, issue_id
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    pub async fn post_cancel_issue(&self, issue_id: Uuid) -> Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/issues/{}/cancel",
+                self.base_addr, issue_id
+            ))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    pub async fn get_web_issue(&self, issue_id: Uuid) -> Response {
+        self.api_client
+            .get(format!("{}/issues/{}", self.base_addr, issue_id))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    pub async fn get_users_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/users", self.base_addr))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_invitations<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize + ?Sized,
+    {
+        self.api_client
+            .post(format!("{}/admin/users/invitations", self.base_addr))
+            .form(body)
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    pub async fn post_accept_invitation<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize + ?Sized,
+    {
+        self.api_client
+            .post(format!("{}/invitations/accept", self.base_addr))
+            .form(body)
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    pub async fn get_security_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/security", self.base_addr))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_security<Body>(&self, path: &str, body: &Body) -> Response
+    where
+        Body: serde::Serialize + ?Sized,
+    {
+        self.api_client
+            .post(format!("{}/admin/security{}", self.base_addr, path))
+            .form(body)
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
     pub async fn post_logout(&self) -> Response {
         self.api_client
             .post(format!("{}/admin/logout", self.base_addr))
@@ -324,6 +559,52 @@ impl TestApp {
             .expect(RQST_FAIL)
     }
 
+    pub async fn post_api_key(&self, name: &str) -> Response {
+        self.api_client
+            .post(format!("{}/admin/api-keys", self.base_addr))
+            .form(&serde_json::json!({ "name": name }))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    /// The status of the subscriber with this email, if there is one.
+    pub async fn subscriber_status(&self, email: &str) -> Option<String> {
+        sqlx::query_scalar!("SELECT status FROM subscriptions WHERE email = $1", email)
+            .fetch_optional(&self.db_pool)
+            .await
+            .unwrap()
+    }
+
+    pub async fn count_queued_deliveries(&self) -> i64 {
+        sqlx::query_scalar!(r#"SELECT COUNT(*) AS "n!" FROM issue_delivery_queue"#)
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+    }
+
+    /// Creates an API key from the dashboard as the test user and returns it.
+    pub async fn create_api_key(&self) -> String {
+        self.login_as_test_user().await;
+        let resp = self.post_api_key("CRM sync").await;
+        assert_eq!(200, resp.status().as_u16());
+        let html = resp.text().await.unwrap();
+        html.split("<code>")
+            .nth(1)
+            .unwrap()
+            .split("</code>")
+            .next()
+            .unwrap()
+            .to_owned()
+    }
+
+    /// A request to the JSON API, sent without the session cookie.
+    pub fn api_request(&self, method: Method, path: &str, key: &str) -> RequestBuilder {
+        reqwest::Client::new()
+            .request(method, format!("{}/api/v1{}", self.base_addr, path))
+            .bearer_auth(key)
+    }
+
     pub async fn create_unconfirmed_subscriber(&self) -> ConfirmationLinks {
         let body = {
             let name: String = Name().fake();
@@ -348,6 +629,7 @@ impl TestApp {
             .await
             .error_for_status()
             .unwrap();
+        self.send_all_confirmation_emails().await;
 
         let email_request = &self
             .email_server
@@ -372,9 +654,84 @@ impl TestApp {
 
     pub async fn dispatch_all_pending_emails(&self) {
         while let Ok(issue_delivery::ExecutionOutcome::TaskCompleted) =
-            issue_delivery::try_execute_task(&self.db_pool, &self.email_client).await
+            self.delivery_worker.try_execute_task().await
         {}
     }
+
+    pub async fn send_all_confirmation_emails(&self) {
+        while let Ok(issue_delivery::ExecutionOutcome::TaskCompleted) =
+            self.confirmation_worker.try_execute_task().await
+        {}
+    }
+
+    /// Publishes an issue through the admin form, narrowing its audience with the given fields,
+    /// and returns the recipients of its delivery.
+    pub async fn publish_and_get_recipients(&self, audience: &[(&str, String)]) -> Vec<String> {
+        let _mock_guard = Mock::given(matchers::path("/email/batch"))
+            .respond_with(batch_delivered)
+            .mount_as_scoped(&self.email_server)
+            .await;
+        let n_before = self.email_server.received_requests().await.unwrap().len();
+        let mut form = vec![
+            ("title", "Newsletter title".to_string()),
+            ("text", "Newsletter body as plain text".to_string()),
+            ("html", "<p>Newsletter body as HTML</p>".to_string()),
+            ("idempotency_key", Uuid::new_v4().to_string()),
+        ];
+        form.extend(audience.iter().cloned());
+        let resp = self.post_newsletters(&form).await;
+        assert_redirects_to(&resp, "/admin/newsletters");
+        self.dispatch_all_pending_emails().await;
+
+        let mut recipients: Vec<String> = self
+            .email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .skip(n_before)
+            .filter(|r| r.url.path() == "/email/batch")
+            .flat_map(|r| {
+                let body: serde_json::Value = r.body_json().unwrap();
+                body.as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|e| e["To"].as_str().unwrap().to_owned())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        recipients.sort();
+        recipients
+    }
+
+    pub async fn publish_all_due_issues(&self) {
+        while let Ok(issue_delivery::ExecutionOutcome::TaskCompleted) =
+            self.publishing_worker.try_execute_task().await
+        {}
+    }
+}
+
+/// Replies to a request to the batch email API, reporting every email as delivered.
+pub fn batch_delivered(request: &Request) -> ResponseTemplate {
+    batch_delivered_except(request, &[])
+}
+
+/// Replies to a request to the batch email API, reporting the emails at the given positions
+/// as failed.
+pub fn batch_delivered_except(request: &Request, failed: &[usize]) -> ResponseTemplate {
+    let emails: Vec<serde_json::Value> = request.body_json().unwrap();
+    let results: Vec<_> = (0..emails.len())
+        .map(|i| match failed.contains(&i) {
+            false => serde_json::json!({ "ErrorCode": 0, "Message": "OK" }),
+            true => serde_json::json!({ "ErrorCode": 406, "Message": "Inactive recipient" }),
+        })
+        .collect();
+    ResponseTemplate::new(200).set_body_json(results)
+}
+
+pub fn assert_api_error(body: &Value, code: &str) {
+    assert_eq!(code, body["error"]["code"]);
+    assert!(body["error"]["message"].is_string());
 }
 
 pub fn assert_redirects_to(resp: &Response, location: &str) {
@@ -0,0 +1,262 @@
+use crate::helpers::{assert_redirects_to, TestApp, TestUser};
+use reqwest::Url;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+impl TestApp {
+    /// Invites a collaborator as the test user and returns the link from the invitation email.
+    async fn invite(&self, email: &str, role: &str) -> Url {
+        let _guard = Mock::given(matchers::path("/email"))
+            .and(matchers::method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount_as_scoped(&self.email_server)
+            .await;
+
+        let resp = self
+            .post_invitations(&serde_json::json!({ "email": email, "role": role }))
+            .await;
+        assert_redirects_to(&resp, "/admin/users");
+
+        let received_requests = self.email_server.received_requests().await.unwrap();
+        self.get_confirmation_links(received_requests.last().unwrap())
+            .html
+    }
+
+    async fn accept(&self, link: &Url, username: &str, password: &str) -> reqwest::Response {
+        let token = link
+            .query_pairs()
+            .find(|(k, _)| k == "token")
+            .unwrap()
+            .1
+            .into_owned();
+        self.post_accept_invitation(&serde_json::json!({
+            "token": token,
+            "username": username,
+            "password": password,
+            "password_check": password,
+        }))
+        .await
+    }
+}
+
+#[tokio::test]
+async fn an_invited_collaborator_can_create_an_account_and_log_in() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let link = app.invite("editor@example.com", "editor").await;
+    app.post_logout().await;
+    let invitee = TestUser::generate_with_role("editor");
+
+    // Act - Part 1 - Open the invitation
+    let resp = reqwest::get(link.clone()).await.unwrap();
+    assert_eq!(200, resp.status().as_u16());
+    assert!(resp.text().await.unwrap().contains("editor@example.com"));
+
+    // Act - Part 2 - Create the account
+    let resp = app
+        .accept(&link, &invitee.username, &invitee.password)
+        .await;
+    assert_redirects_to(&resp, "/login");
+
+    // Act - Part 3 - Log in
+    app.login_as(&invitee).await;
+
+    // Assert
+    let user = sqlx::query!(
+        "SELECT email, role FROM users WHERE username = $1",
+        invitee.username
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(Some("editor@example.com".into()), user.email);
+    assert_eq!("editor", user.role);
+}
+
+#[tokio::test]
+async fn invitations_can_only_be_used_once() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let link = app.invite("viewer@example.com", "viewer").await;
+    app.post_logout().await;
+    app.accept(&link, "first", "a-long-enough-password").await;
+
+    // Act
+    let resp = app.accept(&link, "second", "a-long-enough-password").await;
+
+    // Assert
+    assert_eq!(401, resp.status().as_u16());
+    assert_eq!(401, reqwest::get(link).await.unwrap().status().as_u16());
+}
+
+#[tokio::test]
+async fn a_malformed_invitation_token_is_rejected_with_a_400() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let resp = reqwest::get(format!(
+        "{}/invitations/accept?token=not-a-token",
+        app.base_addr
+    ))
+    .await
+    .unwrap();
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn an_invalid_password_sends_the_invitee_back_to_the_form() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let link = app.invite("viewer@example.com", "viewer").await;
+
+    // Act - Part 1 - Submit a password that is too short
+    let resp = app.accept(&link, "invitee", "short").await;
+    assert_redirects_to(
+        &resp,
+        &format!("/{}?{}", "invitations/accept", link.query().unwrap()),
+    );
+
+    // Act - Part 2 - Follow the redirect
+    let html = app
+        .api_client
+        .get(link.clone())
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html.contains("Passwords must be longer than"));
+}
+
+#[tokio::test]
+async fn a_taken_username_leaves_the_invitation_usable() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let link = app.invite("viewer@example.com", "viewer").await;
+
+    // Act - Part 1 - Pick the test user's username
+    app.accept(&link, &app.test_user.username, "a-long-enough-password")
+        .await;
+    let html = app
+        .api_client
+        .get(link.clone())
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("This username is already taken."));
+
+    // Act - Part 2 - Pick another one
+    let resp = app.accept(&link, "invitee", "a-long-enough-password").await;
+
+    // Assert
+    assert_redirects_to(&resp, "/login");
+}
+
+#[tokio::test]
+async fn a_second_invitation_to_the_same_email_cannot_create_another_account() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let first = app.invite("viewer@example.com", "viewer").await;
+    let second = app.invite("viewer@example.com", "viewer").await;
+    let resp = app
+        .accept(&first, "invitee", "a-long-enough-password")
+        .await;
+    assert_redirects_to(&resp, "/login");
+
+    // Act
+    let resp = app
+        .accept(&second, "another-invitee", "a-long-enough-password")
+        .await;
+
+    // Assert
+    assert_redirects_to(
+        &resp,
+        &format!("/invitations/accept?{}", second.query().unwrap()),
+    );
+    let html = app
+        .api_client
+        .get(second)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("There already is an account with this email."));
+}
+
+#[tokio::test]
+async fn invitations_are_kept_when_their_email_cannot_be_sent() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let resp = app
+        .post_invitations(&serde_json::json!({ "email": "viewer@example.com", "role": "viewer" }))
+        .await;
+
+    // Assert
+    assert_redirects_to(&resp, "/admin/users");
+    let html = app.get_users_html().await;
+    assert!(html.contains("has been saved, but its email could not be sent."));
+    assert!(html.contains("viewer@example.com"));
+}
+
+#[tokio::test]
+async fn only_admins_can_invite_collaborators() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let editor = TestUser::generate_with_role("editor");
+    editor.store(&app.db_pool).await;
+    app.login_as(&editor).await;
+
+    Mock::given(matchers::any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let resp = app
+        .post_invitations(&serde_json::json!({ "email": "viewer@example.com", "role": "viewer" }))
+        .await;
+
+    // Assert
+    assert_eq!(403, resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn pending_invitations_are_listed() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    app.invite("viewer@example.com", "viewer").await;
+
+    // Act
+    let html = app.get_users_html().await;
+
+    // Assert
+    assert!(html.contains("An invitation has been sent to viewer@example.com."));
+    assert!(html.contains("<tr><td>viewer@example.com</td><td>viewer</td>"));
+}
@@ -0,0 +1,139 @@
+use crate::helpers::{self, assert_redirects_to, TestApp};
+use uuid::Uuid;
+use wiremock::{matchers, Mock};
+
+impl TestApp {
+    async fn publish_issue(&self, title: &str) -> Uuid {
+        let resp = self
+            .post_newsletters(&serde_json::json!({
+                "title": title,
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+                "idempotency_key": Uuid::new_v4(),
+            }))
+            .await;
+        assert_redirects_to(&resp, "/admin/newsletters");
+        sqlx::query!("SELECT id FROM newsletter_issues WHERE title = $1", title)
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+            .id
+    }
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_browse_the_issues() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let resp = app.get_admin_issues(1).await;
+
+    // Assert
+    assert_redirects_to(&resp, "/login");
+}
+
+#[tokio::test]
+async fn the_archive_shows_the_delivery_progress_of_each_issue() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+
+    Mock::given(matchers::path("/email/batch"))
+        .respond_with(helpers::batch_delivered)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Publish an issue
+    let issue_id = app.publish_issue("Delivered issue").await;
+    let html = app.get_admin_issues(1).await.text().await.unwrap();
+
+    // Assert - Part 1
+    assert!(html.contains("Delivered issue"));
+    assert!(html.contains("<td>2</td>\n                <td>0</td>\n                <td>0</td>"));
+
+    // Act - Part 2 - Deliver it
+    app.dispatch_all_pending_emails().await;
+    let html = app.get_admin_issues(1).await.text().await.unwrap();
+
+    // Assert - Part 2
+    assert!(html.contains("<td>0</td>\n                <td>2</td>\n                <td>0</td>"));
+
+    // Act - Part 3 - Look at its details
+    let html = app.get_admin_issue(issue_id).await.text().await.unwrap();
+
+    // Assert - Part 3
+    assert!(html.contains("<li>Sent: 2</li>"));
+    assert!(html.contains("Newsletter body as plain text"));
+}
+
+#[tokio::test]
+async fn the_archive_is_paginated() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    for i in 0..21 {
+        app.publish_issue(&format!("Issue #{i}")).await;
+    }
+
+    // Act
+    let first_page = app.get_admin_issues(1).await.text().await.unwrap();
+    let second_page = app.get_admin_issues(2).await.text().await.unwrap();
+
+    // Assert
+    assert!(first_page.contains("Issue #20"));
+    assert!(!first_page.contains("Issue #0<"));
+    assert!(first_page.contains("?page=2\">Next"));
+    assert!(second_page.contains("Issue #0<"));
+    assert!(second_page.contains("?page=1\">Previous"));
+    assert!(!second_page.contains("Next"));
+}
+
+#[tokio::test]
+async fn out_of_range_pages_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+
+    // Act
+    let resp = app.get_admin_issues(i64::MAX).await;
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn an_unknown_issue_is_not_found() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+
+    // Act
+    let admin_resp = app.get_admin_issue(Uuid::new_v4()).await;
+    let web_resp = app.get_web_issue(Uuid::new_v4()).await;
+
+    // Assert
+    assert_eq!(404, admin_resp.status().as_u16());
+    assert_eq!(404, web_resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn anyone_can_read_a_past_issue_in_the_browser() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let issue_id = app.publish_issue("A past issue").await;
+    app.post_logout().await;
+
+    // Act
+    let resp = app.get_web_issue(issue_id).await;
+
+    // Assert
+    assert_eq!(200, resp.status().as_u16());
+    let html = resp.text().await.unwrap();
+    assert!(html.contains("<title>A past issue</title>"));
+    assert!(html.contains("<p>Newsletter body as HTML</p>"));
+}
@@ -0,0 +1,251 @@
+use crate::helpers::{self, TestApp};
+use reqwest::Response;
+use uuid::Uuid;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+impl TestApp {
+    async fn post_list(&self, name: &str) -> Response {
+        self.api_client
+            .post(format!("{}/admin/lists", self.base_addr))
+            .form(&[("name", name), ("description", "")])
+            .send()
+            .await
+            .unwrap()
+    }
+
+    async fn get_lists_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/lists", self.base_addr))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// Creates a list as the logged-in admin and returns its id.
+    async fn create_list(&self, name: &str) -> Uuid {
+        helpers::assert_redirects_to(&self.post_list(name).await, "/admin/lists");
+        sqlx::query_scalar!("SELECT id FROM lists WHERE name = $1", name)
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+    }
+
+    /// Signs up for the given lists and confirms the subscription.
+    async fn create_confirmed_subscriber_of(&self, email: &str, lists: &[Uuid]) {
+        let _mock_guard = Mock::given(matchers::path("/email"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount_as_scoped(&self.email_server)
+            .await;
+        let mut form = vec![("name", "Ursula".to_string()), ("email", email.to_string())];
+        form.extend(lists.iter().map(|id| ("lists", id.to_string())));
+        self.api_client
+            .post(format!("{}/subscriptions", self.base_addr))
+            .form(&form)
+            .send()
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+        self.send_all_confirmation_emails().await;
+
+        let email_request = self.email_server.received_requests().await.unwrap().pop();
+        let link = self.get_confirmation_links(&email_request.unwrap()).html;
+        reqwest::get(link)
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+    }
+
+    async fn publish_to(&self, lists: &[Uuid]) -> Vec<String> {
+        let audience: Vec<_> = lists.iter().map(|id| ("lists", id.to_string())).collect();
+        self.publish_and_get_recipients(&audience).await
+    }
+}
+
+#[tokio::test]
+async fn admins_can_create_lists() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+
+    // Act - Part 1 - Create a list
+    let resp = app.post_list("Weekly digest").await;
+    helpers::assert_redirects_to(&resp, "/admin/lists");
+
+    // Assert
+    let html = app.get_lists_html().await;
+    assert!(html.contains("<p><i>The list Weekly digest has been created.</i></p>"));
+    assert!(html.contains("<td>Weekly digest</td>"));
+
+    // Act - Part 2 - Names are unique
+    app.post_list("Weekly digest").await;
+
+    // Assert
+    let html = app.get_lists_html().await;
+    assert!(html.contains("<p><i>A list named Weekly digest already exists.</i></p>"));
+}
+
+#[tokio::test]
+async fn issues_targeting_lists_are_only_delivered_to_their_subscribers() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let digest = app.create_list("Weekly digest").await;
+    let releases = app.create_list("Release notes").await;
+    app.create_confirmed_subscriber_of("digest@example.com", &[digest])
+        .await;
+    app.create_confirmed_subscriber_of("releases@example.com", &[releases])
+        .await;
+    app.create_confirmed_subscriber_of("nothing@example.com", &[])
+        .await;
+
+    // Act
+    let recipients = app.publish_to(&[digest]).await;
+
+    // Assert
+    assert_eq!(vec!["digest@example.com"], recipients);
+}
+
+#[tokio::test]
+async fn issues_without_lists_are_delivered_to_every_confirmed_subscriber() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let digest = app.create_list("Weekly digest").await;
+    app.create_confirmed_subscriber_of("digest@example.com", &[digest])
+        .await;
+    app.create_confirmed_subscriber_of("nothing@example.com", &[])
+        .await;
+
+    // Act
+    let recipients = app.publish_to(&[]).await;
+
+    // Assert
+    assert_eq!(
+        vec!["digest@example.com", "nothing@example.com"],
+        recipients
+    );
+}
+
+#[tokio::test]
+async fn issues_targeting_unknown_lists_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let digest = app.create_list("Weekly digest").await;
+    let form = [
+        ("title", "Newsletter title".to_string()),
+        ("text", "Newsletter body as plain text".to_string()),
+        ("html", "<p>Newsletter body as HTML</p>".to_string()),
+        ("idempotency_key", Uuid::new_v4().to_string()),
+        ("lists", digest.to_string()),
+        ("lists", digest.to_string()),
+        ("lists", Uuid::new_v4().to_string()),
+    ];
+
+    // Act
+    let resp = app.post_newsletters(&form).await;
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+    let n_issues = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "n!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, n_issues);
+}
+
+#[tokio::test]
+async fn duplicate_lists_are_targeted_once() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let digest = app.create_list("Weekly digest").await;
+    app.create_confirmed_subscriber_of("digest@example.com", &[digest])
+        .await;
+    app.create_confirmed_subscriber_of("nothing@example.com", &[])
+        .await;
+
+    // Act
+    let recipients = app.publish_to(&[digest, digest]).await;
+
+    // Assert
+    assert_eq!(vec!["digest@example.com"], recipients);
+}
+
+#[tokio::test]
+async fn subscribers_can_change_their_lists_from_the_preferences_page() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let digest = app.create_list("Weekly digest").await;
+    let releases = app.create_list("Release notes").await;
+    app.create_confirmed_subscriber_of("ursula@example.com", &[digest])
+        .await;
+    let link = {
+        app.publish_to(&[]).await;
+        let email_request = app.email_server.received_requests().await.unwrap().pop();
+        let mut link = app.get_unsubscribe_link(&email_request.unwrap());
+        link.set_path("/subscriptions/preferences");
+        link
+    };
+
+    // Act - Part 1 - Show the preferences
+    let html = app
+        .api_client
+        .get(link.clone())
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains(&format!(r#"value="{}" checked"#, digest)));
+    assert!(html.contains(&format!(r#"value="{}">"#, releases)));
+
+    // Act - Part 2 - Switch lists
+    let resp = app
+        .api_client
+        .post(link.clone())
+        .form(&[("lists", releases.to_string())])
+        .send()
+        .await
+        .unwrap();
+    helpers::assert_redirects_to(&resp, link.as_str().trim_start_matches(&app.base_addr));
+
+    // Assert
+    let lists = sqlx::query_scalar!("SELECT list_id FROM subscription_lists")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(vec![releases], lists);
+    let recipients = app.publish_to(&[digest]).await;
+    assert!(recipients.is_empty());
+}
+
+#[tokio::test]
+async fn the_preferences_page_requires_a_valid_signature() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let resp = app
+        .api_client
+        .get(format!(
+            "{}/subscriptions/preferences?subscriber_id={}&token={}",
+            app.base_addr,
+            Uuid::new_v4(),
+            "A".repeat(43)
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(401, resp.status().as_u16());
+}
@@ -0,0 +1,147 @@
+use crate::helpers::{assert_redirects_to, TestApp};
+use std::net::Ipv6Addr;
+use uuid::Uuid;
+
+const LOCKOUT_MESSAGE: &str = "Too many failed login attempts - please try again in";
+
+impl TestApp {
+    async fn post_wrong_login(&self, username: &str) -> String {
+        let resp = self
+            .post_login(&serde_json::json!({
+                "username": username,
+                "password": Uuid::new_v4().to_string(),
+            }))
+            .await;
+        assert_redirects_to(&resp, "/login");
+        self.get_login_html().await
+    }
+
+    async fn post_test_user_login(&self) -> reqwest::Response {
+        self.post_login(&serde_json::json!({
+            "username": &self.test_user.username,
+            "password": &self.test_user.password,
+        }))
+        .await
+    }
+}
+
+#[tokio::test]
+async fn repeated_failures_lock_out_the_username() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    for _ in 0..5 {
+        let html = app.post_wrong_login(&app.test_user.username).await;
+        assert!(html.contains("<p><i>Authentication failed</i></p>"));
+    }
+
+    // Act
+    let html = app.post_wrong_login(&app.test_user.username).await;
+
+    // Assert
+    assert!(html.contains(
+        "<p><i>Too many failed login attempts - please try again in 30 seconds.</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn the_right_password_is_rejected_while_locked_out() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    for _ in 0..6 {
+        app.post_wrong_login(&app.test_user.username).await;
+    }
+
+    // Act
+    let resp = app.post_test_user_login().await;
+
+    // Assert
+    assert_redirects_to(&resp, "/login");
+    let html = app.get_login_html().await;
+    assert!(html.contains(LOCKOUT_MESSAGE));
+}
+
+#[tokio::test]
+async fn a_successful_login_resets_the_failure_counter() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    for _ in 0..5 {
+        app.post_wrong_login(&app.test_user.username).await;
+    }
+    let resp = app.post_test_user_login().await;
+    assert_redirects_to(&resp, "/admin/dashboard");
+
+    // Act
+    let mut pages = Vec::new();
+    for _ in 0..5 {
+        pages.push(app.post_wrong_login(&app.test_user.username).await);
+    }
+
+    // Assert
+    for html in pages {
+        assert!(!html.contains(LOCKOUT_MESSAGE));
+    }
+}
+
+#[tokio::test]
+async fn repeated_failures_lock_out_the_client_ip() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    for _ in 0..21 {
+        app.post_wrong_login(&Uuid::new_v4().to_string()).await;
+    }
+
+    // Act
+    let resp = app.post_test_user_login().await;
+
+    // Assert
+    assert_redirects_to(&resp, "/login");
+    let html = app.get_login_html().await;
+    assert!(html.contains(LOCKOUT_MESSAGE));
+}
+
+#[tokio::test]
+async fn a_spoofed_forwarded_for_header_does_not_dodge_the_ip_lockout() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    for _ in 0..21 {
+        app.api_client
+            .post(format!("{}/login", app.base_addr))
+            .header(
+                "X-Forwarded-For",
+                Ipv6Addr::from(Uuid::new_v4().as_u128()).to_string(),
+            )
+            .form(&serde_json::json!({
+                "username": Uuid::new_v4().to_string(),
+                "password": Uuid::new_v4().to_string(),
+            }))
+            .send()
+            .await
+            .unwrap();
+    }
+
+    // Assert
+    let resp = app.post_test_user_login().await;
+    assert_redirects_to(&resp, "/login");
+    let html = app.get_login_html().await;
+    assert!(html.contains(LOCKOUT_MESSAGE));
+}
+
+#[tokio::test]
+async fn usernames_are_throttled_regardless_of_case() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let username = app.test_user.username.to_uppercase();
+    for _ in 0..6 {
+        app.post_wrong_login(&username).await;
+    }
+
+    // Act
+    let resp = app.post_test_user_login().await;
+
+    // Assert
+    assert_redirects_to(&resp, "/login");
+    let html = app.get_login_html().await;
+    assert!(html.contains(LOCKOUT_MESSAGE));
+}
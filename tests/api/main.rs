@@ -1,9 +1,28 @@
+mod admin_cli;
 mod admin_dashboard;
+mod api_issues;
+mod api_subscribers;
 mod change_password;
+mod deliveries;
+mod drafts;
 mod health_check;
 mod helpers;
+mod invitations;
+mod issues;
+mod lists;
 mod login;
+mod login_throttle;
+mod metrics;
 mod newsletter;
+mod password_reset;
+mod roles;
+mod scheduled_issues;
+mod segments;
+mod subscriber_csv;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
+mod tracking;
+mod two_factor;
+mod webhooks;
 mod workers;
@@ -0,0 +1,100 @@
+use crate::helpers::{TestApp, RQST_FAIL};
+use reqwest::Response;
+use uuid::Uuid;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+/// The metrics token of `config/Base.toml`.
+const TOKEN: &str = "my-metrics-token";
+
+impl TestApp {
+    async fn get_metrics(&self, token: Option<&str>) -> Response {
+        let mut req = self.api_client.get(format!("{}/metrics", self.base_addr));
+        if let Some(token) = token {
+            req = req.bearer_auth(token);
+        }
+        req.send().await.expect(RQST_FAIL)
+    }
+
+    async fn get_metrics_text(&self) -> String {
+        let resp = self.get_metrics(Some(TOKEN)).await;
+        assert_eq!(200, resp.status().as_u16());
+        resp.text().await.unwrap()
+    }
+}
+
+#[tokio::test]
+async fn metrics_require_the_bearer_token() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let anonymous = app.get_metrics(None).await;
+    let wrong_token = app.get_metrics(Some("not-the-token")).await;
+
+    // Assert
+    assert_eq!(401, anonymous.status().as_u16());
+    assert_eq!(401, wrong_token.status().as_u16());
+}
+
+#[tokio::test]
+async fn requests_are_counted_by_route_pattern() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.get_web_issue(Uuid::new_v4()).await;
+
+    // Act
+    let metrics = app.get_metrics_text().await;
+
+    // Assert
+    assert!(metrics
+        .contains(r#"http_requests_total{method="GET",route="/issues/{issue_id}",status="404"}"#));
+    assert!(metrics.contains(
+        r#"http_request_duration_seconds_bucket{method="GET",route="/issues/{issue_id}""#
+    ));
+}
+
+#[tokio::test]
+async fn email_failures_are_counted_by_reason() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await
+        .error_for_status()
+        .unwrap();
+    Mock::given(matchers::path("/email"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.send_all_confirmation_emails().await;
+
+    // Assert
+    let metrics = app.get_metrics_text().await;
+    assert!(metrics.contains(r#"email_failures_total{reason="http_503"}"#));
+}
+
+#[tokio::test]
+async fn the_delivery_queue_and_the_pool_are_sampled() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let metrics = app.get_metrics_text().await;
+
+    // Assert
+    for name in [
+        "issue_delivery_queue_depth",
+        "issue_delivery_queue_oldest_task_age_seconds",
+        r#"db_pool_connections{state="in_use"}"#,
+        "db_pool_max_connections",
+        "password_verification_duration_seconds_count",
+        "emails_sent_total",
+    ] {
+        assert!(
+            metrics.contains(&format!("\n{} ", name)),
+            "{} is missing",
+            name
+        );
+    }
+}
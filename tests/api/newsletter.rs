@@ -1,9 +1,9 @@
 use crate::helpers::{self, TestApp};
 use std::time::Duration;
-use wiremock::{matchers, Mock, MockBuilder, ResponseTemplate};
+use wiremock::{matchers, Mock, MockBuilder, Request, ResponseTemplate};
 
-fn when_sending_an_email() -> MockBuilder {
-    Mock::given(matchers::path("/email")).and(matchers::method("POST"))
+fn when_sending_an_issue() -> MockBuilder {
+    Mock::given(matchers::path("/email/batch")).and(matchers::method("POST"))
 }
 
 #[tokio::test]
@@ -44,8 +44,8 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     let app = TestApp::spawn().await;
     app.create_confirmed_subscriber().await;
 
-    when_sending_an_email()
-        .respond_with(ResponseTemplate::new(200))
+    when_sending_an_issue()
+        .respond_with(helpers::batch_delivered)
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -110,8 +110,8 @@ async fn newsletter_creation_is_idempotent() {
     app.create_confirmed_subscriber().await;
     app.login_as_test_user().await;
 
-    when_sending_an_email()
-        .respond_with(ResponseTemplate::new(200))
+    when_sending_an_issue()
+        .respond_with(helpers::batch_delivered)
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -153,8 +153,8 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     app.create_confirmed_subscriber().await;
     app.login_as_test_user().await;
 
-    when_sending_an_email()
-        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+    when_sending_an_issue()
+        .respond_with(|r: &Request| helpers::batch_delivered(r).set_delay(Duration::from_secs(2)))
         .expect(1)
         .mount(&app.email_server)
         .await;
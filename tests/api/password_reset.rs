@@ -0,0 +1,347 @@
+use crate::helpers::{assert_redirects_to, TestApp, RQST_FAIL};
+use reqwest::{Response, Url};
+use std::time::Duration;
+use uuid::Uuid;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+impl TestApp {
+    async fn post_password_reset(&self, email: &str) -> Response {
+        self.api_client
+            .post(format!("{}/password-reset", self.base_addr))
+            .form(&serde_json::json!({ "email": email }))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    async fn post_new_password(&self, token: &str, password: &str, check: &str) -> Response {
+        self.api_client
+            .post(format!("{}/password-reset/confirm", self.base_addr))
+            .form(&serde_json::json!({
+                "token": token,
+                "new_password": password,
+                "new_password_check": check,
+            }))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    /// Asks for a reset of the test user's password and returns the link from the email.
+    async fn request_reset_link(&self) -> Url {
+        self.request_reset_link_to(&self.test_user.email).await
+    }
+
+    /// Asks for a reset of the password of the account with this email and returns the link
+    /// from the email.
+    async fn request_reset_link_to(&self, email: &str) -> Url {
+        let _guard = Mock::given(matchers::path("/email"))
+            .and(matchers::method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount_as_scoped(&self.email_server)
+            .await;
+
+        let resp = self.post_password_reset(email).await;
+        assert_redirects_to(&resp, "/login");
+
+        let email_request = self.wait_for_email().await;
+        self.get_confirmation_links(&email_request).html
+    }
+
+    /// Waits for the email sent in the background after a password reset request.
+    async fn wait_for_email(&self) -> wiremock::Request {
+        for _ in 0..50 {
+            let received_requests = self.email_server.received_requests().await.unwrap();
+            if let Some(r) = received_requests.into_iter().last() {
+                return r;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("No email was sent.");
+    }
+}
+
+fn token_of(link: &Url) -> String {
+    link.query_pairs()
+        .find(|(k, _)| k == "token")
+        .unwrap()
+        .1
+        .into_owned()
+}
+
+#[tokio::test]
+async fn the_login_page_links_to_the_password_reset_form() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let html = app.get_login_html().await;
+
+    // Assert
+    assert!(html.contains(r#"<a href="/password-reset">"#));
+}
+
+#[tokio::test]
+async fn unknown_emails_get_the_same_answer_but_no_email() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    Mock::given(matchers::any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let resp = app.post_password_reset("nobody@example.com").await;
+
+    // Assert
+    assert_redirects_to(&resp, "/login");
+    let html = app.get_login_html().await;
+    assert!(html.contains(
+        "If nobody@example.com belongs to an account, we have sent it a link to reset the password."
+    ));
+}
+
+#[tokio::test]
+async fn a_reset_link_lets_the_user_choose_a_new_password() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let link = app.request_reset_link().await;
+    let new_password = Uuid::new_v4().to_string();
+
+    // Act - Part 1 - Open the link
+    let resp = app.api_client.get(link.clone()).send().await.unwrap();
+    assert_eq!(200, resp.status().as_u16());
+
+    // Act - Part 2 - Choose a new password
+    let resp = app
+        .post_new_password(&token_of(&link), &new_password, &new_password)
+        .await;
+    assert_redirects_to(&resp, "/login");
+    let html = app.get_login_html().await;
+    assert!(html.contains("<p><i>Your password has been reset - you can now log in.</i></p>"));
+
+    // Act - Part 3 - Log in with the new password
+    let resp = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &new_password,
+        }))
+        .await;
+
+    // Assert
+    assert_redirects_to(&resp, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn reset_links_can_only_be_used_once() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let link = app.request_reset_link().await;
+    let new_password = Uuid::new_v4().to_string();
+    app.post_new_password(&token_of(&link), &new_password, &new_password)
+        .await;
+
+    // Act
+    let resp = app
+        .post_new_password(&token_of(&link), &new_password, &new_password)
+        .await;
+
+    // Assert
+    assert_eq!(401, resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn expired_reset_links_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let link = app.request_reset_link().await;
+    sqlx::query!("UPDATE password_reset_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let resp = app.api_client.get(link).send().await.unwrap();
+
+    // Assert
+    assert_eq!(401, resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn new_passwords_must_match() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let link = app.request_reset_link().await;
+    let token = token_of(&link);
+
+    // Act
+    let resp = app
+        .post_new_password(
+            &token,
+            &Uuid::new_v4().to_string(),
+            &Uuid::new_v4().to_string(),
+        )
+        .await;
+
+    // Assert
+    assert_redirects_to(&resp, &format!("/password-reset/confirm?token={}", token));
+    let html = app
+        .api_client
+        .get(link)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains(
+        "<p><i>You entered two different new passwords - the field values must match.</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn resetting_the_password_logs_the_user_out_everywhere() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let link = app.request_reset_link().await;
+    let new_password = Uuid::new_v4().to_string();
+
+    // Act
+    app.post_new_password(&token_of(&link), &new_password, &new_password)
+        .await;
+
+    // Assert
+    let resp = app.get_admin_dashboard().await;
+    assert_redirects_to(&resp, "/login");
+}
+
+#[tokio::test]
+async fn the_answer_does_not_depend_on_whether_the_email_can_be_sent() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let resp = app.post_password_reset(&app.test_user.email).await;
+
+    // Assert
+    assert_redirects_to(&resp, "/login");
+    let html = app.get_login_html().await;
+    assert!(html.contains(&format!(
+        "If {} belongs to an account, we have sent it a link to reset the password.",
+        app.test_user.email
+    )));
+    app.wait_for_email().await;
+}
+
+#[tokio::test]
+async fn password_reset_requests_are_rate_limited() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    for _ in 0..5 {
+        let resp = app.post_password_reset("nobody@example.com").await;
+        assert_redirects_to(&resp, "/login");
+    }
+
+    // Act
+    let resp = app.post_password_reset("NOBODY@example.com").await;
+
+    // Assert
+    assert_redirects_to(&resp, "/password-reset");
+    let html = app
+        .api_client
+        .get(format!("{}/password-reset", app.base_addr))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains(
+        "<p><i>Too many password reset requests - please try again in 30 seconds.</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn the_seeded_admin_can_set_an_email_and_reset_their_password() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let email = "owner@example.com";
+    let new_password = Uuid::new_v4().to_string();
+    let resp = app
+        .post_login(&serde_json::json!({
+            "username": "admin",
+            "password": "everythinghastostartsomewhere",
+        }))
+        .await;
+    assert_redirects_to(&resp, "/admin/dashboard");
+
+    // Act - Part 1 - Set an email
+    let resp = app
+        .post_security(
+            "/email",
+            &serde_json::json!({
+                "email": email,
+                "current_password": "everythinghastostartsomewhere",
+            }),
+        )
+        .await;
+    assert_redirects_to(&resp, "/admin/security");
+    let html = app.get_security_html().await;
+    assert!(html.contains("<p><i>Your email has been changed.</i></p>"));
+    assert!(html.contains("Password reset links are sent to <b>owner@example.com</b>."));
+    app.post_logout().await;
+
+    // Act - Part 2 - Reset the forgotten password
+    let link = app.request_reset_link_to(email).await;
+    let resp = app
+        .post_new_password(&token_of(&link), &new_password, &new_password)
+        .await;
+    assert_redirects_to(&resp, "/login");
+
+    // Act - Part 3 - Log in with the new password
+    let resp = app
+        .post_login(&serde_json::json!({
+            "username": "admin",
+            "password": &new_password,
+        }))
+        .await;
+
+    // Assert
+    assert_redirects_to(&resp, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn changing_the_email_requires_the_current_password() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+
+    // Act
+    let resp = app
+        .post_security(
+            "/email",
+            &serde_json::json!({
+                "email": "owner@example.com",
+                "current_password": Uuid::new_v4().to_string(),
+            }),
+        )
+        .await;
+
+    // Assert
+    assert_redirects_to(&resp, "/admin/security");
+    let html = app.get_security_html().await;
+    assert!(html.contains("<p><i>The current password is incorrect.</i></p>"));
+    assert!(html.contains(&format!(
+        "Password reset links are sent to <b>{}</b>.",
+        app.test_user.email
+    )));
+}
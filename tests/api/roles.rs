@@ -0,0 +1,103 @@
+use crate::helpers::{assert_redirects_to, TestApp, TestUser};
+use uuid::Uuid;
+
+fn newsletter_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text": "Newsletter body as plain text",
+        "html": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4(),
+    })
+}
+
+#[tokio::test]
+async fn viewers_can_browse_but_not_publish() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let viewer = TestUser::generate_with_role("viewer");
+    viewer.store(&app.db_pool).await;
+    app.login_as(&viewer).await;
+
+    // Act
+    let form_resp = app.get_newsletters().await;
+    let publish_resp = app.post_newsletters(&newsletter_body()).await;
+    let issues_resp = app.get_admin_issues(1).await;
+
+    // Assert
+    assert_eq!(403, form_resp.status().as_u16());
+    assert_eq!(403, publish_resp.status().as_u16());
+    assert_eq!(200, issues_resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn editors_can_publish_but_not_manage_users() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let editor = TestUser::generate_with_role("editor");
+    editor.store(&app.db_pool).await;
+    app.login_as(&editor).await;
+
+    // Act
+    let publish_resp = app.post_newsletters(&newsletter_body()).await;
+    let users_resp = app
+        .api_client
+        .get(format!("{}/admin/users", app.base_addr))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_redirects_to(&publish_resp, "/admin/newsletters");
+    assert_eq!(403, users_resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn admins_can_change_the_role_of_other_users() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let viewer = TestUser::generate_with_role("viewer");
+    viewer.store(&app.db_pool).await;
+    app.login_as_test_user().await;
+
+    // Act
+    let resp = app
+        .api_client
+        .post(format!("{}/admin/users/{}/role", app.base_addr, viewer.id))
+        .form(&serde_json::json!({ "role": "editor" }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_redirects_to(&resp, "/admin/users");
+    app.post_logout().await;
+    app.login_as(&viewer).await;
+    assert_redirects_to(
+        &app.post_newsletters(&newsletter_body()).await,
+        "/admin/newsletters",
+    );
+}
+
+#[tokio::test]
+async fn admins_cannot_change_their_own_role() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+
+    // Act
+    app.api_client
+        .post(format!(
+            "{}/admin/users/{}/role",
+            app.base_addr, app.test_user.id
+        ))
+        .form(&serde_json::json!({ "role": "viewer" }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(app
+        .get_users_html()
+        .await
+        .contains("You cannot change your own role."));
+}
@@ -0,0 +1,214 @@
+use crate::helpers::{self, assert_redirects_to, TestApp};
+use chrono::{TimeDelta, Utc};
+use uuid::Uuid;
+use wiremock::{matchers, Mock, MockBuilder};
+
+fn when_sending_an_issue() -> MockBuilder {
+    Mock::given(matchers::path("/email/batch")).and(matchers::method("POST"))
+}
+
+impl TestApp {
+    async fn schedule_an_issue(&self) -> Uuid {
+        let scheduled_for = Utc::now() + TimeDelta::days(2);
+        let resp = self
+            .post_newsletters(&serde_json::json!({
+                "title": "Scheduled issue",
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+                "idempotency_key": Uuid::new_v4(),
+                "scheduled_for": scheduled_for.to_rfc3339(),
+            }))
+            .await;
+        assert_redirects_to(&resp, "/admin/newsletters");
+        sqlx::query!("SELECT id FROM newsletter_issues WHERE title = 'Scheduled issue'")
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+            .id
+    }
+
+    /// Moves the publication time of a scheduled issue to the past.
+    async fn make_issue_due(&self, issue_id: Uuid) {
+        sqlx::query!(
+            "UPDATE newsletter_issues SET scheduled_for = now() - interval '1 minute' WHERE id = $1",
+            issue_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .unwrap();
+    }
+
+    async fn get_issue_status(&self, issue_id: Uuid) -> String {
+        sqlx::query!(
+            "SELECT status FROM newsletter_issues WHERE id = $1",
+            issue_id
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .unwrap()
+        .status
+    }
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_not_delivered_before_their_time() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+
+    when_sending_an_issue()
+        .respond_with(helpers::batch_delivered)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let issue_id = app.schedule_an_issue().await;
+    app.publish_all_due_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!("scheduled", app.get_issue_status(issue_id).await);
+    let html = app.get_newsletters_html().await;
+    assert!(html.contains("The newsletter issue has been scheduled for"));
+    assert_eq!(404, app.get_web_issue(issue_id).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_delivered_once_due() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+
+    when_sending_an_issue()
+        .respond_with(helpers::batch_delivered)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let issue_id = app.schedule_an_issue().await;
+    app.make_issue_due(issue_id).await;
+
+    // Act
+    app.publish_all_due_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!("published", app.get_issue_status(issue_id).await);
+    assert_eq!(200, app.get_web_issue(issue_id).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn cancelled_issues_are_never_delivered() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+
+    when_sending_an_issue()
+        .respond_with(helpers::batch_delivered)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let issue_id = app.schedule_an_issue().await;
+
+    // Act - Part 1 - Cancel the issue
+    let resp = app.post_cancel_issue(issue_id).await;
+    assert_redirects_to(&resp, &format!("/admin/newsletters/issues/{issue_id}"));
+
+    // Act - Part 2 - Follow the redirect
+    let html = app.get_admin_issue(issue_id).await.text().await.unwrap();
+    assert!(html.contains("The newsletter issue has been cancelled."));
+
+    // Act - Part 3 - Let its original time pass
+    app.make_issue_due(issue_id).await;
+    app.publish_all_due_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!("cancelled", app.get_issue_status(issue_id).await);
+}
+
+#[tokio::test]
+async fn scheduled_issues_can_be_rescheduled() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let issue_id = app.schedule_an_issue().await;
+
+    // Act
+    let resp = app
+        .post_reschedule_issue(
+            issue_id,
+            &serde_json::json!({ "scheduled_for": "2999-01-01T09:30" }),
+        )
+        .await;
+
+    // Assert
+    assert_redirects_to(&resp, &format!("/admin/newsletters/issues/{issue_id}"));
+    let html = app.get_admin_issue(issue_id).await.text().await.unwrap();
+    assert!(
+        html.contains("The newsletter issue has been rescheduled for 2999-01-01T09:30:00+00:00.")
+    );
+}
+
+#[tokio::test]
+async fn published_issues_cannot_be_rescheduled_or_cancelled() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let issue_id = app.schedule_an_issue().await;
+    app.make_issue_due(issue_id).await;
+    app.publish_all_due_issues().await;
+
+    // Act - Part 1 - Reschedule
+    app.post_reschedule_issue(
+        issue_id,
+        &serde_json::json!({ "scheduled_for": "2999-01-01T09:30" }),
+    )
+    .await;
+    let html = app.get_admin_issue(issue_id).await.text().await.unwrap();
+    assert!(html.contains("Only scheduled issues can be rescheduled."));
+
+    // Act - Part 2 - Cancel
+    app.post_cancel_issue(issue_id).await;
+    let html = app.get_admin_issue(issue_id).await.text().await.unwrap();
+    assert!(html.contains("Only scheduled issues can be cancelled."));
+
+    // Assert
+    assert_eq!("published", app.get_issue_status(issue_id).await);
+}
+
+#[tokio::test]
+async fn issues_cannot_be_scheduled_in_the_past() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+
+    // Act - Part 1 - Submit the form
+    let resp = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4(),
+            "scheduled_for": "2001-01-01T09:30",
+        }))
+        .await;
+    assert_redirects_to(&resp, "/admin/newsletters");
+
+    // Act - Part 2 - Follow the redirect
+    let html = app.get_newsletters_html().await;
+
+    // Assert
+    assert!(html.contains("The publication time must be in the future."));
+    let n_issues = sqlx::query!(r#"SELECT COUNT(*) AS "n!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(0, n_issues);
+}
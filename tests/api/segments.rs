@@ -0,0 +1,169 @@
+use crate::helpers::{self, TestApp, RQST_FAIL};
+use reqwest::{Method, Response};
+use serde_json::Value;
+use uuid::Uuid;
+
+impl TestApp {
+    async fn post_segment(&self, form: &[(&str, &str)]) -> Response {
+        self.api_client
+            .post(format!("{}/admin/segments", self.base_addr))
+            .form(form)
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    async fn get_segments_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/segments", self.base_addr))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// Creates a segment of the subscribers whose `plan` attribute is `pro`.
+    async fn create_pro_segment(&self) -> Uuid {
+        let resp = self
+            .post_segment(&[
+                ("name", "Pro users"),
+                ("attribute_key", "plan"),
+                ("attribute_value", "pro"),
+            ])
+            .await;
+        helpers::assert_redirects_to(&resp, "/admin/segments");
+        sqlx::query_scalar!("SELECT id FROM segments WHERE name = 'Pro users'")
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+    }
+
+    async fn create_subscriber_with_plan(&self, key: &str, email: &str, plan: &str) {
+        self.api_request(Method::POST, "/subscribers", key)
+            .json(&serde_json::json!({
+                "email": email,
+                "name": "Ursula",
+                "confirmed": true,
+                "attributes": { "plan": plan },
+            }))
+            .send()
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn segments_list_their_rules_and_size() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    app.create_subscriber_with_plan(&key, "pro@example.com", "pro")
+        .await;
+    app.create_subscriber_with_plan(&key, "free@example.com", "free")
+        .await;
+
+    // Act
+    app.create_pro_segment().await;
+
+    // Assert
+    let html = app.get_segments_html().await;
+    assert!(html.contains("<p><i>The segment Pro users has been created.</i></p>"));
+    assert!(html.contains("<td>plan is &quot;pro&quot;</td>"));
+    assert!(html.contains("<td>1</td>"));
+}
+
+#[tokio::test]
+async fn segments_need_at_least_one_rule() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+
+    // Act
+    let resp = app.post_segment(&[("name", "Everyone")]).await;
+
+    // Assert
+    helpers::assert_redirects_to(&resp, "/admin/segments");
+    let html = app.get_segments_html().await;
+    assert!(html.contains("<p><i>A segment needs at least one rule.</i></p>"));
+}
+
+#[tokio::test]
+async fn segments_cannot_look_back_more_than_a_century() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+
+    // Act
+    let resp = app
+        .post_segment(&[("name", "Everyone"), ("subscribed_within_days", "40000")])
+        .await;
+
+    // Assert
+    helpers::assert_redirects_to(&resp, "/admin/segments");
+    let html = app.get_segments_html().await;
+    assert!(html.contains("<p><i>A segment cannot look back more than 36500 days.</i></p>"));
+    let n_segments = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "n!" FROM segments"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, n_segments);
+}
+
+#[tokio::test]
+async fn issues_targeting_a_segment_are_only_delivered_to_its_members() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    app.create_subscriber_with_plan(&key, "pro@example.com", "pro")
+        .await;
+    app.create_subscriber_with_plan(&key, "free@example.com", "free")
+        .await;
+    let segment_id = app.create_pro_segment().await;
+
+    // Act
+    let recipients = app
+        .publish_and_get_recipients(&[("segment_id", segment_id.to_string())])
+        .await;
+
+    // Assert
+    assert_eq!(vec!["pro@example.com"], recipients);
+}
+
+#[tokio::test]
+async fn drafts_show_how_many_subscribers_they_would_reach() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    app.create_subscriber_with_plan(&key, "pro@example.com", "pro")
+        .await;
+    app.create_subscriber_with_plan(&key, "free@example.com", "free")
+        .await;
+    let segment_id = app.create_pro_segment().await;
+    let body: Value = app
+        .api_request(Method::POST, "/issues", &key)
+        .header("Idempotency-Key", Uuid::new_v4().to_string())
+        .json(&serde_json::json!({
+            "title": "Newsletter title",
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+            "draft": true,
+            "segment_id": segment_id,
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let issue_id: Uuid = body["issue_id"].as_str().unwrap().parse().unwrap();
+
+    // Act
+    let html = app.get_admin_issue(issue_id).await.text().await.unwrap();
+
+    // Assert
+    assert!(html.contains("<p>Recipients if sent now: 1</p>"));
+}
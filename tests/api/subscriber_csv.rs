@@ -0,0 +1,264 @@
+use crate::helpers::{assert_redirects_to, TestApp, TestUser, RQST_FAIL};
+use reqwest::Response;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+const BOUNDARY: &str = "zero2prod-test-boundary";
+
+impl TestApp {
+    async fn post_import(&self, mode: &str, csv: &str) -> Response {
+        let body = format!(
+            "--{BOUNDARY}\r\n\
+            Content-Disposition: form-data; name=\"mode\"\r\n\r\n\
+            {mode}\r\n\
+            --{BOUNDARY}\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"subscribers.csv\"\r\n\
+            Content-Type: text/csv\r\n\r\n\
+            {csv}\r\n\
+            --{BOUNDARY}--\r\n"
+        );
+        self.api_client
+            .post(format!("{}/admin/subscribers/import", self.base_addr))
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={BOUNDARY}"),
+            )
+            .body(body)
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    async fn get_export(&self, status: &str) -> Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/subscribers/export?status={}",
+                self.base_addr, status
+            ))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    async fn get_subscribers_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/subscribers", self.base_addr))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+            .text()
+            .await
+            .unwrap()
+    }
+}
+
+#[tokio::test]
+async fn valid_rows_are_imported_as_confirmed_and_invalid_ones_reported() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    Mock::given(matchers::any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let csv = "name,email\n\
+        Ursula Le Guin,ursula@example.com\n\
+        Nobody,not-an-email\n\
+        \"Chiang, Ted\",ted@example.com\n\
+        ,nameless@example.com";
+
+    // Act
+    let resp = app.post_import("confirmed", csv).await;
+
+    // Assert
+    assert_eq!(200, resp.status().as_u16());
+    let html = resp.text().await.unwrap();
+    assert!(html.contains("<p>Imported: 2</p>"));
+    assert!(html.contains("<p>Rows with errors: 2</p>"));
+    assert!(html.contains("<li>Row 3: "));
+    assert!(html.contains("<li>Row 5: "));
+    let statuses = sqlx::query_scalar!("SELECT status FROM subscriptions ORDER BY email")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(vec!["confirmed", "confirmed"], statuses);
+}
+
+#[tokio::test]
+async fn imported_subscribers_can_be_sent_a_confirmation_email() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    let csv = "email,name\r\nursula@example.com,Ursula\r\nted@example.com,Ted\r\n";
+
+    // Act
+    let resp = app.post_import("confirmation", csv).await;
+    app.send_all_confirmation_emails().await;
+
+    // Assert
+    let html = resp.text().await.unwrap();
+    assert!(html.contains("<p>Imported: 2</p>"));
+    let statuses = sqlx::query_scalar!("SELECT status FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec!["pending_confirmation", "pending_confirmation"],
+        statuses
+    );
+}
+
+#[tokio::test]
+async fn existing_subscribers_are_not_imported_twice() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let csv = "email,name\nursula@example.com,Ursula\n";
+    app.post_import("confirmed", csv).await;
+
+    // Act
+    let resp = app.post_import("confirmed", csv).await;
+
+    // Assert
+    let html = resp.text().await.unwrap();
+    assert!(html.contains("<p>Imported: 0</p>"));
+    assert!(html.contains("<p>Already subscribed: 1</p>"));
+}
+
+#[tokio::test]
+async fn files_without_the_expected_header_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+
+    // Act
+    let resp = app
+        .post_import("confirmed", "ursula@example.com,Ursula\n")
+        .await;
+
+    // Assert
+    assert_redirects_to(&resp, "/admin/subscribers");
+    let html = app.get_subscribers_html().await;
+    assert!(html.contains(
+        "<p><i>The first row of the CSV file must name its email and name columns.</i></p>"
+    ));
+    let n_subscribers = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "n!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, n_subscribers);
+}
+
+#[tokio::test]
+async fn rows_past_the_row_limit_are_not_imported() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let mut csv = String::from("email,name\n");
+    for _ in 0..10_000 {
+        csv.push_str(",\n");
+    }
+    csv.push_str("ursula@example.com,Ursula\n");
+
+    // Act
+    let resp = app.post_import("confirmed", &csv).await;
+
+    // Assert
+    assert_eq!(200, resp.status().as_u16());
+    let html = resp.text().await.unwrap();
+    assert!(html.contains("<p>Imported: 0</p>"));
+    assert!(html.contains(
+        "<li>The file has more than 10000 rows - the rest of it has not been imported.</li>"
+    ));
+}
+
+#[tokio::test]
+async fn files_past_the_size_limit_are_not_imported_in_full() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let csv = format!(
+        "email,name\nursula@example.com,{}\n",
+        "U".repeat(6 * 1024 * 1024)
+    );
+
+    // Act
+    let resp = app.post_import("confirmed", &csv).await;
+
+    // Assert
+    assert_eq!(200, resp.status().as_u16());
+    let html = resp.text().await.unwrap();
+    assert!(html.contains("<p>Imported: 0</p>"));
+    assert!(html.contains(
+        "<li>The file is larger than 5 MiB - the rest of it has not been imported.</li>"
+    ));
+}
+
+#[tokio::test]
+async fn subscribers_can_be_exported_by_status() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    app.post_import(
+        "confirmed",
+        "email,name\nursula@example.com,\"Le Guin, Ursula\"\n",
+    )
+    .await;
+    app.create_unconfirmed_subscriber().await;
+
+    // Act
+    let resp = app.get_export("confirmed").await;
+
+    // Assert
+    assert_eq!(200, resp.status().as_u16());
+    assert!(resp.headers()["Content-Type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/csv"));
+    let csv = resp.text().await.unwrap();
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(2, lines.len());
+    assert_eq!("email,name,status,subscribed_at,unsubscribed_at", lines[0]);
+    assert!(lines[1].starts_with("ursula@example.com,\"Le Guin, Ursula\",confirmed,"));
+}
+
+#[tokio::test]
+async fn all_subscribers_are_exported_without_a_status() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    app.post_import("confirmed", "email,name\nursula@example.com,Ursula\n")
+        .await;
+    app.create_unconfirmed_subscriber().await;
+
+    // Act
+    let csv = app.get_export("").await.text().await.unwrap();
+
+    // Assert
+    assert_eq!(3, csv.lines().count());
+}
+
+#[tokio::test]
+async fn only_admins_can_import_or_export_subscribers() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let editor = TestUser::generate_with_role("editor");
+    editor.store(&app.db_pool).await;
+    app.login_as(&editor).await;
+
+    // Act
+    let import = app
+        .post_import("confirmed", "email,name\nursula@example.com,Ursula\n")
+        .await;
+    let export = app.get_export("").await;
+
+    // Assert
+    assert_eq!(403, import.status().as_u16());
+    assert_eq!(403, export.status().as_u16());
+}
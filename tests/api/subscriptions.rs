@@ -109,6 +109,7 @@ async fn subscribe_sends_a_confirmation_email_for_valid_data() {
 
     // Act
     app.post_subscriptions(body).await;
+    app.send_all_confirmation_emails().await;
 
     // Assert
 }
@@ -127,6 +128,7 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() {
 
     // Act
     app.post_subscriptions(body).await;
+    app.send_all_confirmation_emails().await;
 
     // Assert
     let links = {
@@ -152,7 +154,9 @@ async fn subscribe_sends_a_confirmation_email_for_each_request() {
 
     // Act
     app.post_subscriptions(body).await;
+    app.send_all_confirmation_emails().await;
     app.post_subscriptions(body).await;
+    app.send_all_confirmation_emails().await;
 
     // Assert
 }
@@ -175,3 +179,60 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     // Assert
     assert_eq!(500, resp.status().as_u16());
 }
+
+#[tokio::test]
+async fn subscribe_succeeds_while_the_email_provider_is_down() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(matchers::any())
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let resp = app.post_subscriptions(body).await;
+
+    // Assert
+    assert_eq!(200, resp.status().as_u16());
+    let n_queued = sqlx::query_scalar!("SELECT COUNT(*) FROM confirmation_email_outbox")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(Some(1), n_queued);
+}
+
+#[tokio::test]
+async fn confirmation_emails_are_retried_until_they_are_sent() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    Mock::given(matchers::path("/email"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(matchers::path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.send_all_confirmation_emails().await;
+
+    // Assert
+    let n_queued = sqlx::query_scalar!("SELECT COUNT(*) FROM confirmation_email_outbox")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(Some(0), n_queued);
+}
@@ -29,6 +29,7 @@ async fn the_link_returned_by_subscribe_returns_a_200_if_called() {
         .await;
 
     app.post_subscriptions(body).await;
+    app.send_all_confirmation_emails().await;
 
     let link = {
         let email_request = &app.email_server.received_requests().await.unwrap()[0];
@@ -55,6 +56,7 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
         .await;
 
     app.post_subscriptions(body).await;
+    app.send_all_confirmation_emails().await;
 
     let link = {
         let email_request = &app.email_server.received_requests().await.unwrap()[0];
@@ -92,6 +94,7 @@ async fn clicking_on_the_link_twice_returns_401() {
         .await;
 
     app.post_subscriptions(body).await;
+    app.send_all_confirmation_emails().await;
 
     let link = {
         let email_request = &app.email_server.received_requests().await.unwrap()[0];
@@ -130,3 +133,113 @@ async fn a_link_with_a_non_existing_token_returns_401() {
     // Assert
     assert_eq!(401, resp.status().as_u16());
 }
+
+#[tokio::test]
+async fn an_expired_link_returns_410_with_a_resend_button() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body).await;
+    app.send_all_confirmation_emails().await;
+
+    let link = {
+        let email_request = &app.email_server.received_requests().await.unwrap()[0];
+        app.get_confirmation_links(email_request).html
+    };
+    app.expire_subscription_tokens().await;
+
+    // Act
+    let response = reqwest::get(link).await.unwrap();
+
+    // Assert
+    assert_eq!(410, response.status().as_u16());
+    let html = response.text().await.unwrap();
+    assert!(html.contains("This confirmation link has expired."));
+    assert!(html.contains(r#"action="/subscriptions/confirm/resend""#));
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn resending_an_expired_link_sends_a_new_link_that_confirms_the_subscriber() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body).await;
+    app.send_all_confirmation_emails().await;
+
+    let old_link = {
+        let email_request = &app.email_server.received_requests().await.unwrap()[0];
+        app.get_confirmation_links(email_request).html
+    };
+    app.expire_subscription_tokens().await;
+    let token = old_link
+        .query_pairs()
+        .find(|(k, _)| k == "token")
+        .unwrap()
+        .1
+        .into_owned();
+
+    // Act - Part 1 - Ask for a new link
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions/confirm/resend", app.base_addr))
+        .form(&[("token", &token)])
+        .send()
+        .await
+        .unwrap();
+    app.send_all_confirmation_emails().await;
+    assert_eq!(200, response.status().as_u16());
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("We have sent you a new confirmation link"));
+
+    // Act - Part 2 - Follow the new link
+    let new_link = {
+        let email_request = &app.email_server.received_requests().await.unwrap()[1];
+        app.get_confirmation_links(email_request).html
+    };
+    assert_ne!(old_link, new_link);
+    reqwest::get(new_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+impl TestApp {
+    async fn expire_subscription_tokens(&self) {
+        sqlx::query!("UPDATE subscription_tokens SET expires_at = now() - interval '1 minute'")
+            .execute(&self.db_pool)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+use crate::helpers::{self, TestApp};
+use secrecy::SecretString;
+use uuid::Uuid;
+use wiremock::{matchers, Mock};
+use zero2prod::domain::UnsubscribeToken;
+
+impl TestApp {
+    /// Publish an issue to every confirmed subscriber and deliver it right away.
+    async fn publish_and_deliver_an_issue(&self) {
+        let resp = self
+            .post_newsletters(&serde_json::json!({
+                "title": "Newsletter title",
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+                "idempotency_key": Uuid::new_v4(),
+            }))
+            .await;
+        helpers::assert_redirects_to(&resp, "/admin/newsletters");
+        self.dispatch_all_pending_emails().await;
+    }
+}
+
+#[tokio::test]
+async fn unsubscribe_requests_without_a_token_are_rejected_with_a_400() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let resp = app
+        .api_client
+        .post(format!("{}/subscriptions/unsubscribe", app.base_addr))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn unsubscribe_requests_with_a_forged_token_are_rejected_with_a_401() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let subscriber_id = Uuid::new_v4();
+    let token = UnsubscribeToken::sign(subscriber_id, &SecretString::from("forged-secret"));
+
+    // Act
+    let resp = app
+        .api_client
+        .post(format!(
+            "{}/subscriptions/unsubscribe?subscriber_id={}&token={}",
+            app.base_addr,
+            subscriber_id,
+            token.as_ref()
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(401, resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn newsletter_issues_carry_a_one_click_unsubscribe_link() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+
+    Mock::given(matchers::path("/email/batch"))
+        .and(matchers::method("POST"))
+        .respond_with(helpers::batch_delivered)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.publish_and_deliver_an_issue().await;
+
+    // Assert
+    let email_request = app.email_server.received_requests().await.unwrap().pop();
+    let link = app.get_unsubscribe_link(&email_request.unwrap());
+    let resp = app.api_client.get(link).send().await.unwrap();
+    assert_eq!(200, resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn the_one_click_link_unsubscribes_the_subscriber() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+
+    Mock::given(matchers::path("/email/batch"))
+        .and(matchers::method("POST"))
+        .respond_with(helpers::batch_delivered)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_and_deliver_an_issue().await;
+    let link = {
+        let email_request = app.email_server.received_requests().await.unwrap().pop();
+        app.get_unsubscribe_link(&email_request.unwrap())
+    };
+
+    // Act
+    let resp = app
+        .api_client
+        .post(link)
+        .form(&[("List-Unsubscribe", "One-Click")])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(200, resp.status().as_u16());
+    let saved = sqlx::query!("SELECT status, unsubscribed_at FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, "unsubscribed");
+    assert!(saved.unsubscribed_at.is_some());
+
+    // Further issues are not delivered to them
+    app.publish_and_deliver_an_issue().await;
+}
+
+#[tokio::test]
+async fn showing_the_unsubscribe_form_does_not_unsubscribe() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.create_confirmed_subscriber().await;
+    app.login_as_test_user().await;
+
+    Mock::given(matchers::path("/email/batch"))
+        .and(matchers::method("POST"))
+        .respond_with(helpers::batch_delivered)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_and_deliver_an_issue().await;
+    let link = {
+        let email_request = app.email_server.received_requests().await.unwrap().pop();
+        app.get_unsubscribe_link(&email_request.unwrap())
+    };
+
+    // Act
+    let html = app
+        .api_client
+        .get(link)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html.contains("Do you want to stop receiving our newsletter?"));
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, "confirmed");
+}
@@ -0,0 +1,244 @@
+use crate::helpers::{self, TestApp, RQST_FAIL};
+use linkify::{LinkFinder, LinkKind};
+use reqwest::{Method, Url};
+use uuid::Uuid;
+use wiremock::{matchers, Mock};
+
+const ARTICLE_URL: &str = "https://example.com/article?id=1&ref=newsletter";
+
+impl TestApp {
+    /// Publishes an issue linking to `ARTICLE_URL` to a single confirmed subscriber
+    /// and returns its id along with the HTML body of the delivered email.
+    async fn publish_an_issue_with_a_link(&self, track: bool) -> (Uuid, String) {
+        self.create_confirmed_subscriber().await;
+        self.login_as_test_user().await;
+        Mock::given(matchers::path("/email/batch"))
+            .and(matchers::method("POST"))
+            .respond_with(helpers::batch_delivered)
+            .expect(1)
+            .mount(&self.email_server)
+            .await;
+
+        let mut form = vec![
+            ("title", "Newsletter title".to_string()),
+            ("text", "Newsletter body as plain text".to_string()),
+            (
+                "html",
+                format!(
+                    r#"<p>Read <a href="{}">the article</a></p>"#,
+                    ARTICLE_URL.replace('&', "&amp;")
+                ),
+            ),
+            ("idempotency_key", Uuid::new_v4().to_string()),
+        ];
+        if track {
+            form.push(("track", "true".to_string()));
+        }
+        let resp = self.post_newsletters(&form).await;
+        helpers::assert_redirects_to(&resp, "/admin/newsletters");
+        self.dispatch_all_pending_emails().await;
+
+        let email_request = self.email_server.received_requests().await.unwrap().pop();
+        let body: serde_json::Value = email_request.unwrap().body_json().unwrap();
+        let issue_id = sqlx::query_scalar!("SELECT id FROM newsletter_issues")
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap();
+        (issue_id, body[0]["HtmlBody"].as_str().unwrap().to_owned())
+    }
+
+    /// Finds the link to the given tracking endpoint in an email body.
+    fn get_tracking_link(&self, html: &str, path: &str) -> Url {
+        let mut url = LinkFinder::new()
+            .kinds(&[LinkKind::Url])
+            .links(html)
+            .map(|l| Url::parse(&l.as_str().replace("&amp;", "&")).unwrap())
+            .find(|u| u.path() == path)
+            .unwrap();
+        assert_eq!(url.host_str().unwrap(), "127.0.0.1");
+        url.set_port(Some(self.socket_addr.port())).unwrap();
+        url
+    }
+
+    async fn count_tracking_events(&self, kind: &str) -> i64 {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "n!" FROM tracking_events WHERE kind = $1"#,
+            kind
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .unwrap()
+    }
+}
+
+#[tokio::test]
+async fn issues_are_not_tracked_by_default() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let (_, html) = app.publish_an_issue_with_a_link(false).await;
+
+    // Assert
+    assert!(!html.contains("/track/"));
+    assert!(html.contains(&ARTICLE_URL.replace('&', "&amp;")));
+}
+
+#[tokio::test]
+async fn loading_the_pixel_records_an_open() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let (_, html) = app.publish_an_issue_with_a_link(true).await;
+    let pixel = app.get_tracking_link(&html, "/track/open");
+
+    // Act
+    let resp = app.api_client.get(pixel).send().await.expect(RQST_FAIL);
+
+    // Assert
+    assert_eq!(200, resp.status().as_u16());
+    assert_eq!("image/gif", resp.headers()["Content-Type"]);
+    assert_eq!(1, app.count_tracking_events("open").await);
+}
+
+#[tokio::test]
+async fn clicking_a_link_records_the_click_and_redirects_to_it() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let (_, html) = app.publish_an_issue_with_a_link(true).await;
+    let link = app.get_tracking_link(&html, "/track/click");
+
+    // Act
+    let resp = app.api_client.get(link).send().await.expect(RQST_FAIL);
+
+    // Assert
+    assert_eq!(302, resp.status().as_u16());
+    assert_eq!(ARTICLE_URL, resp.headers()["Location"]);
+    assert_eq!(1, app.count_tracking_events("click").await);
+}
+
+#[tokio::test]
+async fn repeated_opens_are_recorded_once() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let (_, html) = app.publish_an_issue_with_a_link(true).await;
+    let pixel = app.get_tracking_link(&html, "/track/open");
+
+    // Act
+    for _ in 0..2 {
+        let resp = app
+            .api_client
+            .get(pixel.clone())
+            .send()
+            .await
+            .expect(RQST_FAIL);
+        assert_eq!(200, resp.status().as_u16());
+    }
+
+    // Assert
+    assert_eq!(1, app.count_tracking_events("open").await);
+}
+
+#[tokio::test]
+async fn tracking_events_are_deleted_with_their_subscriber() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let (_, html) = app.publish_an_issue_with_a_link(true).await;
+    let pixel = app.get_tracking_link(&html, "/track/open");
+    app.api_client.get(pixel).send().await.expect(RQST_FAIL);
+    let subscriber_id = sqlx::query_scalar!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    let key = app.create_api_key().await;
+
+    // Act
+    let resp = app
+        .api_request(
+            Method::DELETE,
+            &format!("/subscribers/{}", subscriber_id),
+            &key,
+        )
+        .send()
+        .await
+        .expect(RQST_FAIL);
+
+    // Assert
+    assert_eq!(204, resp.status().as_u16());
+    assert_eq!(0, app.count_tracking_events("open").await);
+}
+
+#[tokio::test]
+async fn click_links_cannot_redirect_elsewhere() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let (_, html) = app.publish_an_issue_with_a_link(true).await;
+    let mut link = app.get_tracking_link(&html, "/track/click");
+    let query: Vec<(String, String)> = link
+        .query_pairs()
+        .map(|(k, v)| match k.as_ref() {
+            "url" => (k.into_owned(), "https://evil.example.com".to_string()),
+            _ => (k.into_owned(), v.into_owned()),
+        })
+        .collect();
+    link.query_pairs_mut().clear().extend_pairs(query);
+
+    // Act
+    let resp = app.api_client.get(link).send().await.expect(RQST_FAIL);
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+    assert_eq!(0, app.count_tracking_events("click").await);
+}
+
+#[tokio::test]
+async fn forged_delivery_ids_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let forged = format!(
+        "{}{}.c2lnbmF0dXJl",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    );
+
+    // Act
+    let resp = app
+        .api_client
+        .get(format!("{}/track/open", app.base_addr))
+        .query(&[("d", forged)])
+        .send()
+        .await
+        .expect(RQST_FAIL);
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+    assert_eq!(0, app.count_tracking_events("open").await);
+}
+
+#[tokio::test]
+async fn the_issue_page_shows_open_and_click_rates() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let (issue_id, html) = app.publish_an_issue_with_a_link(true).await;
+    let link = app.get_tracking_link(&html, "/track/click");
+
+    // Act
+    app.api_client.get(link).send().await.expect(RQST_FAIL);
+
+    // Assert
+    let html = app.get_admin_issue(issue_id).await.text().await.unwrap();
+    assert!(html.contains("<li>Opened: 1 (100.0%)</li>"));
+    assert!(html.contains("<li>Clicked: 1 (100.0%)</li>"));
+}
+
+#[tokio::test]
+async fn untracked_issues_do_not_show_rates() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let (issue_id, _) = app.publish_an_issue_with_a_link(false).await;
+
+    // Assert
+    let html = app.get_admin_issue(issue_id).await.text().await.unwrap();
+    assert!(!html.contains("Opened:"));
+}
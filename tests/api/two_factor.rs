@@ -0,0 +1,215 @@
+use crate::helpers::{assert_redirects_to, TestApp, RQST_FAIL};
+use reqwest::Response;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+fn current_code(secret: &str) -> String {
+    let secret = Secret::Encoded(secret.to_owned()).to_bytes().unwrap();
+    TOTP::new(Algorithm::SHA1, 6, 1, 30, secret, None, "test".into())
+        .unwrap()
+        .generate_current()
+        .unwrap()
+}
+
+impl TestApp {
+    async fn post_second_factor(&self, code: &str) -> Response {
+        self.api_client
+            .post(format!("{}/login/two-factor", self.base_addr))
+            .form(&serde_json::json!({ "code": code }))
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    /// Enrolls the test user, returning their TOTP secret and recovery codes.
+    async fn enable_two_factor(&self) -> (String, Vec<String>) {
+        let html = self.get_security_html().await;
+        let secret = extract_between(&html, "<code>", "</code>")[0].clone();
+
+        let resp = self
+            .post_security(
+                "/totp",
+                &serde_json::json!({ "code": current_code(&secret) }),
+            )
+            .await;
+        assert_eq!(200, resp.status().as_u16());
+        let recovery_codes = extract_between(&resp.text().await.unwrap(), "<code>", "</code>");
+        (secret, recovery_codes)
+    }
+
+    async fn login_with_password(&self) -> Response {
+        self.post_login(&serde_json::json!({
+            "username": &self.test_user.username,
+            "password": &self.test_user.password,
+        }))
+        .await
+    }
+}
+
+fn extract_between(s: &str, start: &str, end: &str) -> Vec<String> {
+    s.split(start)
+        .skip(1)
+        .map(|part| part.split(end).next().unwrap().to_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn enabling_two_factor_shows_ten_recovery_codes() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+
+    // Act
+    let (_, recovery_codes) = app.enable_two_factor().await;
+
+    // Assert
+    assert_eq!(10, recovery_codes.len());
+    let html = app.get_security_html().await;
+    assert!(html.contains("Two-factor authentication is enabled."));
+}
+
+#[tokio::test]
+async fn enrollment_requires_a_valid_code() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    app.get_security_html().await;
+
+    // Act
+    let resp = app
+        .post_security("/totp", &serde_json::json!({ "code": "000000" }))
+        .await;
+
+    // Assert
+    assert_redirects_to(&resp, "/admin/security");
+    let html = app.get_security_html().await;
+    assert!(html.contains("The code is incorrect"));
+    assert!(html.contains("Two-factor authentication is disabled."));
+}
+
+#[tokio::test]
+async fn the_password_alone_does_not_log_in_a_user_with_two_factor() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    app.enable_two_factor().await;
+    app.post_logout().await;
+
+    // Act
+    let resp = app.login_with_password().await;
+
+    // Assert
+    assert_redirects_to(&resp, "/login/two-factor");
+    assert_redirects_to(&app.get_admin_dashboard().await, "/login");
+}
+
+#[tokio::test]
+async fn a_valid_code_completes_the_login() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let (secret, _) = app.enable_two_factor().await;
+    app.post_logout().await;
+    app.login_with_password().await;
+
+    // Act
+    let resp = app.post_second_factor(&current_code(&secret)).await;
+
+    // Assert
+    assert_redirects_to(&resp, "/admin/dashboard");
+    assert_eq!(200, app.get_admin_dashboard().await.status().as_u16());
+}
+
+#[tokio::test]
+async fn an_invalid_code_is_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    app.enable_two_factor().await;
+    app.post_logout().await;
+    app.login_with_password().await;
+
+    // Act
+    let resp = app.post_second_factor("000000").await;
+
+    // Assert
+    assert_redirects_to(&resp, "/login/two-factor");
+    assert_redirects_to(&app.get_admin_dashboard().await, "/login");
+}
+
+#[tokio::test]
+async fn a_code_cannot_be_used_twice() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let (secret, _) = app.enable_two_factor().await;
+    app.post_logout().await;
+    let code = current_code(&secret);
+
+    // Act - Part 1 - Use the code
+    app.login_with_password().await;
+    let resp = app.post_second_factor(&code).await;
+    assert_redirects_to(&resp, "/admin/dashboard");
+    app.post_logout().await;
+
+    // Act - Part 2 - Replay it
+    app.login_with_password().await;
+    let resp = app.post_second_factor(&code).await;
+
+    // Assert
+    assert_redirects_to(&resp, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn recovery_codes_can_only_be_used_once() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let (_, recovery_codes) = app.enable_two_factor().await;
+    app.post_logout().await;
+
+    // Act - Part 1 - Use a recovery code
+    app.login_with_password().await;
+    let resp = app.post_second_factor(&recovery_codes[0]).await;
+    assert_redirects_to(&resp, "/admin/dashboard");
+    app.post_logout().await;
+
+    // Act - Part 2 - Use it again
+    app.login_with_password().await;
+    let resp = app.post_second_factor(&recovery_codes[0]).await;
+
+    // Assert
+    assert_redirects_to(&resp, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn the_second_step_requires_a_verified_password() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let resp = app.post_second_factor("000000").await;
+
+    // Assert
+    assert_redirects_to(&resp, "/login");
+}
+
+#[tokio::test]
+async fn two_factor_can_be_disabled() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let (secret, _) = app.enable_two_factor().await;
+
+    // Act
+    let resp = app
+        .post_security(
+            "/totp/disable",
+            &serde_json::json!({ "code": current_code(&secret) }),
+        )
+        .await;
+
+    // Assert
+    assert_redirects_to(&resp, "/admin/security");
+    app.post_logout().await;
+    assert_redirects_to(&app.login_with_password().await, "/admin/dashboard");
+}
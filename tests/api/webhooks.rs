@@ -0,0 +1,260 @@
+use crate::helpers::{TestApp, RQST_FAIL};
+use reqwest::{Method, Response};
+use serde_json::{json, Value};
+use uuid::Uuid;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+/// The webhook credentials of `config/Base.toml`.
+const USERNAME: &str = "postmark";
+const PASSWORD: &str = "my-webhook-secret";
+
+impl TestApp {
+    async fn post_postmark_webhook(&self, payload: &Value, password: &str) -> Response {
+        self.api_client
+            .post(format!("{}/webhooks/postmark", self.base_addr))
+            .basic_auth(USERNAME, Some(password))
+            .json(payload)
+            .send()
+            .await
+            .expect(RQST_FAIL)
+    }
+
+    async fn report(&self, record_type: &str, kind: &str, email: &str) {
+        let payload = json!({ "RecordType": record_type, "Type": kind, "Email": email });
+        let resp = self.post_postmark_webhook(&payload, PASSWORD).await;
+        assert_eq!(200, resp.status().as_u16());
+    }
+
+    /// Creates a confirmed subscriber through the API and returns their id.
+    async fn create_subscriber(&self, key: &str, email: &str) -> Uuid {
+        let body: Value = self
+            .api_request(Method::POST, "/subscribers", key)
+            .json(&json!({ "email": email, "name": "Ursula", "confirmed": true }))
+            .send()
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        body["id"].as_str().unwrap().parse().unwrap()
+    }
+}
+
+#[tokio::test]
+async fn webhook_requests_without_valid_credentials_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let payload = json!({
+        "RecordType": "Bounce",
+        "Type": "HardBounce",
+        "Email": "ursula@example.com",
+    });
+
+    // Act
+    let anonymous = app
+        .api_client
+        .post(format!("{}/webhooks/postmark", app.base_addr))
+        .json(&payload)
+        .send()
+        .await
+        .expect(RQST_FAIL);
+    let wrong_password = app.post_postmark_webhook(&payload, "not-the-secret").await;
+
+    // Assert
+    for resp in [anonymous, wrong_password] {
+        assert_eq!(401, resp.status().as_u16());
+        assert_eq!(
+            r#"Basic realm="webhooks""#,
+            resp.headers()["WWW-Authenticate"]
+        );
+    }
+    let n: i64 = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "n!" FROM suppressed_emails"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, n);
+}
+
+#[tokio::test]
+async fn malformed_payloads_are_rejected() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    // Act
+    let resp = app
+        .post_postmark_webhook(&json!({ "RecordType": "Bounce" }), PASSWORD)
+        .await;
+
+    // Assert
+    assert_eq!(400, resp.status().as_u16());
+}
+
+#[tokio::test]
+async fn hard_bounces_stop_deliveries_to_the_subscriber() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let key = app.create_api_key().await;
+    app.create_subscriber(&key, "bounced@example.com").await;
+    app.create_subscriber(&key, "ok@example.com").await;
+
+    // Act
+    app.report("Bounce", "HardBounce", "bounced@example.com")
+        .await;
+
+    // Assert
+    assert_eq!(
+        Some("bounced"),
+        app.subscriber_status("bounced@example.com")
+            .await
+            .as_deref()
+    );
+    let recipients = app.publish_and_get_recipients(&[]).await;
+    assert_eq!(vec!["ok@example.com"], recipients);
+}
+
+#[tokio::test]
+async fn spam_complaints_mark_the_subscriber_as_complained() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    app.create_subscriber(&key, "ursula@example.com").await;
+
+    // Act
+    app.report("SpamComplaint", "SpamComplaint", "ursula@example.com")
+        .await;
+
+    // Assert
+    assert_eq!(
+        Some("complained"),
+        app.subscriber_status("ursula@example.com").await.as_deref()
+    );
+}
+
+#[tokio::test]
+async fn soft_bounces_are_acknowledged_and_ignored() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let key = app.create_api_key().await;
+    app.create_subscriber(&key, "ursula@example.com").await;
+
+    // Act
+    app.report("Bounce", "SoftBounce", "ursula@example.com")
+        .await;
+
+    // Assert
+    assert_eq!(
+        Some("confirmed"),
+        app.subscriber_status("ursula@example.com").await.as_deref()
+    );
+}
+
+#[tokio::test]
+async fn suppressed_addresses_are_not_sent_confirmation_emails() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.report("Bounce", "HardBounce", "ursula@example.com")
+        .await;
+    Mock::given(matchers::path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let resp = app
+        .post_subscriptions("name=le%20guin&email=ursula%40example.com")
+        .await;
+    app.send_all_confirmation_emails().await;
+
+    // Assert
+    assert_eq!(200, resp.status().as_u16());
+    let n: i64 = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "n!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, n);
+}
+
+#[tokio::test]
+async fn queued_confirmation_emails_are_not_sent_to_addresses_suppressed_since() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let key = app.create_api_key().await;
+    Mock::given(matchers::path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.api_request(Method::POST, "/subscribers", &key)
+        .json(&json!({ "email": "ursula@example.com", "name": "Ursula" }))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    app.report("Bounce", "HardBounce", "ursula@example.com")
+        .await;
+    app.send_all_confirmation_emails().await;
+
+    // Assert
+    let n_queued: i64 =
+        sqlx::query_scalar!(r#"SELECT COUNT(*) AS "n!" FROM confirmation_email_outbox"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(0, n_queued);
+}
+
+#[tokio::test]
+async fn suppressed_addresses_cannot_be_confirmed_again() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let key = app.create_api_key().await;
+    let id = app.create_subscriber(&key, "ursula@example.com").await;
+    app.report("Bounce", "HardBounce", "ursula@example.com")
+        .await;
+
+    // Act
+    let resp = app
+        .api_request(Method::POST, &format!("/subscribers/{}/confirm", id), &key)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(409, resp.status().as_u16());
+    assert_eq!(
+        Some("bounced"),
+        app.subscriber_status("ursula@example.com").await.as_deref()
+    );
+    assert!(app.publish_and_get_recipients(&[]).await.is_empty());
+}
+
+#[tokio::test]
+async fn suppressed_addresses_cannot_be_added_through_the_api() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    app.login_as_test_user().await;
+    let key = app.create_api_key().await;
+    app.report("SpamComplaint", "SpamComplaint", "ursula@example.com")
+        .await;
+
+    // Act
+    let resp = app
+        .api_request(Method::POST, "/subscribers", &key)
+        .json(&json!({ "email": "ursula@example.com", "name": "Ursula", "confirmed": true }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(409, resp.status().as_u16());
+    assert_eq!(None, app.subscriber_status("ursula@example.com").await);
+}
@@ -1,7 +1,9 @@
 use crate::helpers::TestApp;
 use chrono::{Local, TimeDelta};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
-use zero2prod::workers::expiration;
+use zero2prod::workers::{expiration, issue_delivery};
 
 #[tokio::test]
 async fn experied_idempotency_keys_are_deleted() {
@@ -35,7 +37,106 @@ async fn valid_idempotency_keys_are_not_deleted() {
     assert_eq!(1, rows_found);
 }
 
+#[tokio::test]
+async fn stale_unconfirmed_subscriptions_are_deleted() {
+    // Arrange
+    let app = TestApp::spawn().await;
+
+    app.insert_a_pending_subscriber(30, 20).await;
+    let resent = app.insert_a_pending_subscriber(30, 1).await;
+
+    // Act
+    expiration::delete_unconfirmed(&app.db_pool, 7)
+        .await
+        .unwrap();
+
+    // Assert
+    let ids: Vec<Uuid> = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+
+    assert_eq!(vec![resent], ids);
+}
+
+#[tokio::test]
+async fn idle_workers_stop_as_soon_as_a_shutdown_is_requested() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let shutdown = CancellationToken::new();
+    let worker = tokio::spawn(app.delivery_worker.finish(shutdown.clone()));
+    // Let the worker find the queue empty and go to sleep
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Act
+    shutdown.cancel();
+
+    // Assert
+    let outcome = tokio::time::timeout(Duration::from_secs(1), worker)
+        .await
+        .expect("The worker did not stop in time.");
+    assert!(outcome.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn workers_record_a_heartbeat_while_running() {
+    // Arrange
+    let app = TestApp::spawn().await;
+    let shutdown = CancellationToken::new();
+
+    // Act
+    let worker = tokio::spawn(app.delivery_worker.finish(shutdown.clone()));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    shutdown.cancel();
+    worker.await.unwrap().unwrap();
+
+    // Assert
+    let n_beats: i64 = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "n!" FROM worker_heartbeats WHERE worker = $1"#,
+        issue_delivery::NAME
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(1, n_beats);
+}
+
 impl TestApp {
+    /// Inserts a pending subscriber whose confirmation token expired `expired_days` ago.
+    async fn insert_a_pending_subscriber(&self, subscribed_days: i64, expired_days: i64) -> Uuid {
+        let id = Uuid::new_v4();
+        let now = Local::now();
+        sqlx::query!(
+            r#"
+            INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+            VALUES ($1, $2, 'pending', $3, 'pending_confirmation')
+            "#,
+            id,
+            format!("{}@example.com", id),
+            now - TimeDelta::days(subscribed_days)
+        )
+        .execute(&self.db_pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            r#"
+            INSERT INTO subscription_tokens (subscriber_id, token, created_at, expires_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            id,
+            Uuid::new_v4().simple().to_string(),
+            now - TimeDelta::days(expired_days + 2),
+            now - TimeDelta::days(expired_days)
+        )
+        .execute(&self.db_pool)
+        .await
+        .unwrap();
+        id
+    }
+
     async fn insert_a_new_idempotency_key(&self, sub_hours: i64) {
         let created_at = Local::now()
             .checked_sub_signed(TimeDelta::hours(sub_hours))